@@ -0,0 +1,190 @@
+use std::{fmt, io};
+
+use wp_model_core::model::DataRecord;
+
+use crate::Json;
+use crate::error::FormatError;
+use crate::formatter::{IoSink, render};
+
+/// JSON 批量输出的排列方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsonLayout {
+    /// NDJSON：每行一条记录，记录之间以 `\n` 分隔
+    #[default]
+    Lines,
+    /// 单个 JSON 数组文档
+    Array,
+}
+
+/// 多条记录的 JSON 批量写出器
+///
+/// 接受 `&[DataRecord]` 或任意 `Iterator<Item = &DataRecord>`。`write_batch` 逐条写入
+/// `io::Write`，不会把整批结果保存在内存中，适合大批量导出（建议配合 `BufWriter`）。
+///
+/// NDJSON 总是单行输出，忽略 `Json::pretty`；数组文档会沿用该设置。
+///
+/// ```
+/// use wp_data_fmt::{Json, JsonBatch};
+///
+/// let ndjson = JsonBatch::lines(Json::default()).with_trailing_newline(true);
+/// let array = JsonBatch::array(Json::pretty(2));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct JsonBatch {
+    json: Json,
+    layout: JsonLayout,
+    trailing_newline: bool,
+}
+
+impl JsonBatch {
+    pub fn new(json: Json, layout: JsonLayout) -> Self {
+        Self {
+            json,
+            layout,
+            trailing_newline: false,
+        }
+    }
+
+    /// NDJSON 批量输出
+    pub fn lines(json: Json) -> Self {
+        Self::new(json, JsonLayout::Lines)
+    }
+
+    /// JSON 数组批量输出
+    pub fn array(json: Json) -> Self {
+        Self::new(json, JsonLayout::Array)
+    }
+
+    /// 在输出末尾追加 `\n`；空批次的 NDJSON 不输出任何内容
+    pub fn with_trailing_newline(mut self, trailing_newline: bool) -> Self {
+        self.trailing_newline = trailing_newline;
+        self
+    }
+
+    pub fn layout(&self) -> JsonLayout {
+        self.layout
+    }
+
+    pub fn format_batch<'a>(&self, records: impl IntoIterator<Item = &'a DataRecord>) -> String {
+        self.try_format_batch(records).unwrap_or_default()
+    }
+
+    pub fn try_format_batch<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a DataRecord>,
+    ) -> Result<String, FormatError> {
+        render(|out| self.write_batch_fmt(records, out))
+    }
+
+    /// 逐条写出记录
+    pub fn write_batch_fmt<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a DataRecord>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        match self.layout {
+            JsonLayout::Lines => {
+                let mut empty = true;
+                for (i, record) in records.into_iter().enumerate() {
+                    if i > 0 {
+                        out.write_char('\n')?;
+                    }
+                    self.json.write_record_compact(record, out)?;
+                    empty = false;
+                }
+                if self.trailing_newline && !empty {
+                    out.write_char('\n')?;
+                }
+            }
+            JsonLayout::Array => {
+                self.json.layout(out, |out| {
+                    out.write_char('[')?;
+                    for (i, record) in records.into_iter().enumerate() {
+                        if i > 0 {
+                            out.write_char(',')?;
+                        }
+                        self.json.write_record_compact(record, out)?;
+                    }
+                    out.write_char(']')?;
+                    Ok(())
+                })?;
+                if self.trailing_newline {
+                    out.write_char('\n')?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 流式写入 `io::Write`
+    pub fn write_batch<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a DataRecord>,
+        out: &mut dyn io::Write,
+    ) -> Result<(), FormatError> {
+        let mut sink = IoSink::new(out);
+        self.write_batch_fmt(records, &mut sink)
+            .map_err(|e| sink.map_error(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wp_model_core::model::{DataField, FieldStorage};
+
+    fn make_records() -> Vec<DataRecord> {
+        (1..=3)
+            .map(|n| DataRecord {
+                id: Default::default(),
+                items: vec![FieldStorage::from_owned(DataField::from_digit("n", n))],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lines() {
+        let records = make_records();
+        let batch = JsonBatch::lines(Json::default());
+        assert_eq!(
+            batch.format_batch(&records),
+            "{\"n\":1}\n{\"n\":2}\n{\"n\":3}"
+        );
+        let batch = batch.with_trailing_newline(true);
+        assert_eq!(
+            batch.format_batch(records.iter().filter(|r| r.items.len() == 1).take(1)),
+            "{\"n\":1}\n"
+        );
+        assert_eq!(batch.format_batch(&[]), "");
+        // NDJSON 忽略美化设置
+        let batch = JsonBatch::lines(Json::pretty(2));
+        assert_eq!(batch.format_batch(&records[..1]), "{\"n\":1}");
+    }
+
+    #[test]
+    fn test_array() {
+        let records = make_records();
+        let batch = JsonBatch::array(Json::default());
+        assert_eq!(batch.format_batch(&records), r#"[{"n":1},{"n":2},{"n":3}]"#);
+        assert_eq!(batch.format_batch(&[]), "[]");
+        let batch = JsonBatch::array(Json::pretty(2)).with_trailing_newline(true);
+        assert_eq!(
+            batch.format_batch(&records[..2]),
+            "[\n  {\n    \"n\": 1\n  },\n  {\n    \"n\": 2\n  }\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_write_batch_io() {
+        let records = make_records();
+        let mut buf = Vec::new();
+        JsonBatch::lines(Json::default())
+            .with_trailing_newline(true)
+            .write_batch(&records, &mut buf)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "{\"n\":1}\n{\"n\":2}\n{\"n\":3}\n"
+        );
+    }
+}
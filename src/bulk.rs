@@ -0,0 +1,430 @@
+use std::{fmt, io};
+
+use wp_model_core::model::{DataRecord, Value};
+
+use crate::error::{ConfigError, FormatError};
+use crate::formatter::{IoSink, render};
+use crate::json::write_json_str;
+use crate::projected::lookup;
+use crate::template::Template;
+use crate::{Json, TypeStyle};
+
+/// `_bulk` 请求中每条记录的操作
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BulkAction {
+    /// 写入文档，已存在时覆盖
+    #[default]
+    Index,
+    /// 写入文档，已存在时失败
+    Create,
+    /// 以 `{"doc":..,"doc_as_upsert":true}` 更新文档，不存在时写入；需要 `_id`
+    Update,
+    /// 删除文档，只输出操作行；需要 `_id`
+    Delete,
+}
+
+impl BulkAction {
+    fn name(&self) -> &'static str {
+        match self {
+            BulkAction::Index => "index",
+            BulkAction::Create => "create",
+            BulkAction::Update => "update",
+            BulkAction::Delete => "delete",
+        }
+    }
+}
+
+/// Elasticsearch / OpenSearch `_bulk` 请求体写出器
+///
+/// 每条记录输出一行操作元数据和一行文档（`Delete` 无文档行），每行以 `\n` 结尾。
+/// 文档使用 `Json` 的紧凑输出，忽略 `Json::pretty`；文档必须是对象，
+/// 因此不接受 `TypeStyle::Triples`。
+///
+/// - `with_index`：`_index` 模板，如 `logs-{service}-{ts:%Y.%m.%d}`，语法见下文；
+///   未设置时省略 `_index`，由请求 URL 指定
+/// - `with_id_field`：取该字段的值作为 `_id`；`Index`/`Create` 时字段缺失则省略 `_id`
+/// - `with_max_bytes`：`format_batches` 按请求体字节数拆分批次
+///
+/// 模板中 `{path}` 引用字段值（路径规则同 `Projected`），`{path:pattern}` 以 strftime
+/// 模式格式化时间字段，`{{`/`}}` 输出字面括号。引用的字段缺失时返回
+/// `FormatError::MissingField`。渲染出的索引名转为小写，仍不符合 Elasticsearch 命名规则
+/// （含 `\ / * ? " < > | , # :` 或空格、以 `-`、`_`、`+` 开头、为 `.`/`..`、超过 255 字节）
+/// 时返回 `FormatError::Unrepresentable`。
+///
+/// ```
+/// use wp_data_fmt::{BulkAction, EsBulk, Json};
+///
+/// let bulk = EsBulk::new(BulkAction::Update)
+///     .with_json(Json::default().with_strict(true))
+///     .unwrap()
+///     .with_index("logs-{service}-{ts:%Y.%m.%d}")
+///     .unwrap()
+///     .with_id_field("event_id")
+///     .with_max_bytes(5 * 1024 * 1024);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EsBulk {
+    json: Json,
+    action: BulkAction,
+    index: Option<Template>,
+    id_field: Option<String>,
+    max_bytes: Option<usize>,
+}
+
+impl EsBulk {
+    pub fn new(action: BulkAction) -> Self {
+        Self {
+            action,
+            ..Self::default()
+        }
+    }
+
+    /// 文档的 JSON 选项；`TypeStyle::Triples` 会输出数组文档，返回错误
+    pub fn with_json(mut self, json: Json) -> Result<Self, ConfigError> {
+        if json.type_style() == TypeStyle::Triples {
+            return Err(ConfigError::InvalidOption {
+                kind: "es_bulk",
+                option: "json",
+                reason: "TypeStyle::Triples writes array documents, `_bulk` requires objects"
+                    .to_string(),
+            });
+        }
+        self.json = json;
+        Ok(self)
+    }
+
+    /// `_index` 模板；括号不匹配、时间模式不合法或字面文本含非法字符时返回错误
+    pub fn with_index(mut self, template: &str) -> Result<Self, ConfigError> {
+        let index = Template::parse(template, "es_bulk", "index")?;
+        if let Some(c) = index
+            .literals()
+            .flat_map(str::chars)
+            .find(|c| INVALID_INDEX_CHARS.contains(c))
+        {
+            return Err(ConfigError::InvalidOption {
+                kind: "es_bulk",
+                option: "index",
+                reason: format!("{:?} is not allowed in index names", c),
+            });
+        }
+        self.index = Some(index);
+        Ok(self)
+    }
+
+    pub fn with_id_field(mut self, field: impl Into<String>) -> Self {
+        self.id_field = Some(field.into());
+        self
+    }
+
+    /// 单个批次的最大字节数；单条记录超过上限时独占一个批次
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn action(&self) -> BulkAction {
+        self.action
+    }
+
+    pub fn format_batch<'a>(&self, records: impl IntoIterator<Item = &'a DataRecord>) -> String {
+        self.try_format_batch(records).unwrap_or_default()
+    }
+
+    pub fn try_format_batch<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a DataRecord>,
+    ) -> Result<String, FormatError> {
+        render(|out| self.write_batch_fmt(records, out))
+    }
+
+    /// 按 `with_max_bytes` 拆分为多个请求体；未设置上限时所有记录位于同一批次
+    pub fn format_batches<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a DataRecord>,
+    ) -> Result<Vec<String>, FormatError> {
+        let mut batches = Vec::new();
+        let mut current = String::new();
+        for record in records {
+            let entry = render(|out| self.write_entry(record, out))?;
+            if let Some(max) = self.max_bytes
+                && !current.is_empty()
+                && current.len() + entry.len() > max
+            {
+                batches.push(std::mem::take(&mut current));
+            }
+            current.push_str(&entry);
+        }
+        if !current.is_empty() {
+            batches.push(current);
+        }
+        Ok(batches)
+    }
+
+    /// 逐条写出操作行和文档行，不拆分批次
+    pub fn write_batch_fmt<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a DataRecord>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        records
+            .into_iter()
+            .try_for_each(|record| self.write_entry(record, out))
+    }
+
+    /// 流式写入 `io::Write`
+    pub fn write_batch<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a DataRecord>,
+        out: &mut dyn io::Write,
+    ) -> Result<(), FormatError> {
+        let mut sink = IoSink::new(out);
+        self.write_batch_fmt(records, &mut sink)
+            .map_err(|e| sink.map_error(e))
+    }
+
+    /// 写出一条记录的操作行和文档行
+    fn write_entry(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        write!(out, "{{\"{}\":{{", self.action.name())?;
+        let mut sep = "";
+        if let Some(index) = &self.index {
+            out.write_str("\"_index\":")?;
+            write_json_str(&index_name(index.render(record, "es_bulk")?)?, out)?;
+            sep = ",";
+        }
+        match (self.document_id(record)?, self.action) {
+            (Some(id), _) => {
+                write!(out, "{}\"_id\":", sep)?;
+                write_json_str(&id, out)?;
+            }
+            (None, BulkAction::Update | BulkAction::Delete) => {
+                return Err(FormatError::MissingField {
+                    format: "es_bulk",
+                    name: self.id_field.clone().unwrap_or_else(|| "_id".to_string()),
+                });
+            }
+            (None, BulkAction::Index | BulkAction::Create) => {}
+        }
+        out.write_str("}}\n")?;
+        match self.action {
+            BulkAction::Index | BulkAction::Create => {
+                self.json.write_record_compact(record, out)?;
+                out.write_char('\n')?;
+            }
+            BulkAction::Update => {
+                out.write_str("{\"doc\":")?;
+                self.json.write_record_compact(record, out)?;
+                out.write_str(",\"doc_as_upsert\":true}\n")?;
+            }
+            BulkAction::Delete => {}
+        }
+        Ok(())
+    }
+
+    fn document_id(&self, record: &DataRecord) -> Result<Option<String>, FormatError> {
+        let Some(name) = &self.id_field else {
+            return Ok(None);
+        };
+        Ok(match lookup(record, name).map(|f| f.get_value()) {
+            None | Some(Value::Null | Value::Ignore(_)) => None,
+            Some(Value::Chars(s)) => Some(s.to_string()),
+            Some(value) => Some(value.to_string()),
+        })
+    }
+}
+
+/// Elasticsearch 索引名中不允许出现的字符
+const INVALID_INDEX_CHARS: &[char] = &['\\', '/', '*', '?', '"', '<', '>', '|', ' ', ',', '#', ':'];
+
+/// 把渲染出的索引名转为小写并按 Elasticsearch 的命名规则校验
+fn index_name(rendered: String) -> Result<String, FormatError> {
+    let name = rendered.to_lowercase();
+    let problem = if name.is_empty() {
+        Some("must not be empty".to_string())
+    } else if name == "." || name == ".." {
+        Some("must not be `.` or `..`".to_string())
+    } else if name.starts_with(['-', '_', '+']) {
+        Some("must not start with `-`, `_` or `+`".to_string())
+    } else if name.len() > 255 {
+        Some("must not be longer than 255 bytes".to_string())
+    } else {
+        name.chars()
+            .find(|c| INVALID_INDEX_CHARS.contains(c))
+            .map(|c| format!("must not contain {:?}", c))
+    };
+    match problem {
+        Some(problem) => Err(FormatError::Unrepresentable {
+            format: "es_bulk",
+            detail: format!("index name {:?} {}", rendered, problem),
+        }),
+        None => Ok(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wp_model_core::model::{DataField, FieldStorage};
+
+    fn make_records() -> Vec<DataRecord> {
+        (1..=3)
+            .map(|n| DataRecord {
+                id: Default::default(),
+                items: vec![
+                    FieldStorage::from_owned(DataField::from_chars("event_id", format!("e{}", n))),
+                    FieldStorage::from_owned(DataField::from_chars("service", "web")),
+                    FieldStorage::from_owned(DataField::from_digit("n", n)),
+                ],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_index_and_create() {
+        let records = make_records();
+        let bulk = EsBulk::default();
+        assert_eq!(
+            bulk.format_batch(&records[..1]),
+            "{\"index\":{}}\n{\"event_id\":\"e1\",\"service\":\"web\",\"n\":1}\n"
+        );
+
+        let bulk = EsBulk::new(BulkAction::Create)
+            .with_index("logs-{service}")
+            .unwrap()
+            .with_id_field("event_id");
+        assert_eq!(
+            bulk.format_batch(&records[..2]),
+            concat!(
+                "{\"create\":{\"_index\":\"logs-web\",\"_id\":\"e1\"}}\n",
+                "{\"event_id\":\"e1\",\"service\":\"web\",\"n\":1}\n",
+                "{\"create\":{\"_index\":\"logs-web\",\"_id\":\"e2\"}}\n",
+                "{\"event_id\":\"e2\",\"service\":\"web\",\"n\":2}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_update_and_delete() {
+        let records = make_records();
+        let bulk = EsBulk::new(BulkAction::Update).with_id_field("n");
+        assert_eq!(
+            bulk.format_batch(&records[..1]),
+            concat!(
+                "{\"update\":{\"_id\":\"1\"}}\n",
+                "{\"doc\":{\"event_id\":\"e1\",\"service\":\"web\",\"n\":1},\"doc_as_upsert\":true}\n",
+            )
+        );
+
+        let bulk = EsBulk::new(BulkAction::Delete)
+            .with_index("logs")
+            .unwrap()
+            .with_id_field("event_id");
+        assert_eq!(
+            bulk.format_batch(&records[..1]),
+            "{\"delete\":{\"_index\":\"logs\",\"_id\":\"e1\"}}\n"
+        );
+
+        let err = EsBulk::new(BulkAction::Delete)
+            .with_id_field("missing")
+            .try_format_batch(&records)
+            .unwrap_err();
+        assert!(matches!(err, FormatError::MissingField { ref name, .. } if name == "missing"));
+    }
+
+    #[test]
+    fn test_missing_index_field() {
+        let err = EsBulk::default()
+            .with_index("logs-{host}")
+            .unwrap()
+            .try_format_batch(&make_records())
+            .unwrap_err();
+        assert!(matches!(err, FormatError::MissingField { ref name, .. } if name == "host"));
+        assert!(EsBulk::default().with_index("logs-{host").is_err());
+    }
+
+    #[test]
+    fn test_index_name_rules() {
+        let records = make_records();
+        let mut upper = records[0].clone();
+        upper.items[1] = FieldStorage::from_owned(DataField::from_chars("service", "Web"));
+        let bulk = EsBulk::default().with_index("Logs-{service}").unwrap();
+        assert!(
+            bulk.format_batch([&upper])
+                .starts_with("{\"index\":{\"_index\":\"logs-web\"}}\n")
+        );
+
+        for service in ["web api", "a/b", "", "x#1"] {
+            let mut record = records[0].clone();
+            record.items[1] = FieldStorage::from_owned(DataField::from_chars("service", service));
+            let err = EsBulk::default()
+                .with_index("{service}")
+                .unwrap()
+                .try_format_batch([&record])
+                .unwrap_err();
+            assert!(
+                matches!(
+                    err,
+                    FormatError::Unrepresentable {
+                        format: "es_bulk",
+                        ..
+                    }
+                ),
+                "{:?}",
+                service
+            );
+        }
+        let mut record = records[0].clone();
+        record.items[1] = FieldStorage::from_owned(DataField::from_chars("service", "_web"));
+        assert!(
+            EsBulk::default()
+                .with_index("{service}-logs")
+                .unwrap()
+                .try_format_batch([&record])
+                .is_err()
+        );
+
+        assert!(matches!(
+            EsBulk::default().with_index("logs {service}"),
+            Err(ConfigError::InvalidOption {
+                option: "index",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_rejects_array_documents() {
+        let triples = Json::default().with_type_style(TypeStyle::Triples);
+        assert!(matches!(
+            EsBulk::default().with_json(triples),
+            Err(ConfigError::InvalidOption { option: "json", .. })
+        ));
+        let wrappers = Json::default().with_type_style(TypeStyle::Wrappers);
+        assert!(EsBulk::default().with_json(wrappers).is_ok());
+    }
+
+    #[test]
+    fn test_split_by_bytes() {
+        let records = make_records();
+        let bulk = EsBulk::default();
+        let entry = bulk.format_batch(&records[..1]).len();
+
+        assert_eq!(bulk.format_batches(&records).unwrap().len(), 1);
+        assert!(bulk.format_batches(&[]).unwrap().is_empty());
+
+        let batches = bulk
+            .clone()
+            .with_max_bytes(entry * 2)
+            .format_batches(&records)
+            .unwrap();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0], bulk.format_batch(&records[..2]));
+        assert_eq!(batches[1], bulk.format_batch(&records[2..]));
+
+        let batches = bulk.with_max_bytes(1).format_batches(&records).unwrap();
+        assert_eq!(batches.len(), 3);
+    }
+}
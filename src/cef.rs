@@ -0,0 +1,702 @@
+use std::fmt;
+
+use chrono::FixedOffset;
+use wp_model_core::model::{DataRecord, FieldStorage, Value};
+
+use crate::Raw;
+use crate::error::{ConfigError, FormatError};
+use crate::flatten::{Collision, Flatten};
+use crate::formatter::{RecordFormatter, RecordWriter, ValueFormatter, ValueWriter, render};
+use crate::meta::{MetaValue, without_fields};
+use crate::projected::lookup;
+use crate::time::TimeFormat;
+
+/// 自定义字符串槽位 `cs1`~`cs6` 的数量
+const CUSTOM_STRINGS: usize = 6;
+
+/// 内置字典：常见字段名（小写）到 CEF 扩展键
+const DICTIONARY: &[(&str, &str)] = &[
+    ("src", "src"),
+    ("src_ip", "src"),
+    ("source_ip", "src"),
+    ("client_ip", "src"),
+    ("dst", "dst"),
+    ("dst_ip", "dst"),
+    ("dest_ip", "dst"),
+    ("destination_ip", "dst"),
+    ("server_ip", "dst"),
+    ("spt", "spt"),
+    ("src_port", "spt"),
+    ("source_port", "spt"),
+    ("client_port", "spt"),
+    ("dpt", "dpt"),
+    ("dst_port", "dpt"),
+    ("dest_port", "dpt"),
+    ("destination_port", "dpt"),
+    ("server_port", "dpt"),
+    ("shost", "shost"),
+    ("src_host", "shost"),
+    ("source_host", "shost"),
+    ("dhost", "dhost"),
+    ("dst_host", "dhost"),
+    ("dest_host", "dhost"),
+    ("smac", "smac"),
+    ("src_mac", "smac"),
+    ("dmac", "dmac"),
+    ("dst_mac", "dmac"),
+    ("suser", "suser"),
+    ("src_user", "suser"),
+    ("user", "suser"),
+    ("username", "suser"),
+    ("duser", "duser"),
+    ("dst_user", "duser"),
+    ("dvc", "dvc"),
+    ("device_ip", "dvc"),
+    ("dvchost", "dvchost"),
+    ("host", "dvchost"),
+    ("hostname", "dvchost"),
+    ("device_host", "dvchost"),
+    ("proto", "proto"),
+    ("protocol", "proto"),
+    ("app", "app"),
+    ("app_protocol", "app"),
+    ("act", "act"),
+    ("action", "act"),
+    ("outcome", "outcome"),
+    ("result", "outcome"),
+    ("reason", "reason"),
+    ("cat", "cat"),
+    ("category", "cat"),
+    ("msg", "msg"),
+    ("message", "msg"),
+    ("request", "request"),
+    ("url", "request"),
+    ("method", "requestMethod"),
+    ("http_method", "requestMethod"),
+    ("user_agent", "requestClientApplication"),
+    ("in", "in"),
+    ("bytes_in", "in"),
+    ("out", "out"),
+    ("bytes_out", "out"),
+    ("cnt", "cnt"),
+    ("count", "cnt"),
+    ("fname", "fname"),
+    ("file_name", "fname"),
+    ("file_path", "filePath"),
+    ("file_size", "fsize"),
+    ("rt", "rt"),
+    ("ts", "rt"),
+    ("time", "rt"),
+    ("timestamp", "rt"),
+    ("start", "start"),
+    ("start_time", "start"),
+    ("end", "end"),
+    ("end_time", "end"),
+];
+
+/// 取值为时间戳的扩展键，`Value::Time` 输出为 epoch 毫秒
+const TIME_KEYS: [&str; 4] = ["rt", "start", "end", "art"];
+
+/// ArcSight Common Event Format（CEF）
+///
+/// 每条记录输出一行
+/// `CEF:0|Vendor|Product|Version|SignatureID|Name|Severity|key=value key=value`。
+///
+/// - 头部各项取固定文本或字段；`SignatureID` 默认取字段 `signature_id`，`Name` 默认取
+///   字段 `name`。除 `Severity` 外取不到值时返回 `FormatError::MissingField`，作为来源的
+///   字段不再输出到扩展部分。
+/// - `Severity` 默认 `5`，可取 `0`~`10`、`Low`/`Medium`/`High`/`Very-High` 或常见的日志
+///   级别名称（`info`、`error` 等），其余输出 `Unknown`。
+/// - 其余字段按 `Flatten` 展开（展开后重名的字段追加 `_2` 等后缀），字段名经内置字典（如 `src_ip` → `src`、`dst_port` →
+///   `dpt`、`timestamp` → `rt`）或 `with_key` 映射为 CEF 键；未映射的字段依次放入
+///   `cs1`~`cs6`，并以 `cs1Label` 等记录字段名，槽位用完后以字段名（非字母数字替换为 `_`，
+///   为空或以数字开头时加 `field` 前缀）作为自定义键。同一个键只使用一次，重名的自定义键
+///   追加 `_2` 等后缀。
+/// - 头部中的 `\` 和 `|` 加反斜杠，换行替换为空格；扩展值中的 `\` 和 `=` 加反斜杠，
+///   换行写为 `\n`、`\r`。
+/// - `rt`、`start`、`end`、`art` 中的 `Value::Time` 输出为 epoch 毫秒，时区由 `with_time_offset`
+///   指定（默认 UTC）；其余值按 `Raw` 输出，空值字段省略。
+///
+/// ```
+/// use wp_data_fmt::{Cef, MetaValue};
+///
+/// let cef = Cef::new(
+///     MetaValue::text("Acme"),
+///     MetaValue::text("WAF"),
+///     MetaValue::text("1.0"),
+/// )
+/// .with_signature_id(MetaValue::field("rule_id"))
+/// .with_name(MetaValue::field("rule_name"))
+/// .with_severity(MetaValue::field("level"))
+/// .with_time_field("timestamp")
+/// .with_key("client", "src")
+/// .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cef {
+    vendor: MetaValue,
+    product: MetaValue,
+    version: MetaValue,
+    signature_id: MetaValue,
+    name: MetaValue,
+    severity: MetaValue,
+    keys: Vec<(String, String)>,
+    dictionary: bool,
+    time_field: Option<String>,
+    time: TimeFormat,
+    flatten: Flatten,
+    raw: Raw,
+}
+
+impl Cef {
+    pub fn new(vendor: MetaValue, product: MetaValue, version: MetaValue) -> Self {
+        Self {
+            vendor,
+            product,
+            version,
+            signature_id: MetaValue::field("signature_id"),
+            name: MetaValue::field("name"),
+            severity: MetaValue::text("5"),
+            keys: Vec::new(),
+            dictionary: true,
+            time_field: None,
+            time: TimeFormat::epoch_millis(),
+            flatten: Flatten::new().with_collision(Collision::Suffix),
+            raw: Raw::default(),
+        }
+    }
+
+    pub fn with_signature_id(mut self, signature_id: MetaValue) -> Self {
+        self.signature_id = signature_id;
+        self
+    }
+
+    pub fn with_name(mut self, name: MetaValue) -> Self {
+        self.name = name;
+        self
+    }
+
+    pub fn with_severity(mut self, severity: MetaValue) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// 将字段映射为 CEF 键，优先于内置字典；键须为字母和数字
+    pub fn with_key(
+        mut self,
+        field: impl Into<String>,
+        key: impl Into<String>,
+    ) -> Result<Self, ConfigError> {
+        let key = key.into();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(ConfigError::InvalidOption {
+                kind: "cef",
+                option: "key",
+                reason: format!("invalid extension key {:?}", key),
+            });
+        }
+        self.keys.push((field.into(), key));
+        Ok(self)
+    }
+
+    /// 是否使用内置字典，默认使用
+    pub fn with_dictionary(mut self, dictionary: bool) -> Self {
+        self.dictionary = dictionary;
+        self
+    }
+
+    /// 作为 `rt` 输出的 `Value::Time` 字段
+    pub fn with_time_field(mut self, field: impl Into<String>) -> Self {
+        self.time_field = Some(field.into());
+        self
+    }
+
+    /// `Value::Time` 所在的时区
+    pub fn with_time_offset(mut self, offset: FixedOffset) -> Self {
+        self.time = self.time.with_offset(offset);
+        self
+    }
+
+    /// 嵌套字段的展开方式，默认以 `.` 连接，展开后重名时追加后缀
+    pub fn with_flatten(mut self, flatten: Flatten) -> Self {
+        self.flatten = flatten;
+        self
+    }
+
+    /// 扩展值的文本选项
+    pub fn with_raw(mut self, raw: Raw) -> Self {
+        self.raw = raw;
+        self
+    }
+
+    fn headers(&self) -> [(&'static str, &MetaValue); 5] {
+        [
+            ("vendor", &self.vendor),
+            ("product", &self.product),
+            ("version", &self.version),
+            ("signature_id", &self.signature_id),
+            ("name", &self.name),
+        ]
+    }
+
+    /// 字段对应的 CEF 键
+    fn key_for(&self, field: &str) -> Option<&str> {
+        if let Some((_, key)) = self.keys.iter().find(|(name, _)| name == field) {
+            return Some(key);
+        }
+        if !self.dictionary {
+            return None;
+        }
+        let field = field.to_ascii_lowercase();
+        DICTIONARY
+            .iter()
+            .find(|(name, _)| *name == field)
+            .map(|(_, key)| *key)
+    }
+
+    fn write_extension_value(
+        &self,
+        key: &str,
+        value: &Value,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        match value {
+            Value::Time(t) if TIME_KEYS.contains(&key) => self.time.write(t, out)?,
+            _ => write_extension_text(&self.raw.try_format_value(value)?, out)?,
+        }
+        Ok(())
+    }
+}
+
+/// 头部：`\` 和 `|` 加反斜杠，换行替换为空格
+fn write_header_text(value: &str, out: &mut dyn fmt::Write) -> fmt::Result {
+    for c in value.chars() {
+        match c {
+            '\\' | '|' => {
+                out.write_char('\\')?;
+                out.write_char(c)?;
+            }
+            '\r' | '\n' => out.write_char(' ')?,
+            _ => out.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+/// 扩展值：`\` 和 `=` 加反斜杠，换行写为 `\n`、`\r`
+fn write_extension_text(value: &str, out: &mut dyn fmt::Write) -> fmt::Result {
+    for c in value.chars() {
+        match c {
+            '\\' | '=' => {
+                out.write_char('\\')?;
+                out.write_char(c)?;
+            }
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            _ => out.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+/// 槽位用完后的自定义键：非字母数字替换为 `_`，为空或以数字开头时加 `field` 前缀，
+/// 与已用的键重名时追加 `_2`、`_3` 等后缀
+fn overflow_key(name: &str, used: &[String]) -> String {
+    let key: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let key = if key.is_empty() {
+        "field".to_string()
+    } else if key.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("field_{}", key)
+    } else {
+        key
+    };
+    if !used.contains(&key) {
+        return key;
+    }
+    (2..)
+        .map(|n| format!("{}_{}", key, n))
+        .find(|candidate| !used.contains(candidate))
+        .unwrap_or_default()
+}
+
+/// 严重程度：0~10、Low/Medium/High/Very-High 或日志级别名称，其余为 `Unknown`
+fn severity(text: &str) -> String {
+    let text = text.trim();
+    if let Ok(n) = text.parse::<u8>() {
+        return if n <= 10 {
+            n.to_string()
+        } else {
+            "Unknown".to_string()
+        };
+    }
+    let severity = match text.to_ascii_lowercase().as_str() {
+        "low" => "Low",
+        "medium" => "Medium",
+        "high" => "High",
+        "very-high" => "Very-High",
+        "trace" | "debug" => "1",
+        "info" | "information" | "notice" => "3",
+        "warn" | "warning" => "5",
+        "err" | "error" => "7",
+        "crit" | "critical" | "fatal" => "9",
+        "alert" | "emerg" | "emergency" | "panic" => "10",
+        _ => "Unknown",
+    };
+    severity.to_string()
+}
+
+impl ValueWriter for Cef {
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        self.raw.write_value(value, out)
+    }
+}
+
+impl RecordWriter for Cef {
+    fn write_field(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        self.raw.write_field(field, out)
+    }
+
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        out.write_str("CEF:0")?;
+        for (key, meta) in self.headers() {
+            let text = meta
+                .resolve(record)
+                .ok_or_else(|| FormatError::MissingField {
+                    format: "cef",
+                    name: meta.field_name().unwrap_or(key).to_string(),
+                })?;
+            out.write_char('|')?;
+            write_header_text(&text, out)?;
+        }
+        out.write_char('|')?;
+        let level = self.severity.resolve(record);
+        out.write_str(&severity(level.as_deref().unwrap_or("")))?;
+        out.write_char('|')?;
+
+        let mut used: Vec<String> = Vec::new();
+        let mut first = true;
+        let mut pair = |out: &mut dyn fmt::Write, key: &str| -> fmt::Result {
+            if !std::mem::take(&mut first) {
+                out.write_char(' ')?;
+            }
+            write!(out, "{}=", key)
+        };
+
+        if let Some(name) = &self.time_field {
+            match lookup(record, name).map(|f| f.get_value()) {
+                None | Some(Value::Null | Value::Ignore(_)) => {}
+                Some(Value::Time(t)) => {
+                    pair(out, "rt")?;
+                    self.time.write(t, out)?;
+                    used.push("rt".to_string());
+                }
+                Some(value) => return Err(FormatError::unsupported("cef", value)),
+            }
+        }
+
+        let promoted: Vec<&str> = self
+            .headers()
+            .into_iter()
+            .map(|(_, meta)| meta)
+            .chain([&self.severity])
+            .filter_map(|meta| meta.field_name())
+            .chain(self.time_field.as_deref())
+            .collect();
+        let rest = without_fields(record, &promoted);
+        let flat = self.flatten.flatten(&rest)?;
+        let mut slots = 0;
+        for field in &flat.items {
+            let value = field.get_value();
+            if matches!(value, Value::Null | Value::Ignore(_)) {
+                continue;
+            }
+            let name = field.get_name();
+            match self
+                .key_for(name)
+                .filter(|key| !used.iter().any(|u| u == key))
+            {
+                Some(key) => {
+                    pair(out, key)?;
+                    self.write_extension_value(key, value, out)?;
+                    used.push(key.to_string());
+                }
+                None if slots < CUSTOM_STRINGS => {
+                    slots += 1;
+                    let (key, label) = (format!("cs{}", slots), format!("cs{}Label", slots));
+                    pair(out, &key)?;
+                    self.write_extension_value("", value, out)?;
+                    pair(out, &label)?;
+                    write_extension_text(name, out)?;
+                    used.extend([key, label]);
+                }
+                None => {
+                    let key = overflow_key(name, &used);
+                    pair(out, &key)?;
+                    self.write_extension_value("", value, out)?;
+                    used.push(key);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ValueFormatter for Cef {
+    type Output = String;
+
+    fn format_value(&self, value: &Value) -> String {
+        self.try_format_value(value).unwrap_or_default()
+    }
+
+    fn try_format_value(&self, value: &Value) -> Result<String, FormatError> {
+        render(|out| self.write_value(value, out))
+    }
+}
+
+impl RecordFormatter for Cef {
+    fn fmt_field(&self, field: &FieldStorage) -> String {
+        self.try_fmt_field(field).unwrap_or_default()
+    }
+
+    fn fmt_record(&self, record: &DataRecord) -> String {
+        self.try_fmt_record(record).unwrap_or_default()
+    }
+
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<String, FormatError> {
+        render(|out| self.write_field(field, out))
+    }
+
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<String, FormatError> {
+        render(|out| self.write_record_fmt(record, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wp_model_core::model::DataField;
+    use wp_model_core::model::types::value::ObjectValue;
+
+    fn make_record() -> DataRecord {
+        let ts = chrono::NaiveDateTime::parse_from_str(
+            "2019-08-06 12:12:19.250",
+            "%Y-%m-%d %H:%M:%S%.f",
+        )
+        .unwrap();
+        let mut http = ObjectValue::new();
+        http.insert(
+            "status".to_string(),
+            FieldStorage::from_owned(DataField::from_digit("status", 200)),
+        );
+        DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_time("ts", ts)),
+                FieldStorage::from_owned(DataField::from_chars("rule_id", "942100")),
+                FieldStorage::from_owned(DataField::from_chars("rule_name", "SQLi|detected")),
+                FieldStorage::from_owned(DataField::from_chars("level", "ERROR")),
+                FieldStorage::from_owned(DataField::from_chars("src_ip", "10.0.0.1")),
+                FieldStorage::from_owned(DataField::from_digit("dst_port", 443)),
+                FieldStorage::from_owned(DataField::from_chars("url", "/q?a=1\\2\nx")),
+                FieldStorage::from_owned(DataField::from_obj("http", http)),
+            ],
+        }
+    }
+
+    fn make_cef() -> Cef {
+        Cef::new(
+            MetaValue::text("Acme"),
+            MetaValue::text("WAF"),
+            MetaValue::text("1.0"),
+        )
+        .with_signature_id(MetaValue::field("rule_id"))
+        .with_name(MetaValue::field("rule_name"))
+        .with_severity(MetaValue::field("level"))
+    }
+
+    #[test]
+    fn test_cef_line() {
+        assert_eq!(
+            make_cef().with_time_field("ts").fmt_record(&make_record()),
+            concat!(
+                r"CEF:0|Acme|WAF|1.0|942100|SQLi\|detected|7|rt=1565093539250 src=10.0.0.1 ",
+                r"dpt=443 request=/q?a\=1\\2\nx cs1=200 cs1Label=http.status"
+            )
+        );
+
+        // 未指定时间字段时 `ts` 经字典映射为 `rt`
+        let cef = make_cef()
+            .with_severity(MetaValue::text("Very-high"))
+            .with_time_offset(FixedOffset::east_opt(3600).unwrap());
+        assert!(
+            cef.fmt_record(&make_record()).starts_with(
+                "CEF:0|Acme|WAF|1.0|942100|SQLi\\|detected|Very-High|rt=1565089939250 "
+            )
+        );
+    }
+
+    #[test]
+    fn test_custom_keys_and_slots() {
+        let cef = make_cef()
+            .with_dictionary(false)
+            .with_key("src_ip", "src")
+            .unwrap();
+        let line = cef.fmt_record(&make_record());
+        let ext = line.rsplit('|').next().unwrap();
+        assert_eq!(
+            ext,
+            concat!(
+                r"cs1=2019-08-06 12:12:19.250 cs1Label=ts src=10.0.0.1 cs2=443 cs2Label=dst_port ",
+                r"cs3=/q?a\=1\\2\nx cs3Label=url cs4=200 cs4Label=http.status"
+            )
+        );
+
+        let items = (0..8)
+            .map(|i| FieldStorage::from_owned(DataField::from_digit(format!("f-{}", i), i)))
+            .collect();
+        let record = DataRecord {
+            id: Default::default(),
+            items,
+        };
+        let cef = Cef::new(
+            MetaValue::text("a"),
+            MetaValue::text("b"),
+            MetaValue::text("c"),
+        )
+        .with_signature_id(MetaValue::text("1"))
+        .with_name(MetaValue::text("n"));
+        assert!(
+            cef.fmt_record(&record)
+                .ends_with("cs6=5 cs6Label=f-5 f_6=6 f_7=7")
+        );
+
+        assert!(cef.clone().with_key("f", "bad key").is_err());
+    }
+
+    #[test]
+    fn test_overflow_keys() {
+        let items = (1..=6)
+            .map(|i| FieldStorage::from_owned(DataField::from_digit(format!("f{}", i), i)))
+            .chain(
+                [
+                    ("src_ip", 7),
+                    ("src-ip", 8),
+                    ("", 9),
+                    ("9lives", 10),
+                    ("cs1", 11),
+                ]
+                .into_iter()
+                .map(|(name, n)| FieldStorage::from_owned(DataField::from_digit(name, n))),
+            )
+            .collect();
+        let record = DataRecord {
+            id: Default::default(),
+            items,
+        };
+        let cef = Cef::new(
+            MetaValue::text("a"),
+            MetaValue::text("b"),
+            MetaValue::text("c"),
+        )
+        .with_signature_id(MetaValue::text("1"))
+        .with_name(MetaValue::text("n"))
+        .with_dictionary(false);
+        assert!(
+            cef.fmt_record(&record).ends_with(
+                "cs6=6 cs6Label=f6 src_ip=7 src_ip_2=8 field=9 field_9lives=10 cs1_2=11"
+            )
+        );
+
+        assert_eq!(
+            overflow_key("src_ip", &["src_ip".to_string(), "src_ip_2".to_string()]),
+            "src_ip_3"
+        );
+    }
+
+    #[test]
+    fn test_flatten_collision() {
+        let mut http = ObjectValue::new();
+        http.insert(
+            "status".to_string(),
+            FieldStorage::from_owned(DataField::from_digit("status", 200)),
+        );
+        let colliding = [
+            FieldStorage::from_owned(DataField::from_obj("http", http)),
+            FieldStorage::from_owned(DataField::from_digit("http.status", 404)),
+        ];
+        let cef = Cef::new(
+            MetaValue::text("a"),
+            MetaValue::text("b"),
+            MetaValue::text("c"),
+        )
+        .with_signature_id(MetaValue::text("1"))
+        .with_name(MetaValue::text("n"));
+
+        let record = DataRecord {
+            id: Default::default(),
+            items: colliding.to_vec(),
+        };
+        assert!(
+            cef.try_fmt_record(&record)
+                .unwrap()
+                .ends_with("cs1=200 cs1Label=http.status cs2=404 cs2Label=http.status_2")
+        );
+
+        // 槽位用完后由自定义键保证唯一
+        let items = (1..=6)
+            .map(|i| FieldStorage::from_owned(DataField::from_digit(format!("f{}", i), i)))
+            .chain(colliding)
+            .chain([FieldStorage::from_owned(DataField::from_digit(
+                "http_status_2",
+                1,
+            ))])
+            .collect();
+        let record = DataRecord {
+            id: Default::default(),
+            items,
+        };
+        assert!(
+            cef.try_fmt_record(&record)
+                .unwrap()
+                .ends_with("http_status=200 http_status_2=404 http_status_2_2=1")
+        );
+    }
+
+    #[test]
+    fn test_severity_and_errors() {
+        assert_eq!(severity("10"), "10");
+        assert_eq!(severity("11"), "Unknown");
+        assert_eq!(severity("HIGH"), "High");
+        assert_eq!(severity("warn"), "5");
+        assert_eq!(severity(""), "Unknown");
+
+        let err = make_cef()
+            .with_name(MetaValue::field("missing"))
+            .try_fmt_record(&make_record())
+            .unwrap_err();
+        assert!(matches!(err, FormatError::MissingField { ref name, .. } if name == "missing"));
+
+        let err = make_cef()
+            .with_time_field("dst_port")
+            .try_fmt_record(&make_record())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            FormatError::UnsupportedType {
+                format: "cef",
+                type_name: "digit"
+            }
+        ));
+    }
+}
@@ -0,0 +1,432 @@
+use std::fmt;
+
+use wp_model_core::model::{DataRecord, FieldStorage, Value};
+
+use crate::Json;
+use crate::error::{ConfigError, FormatError};
+use crate::formatter::{RecordFormatter, RecordWriter, ValueFormatter, ValueWriter, render};
+use crate::json::write_json_str;
+use crate::meta::{MetaValue, without_fields};
+use crate::projected::lookup;
+use crate::time::TimeFormat;
+
+/// 由格式化器自身写出、不能通过 `with_attribute` 设置的属性
+const RESERVED: [&str; 8] = [
+    "specversion",
+    "id",
+    "source",
+    "type",
+    "time",
+    "datacontenttype",
+    "data",
+    "data_base64",
+];
+
+/// 二进制模式的事件：属性作为消息头，`data` 作为消息体
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryEvent {
+    /// 带前缀的属性头，以及 `content-type`
+    pub headers: Vec<(String, String)>,
+    pub data: String,
+}
+
+/// CloudEvents 1.0 事件信封
+///
+/// 结构化模式（`fmt_record`）输出
+/// `{"specversion":"1.0","id":..,"source":..,"type":..,"time":..,"datacontenttype":"application/json",..,"data":{..}}`，
+/// `data` 由 `Json` 的紧凑输出生成。`binary` 生成二进制模式的消息头和消息体，
+/// 供 Kafka、AMQP 等消息总线连接器使用。
+///
+/// - `id` 默认取 `DataRecord.id`，可用 `with_id` 改为固定文本或字段
+/// - `source` 和 `type` 必填；取不到值时返回 `FormatError::MissingField`
+/// - `time` 取自 `with_time_field` 指定的 `Value::Time` 字段，输出为 RFC3339，
+///   时区取 `Json` 的 `TimeFormat::with_offset`
+/// - `with_attribute` 设置 `subject`、`dataschema` 等可选属性和扩展属性，
+///   取不到值时省略
+///
+/// ```
+/// use wp_data_fmt::{CloudEvent, MetaValue};
+///
+/// let event = CloudEvent::new(
+///     MetaValue::text("/wp/nginx"),
+///     MetaValue::text("com.example.access"),
+/// )
+/// .with_time_field("timestamp")
+/// .with_attribute("subject", MetaValue::field("host"))
+/// .unwrap()
+/// .with_header_prefix("ce_");
+/// ```
+#[derive(Debug, Clone)]
+pub struct CloudEvent {
+    json: Json,
+    id: Option<MetaValue>,
+    source: MetaValue,
+    event_type: MetaValue,
+    time_field: Option<String>,
+    attributes: Vec<(String, MetaValue)>,
+    remove_fields: bool,
+    header_prefix: String,
+}
+
+impl CloudEvent {
+    pub fn new(source: MetaValue, event_type: MetaValue) -> Self {
+        Self {
+            json: Json::default(),
+            id: None,
+            source,
+            event_type,
+            time_field: None,
+            attributes: Vec::new(),
+            remove_fields: false,
+            header_prefix: "ce-".to_string(),
+        }
+    }
+
+    /// `data` 的 JSON 选项
+    pub fn with_json(mut self, json: Json) -> Self {
+        self.json = json;
+        self
+    }
+
+    pub fn with_id(mut self, id: MetaValue) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn with_time_field(mut self, field: impl Into<String>) -> Self {
+        self.time_field = Some(field.into());
+        self
+    }
+
+    /// 可选属性或扩展属性；名称须为小写字母和数字，且不能是上述核心属性
+    pub fn with_attribute(
+        mut self,
+        name: impl Into<String>,
+        value: MetaValue,
+    ) -> Result<Self, ConfigError> {
+        let name = name.into();
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
+        if !valid || RESERVED.contains(&name.as_str()) {
+            return Err(ConfigError::InvalidOption {
+                kind: "cloudevents",
+                option: "attribute",
+                reason: format!("invalid attribute name {:?}", name),
+            });
+        }
+        self.attributes.push((name, value));
+        Ok(self)
+    }
+
+    /// 从 `data` 中去掉作为属性来源的顶层字段和时间字段
+    pub fn with_remove_fields(mut self, remove_fields: bool) -> Self {
+        self.remove_fields = remove_fields;
+        self
+    }
+
+    /// 二进制模式的属性头前缀：HTTP 为 `ce-`（默认），Kafka 为 `ce_`。
+    /// 前缀为 `ce-` 时按 HTTP 绑定对属性值做百分号编码
+    pub fn with_header_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.header_prefix = prefix.into();
+        self
+    }
+
+    /// 二进制模式：属性作为消息头，`data` 作为消息体
+    pub fn binary(&self, record: &DataRecord) -> Result<BinaryEvent, FormatError> {
+        let http = self.header_prefix.eq_ignore_ascii_case("ce-");
+        let headers = self
+            .attribute_values(record)?
+            .into_iter()
+            .map(|(name, value)| {
+                let value = if http { percent_encode(&value) } else { value };
+                (format!("{}{}", self.header_prefix, name), value)
+            })
+            .chain([("content-type".to_string(), "application/json".to_string())])
+            .collect();
+        let data = render(|out| self.write_data(record, out))?;
+        Ok(BinaryEvent { headers, data })
+    }
+
+    /// 按输出顺序求出所有属性（不含 `datacontenttype` 和 `data`）
+    fn attribute_values(&self, record: &DataRecord) -> Result<Vec<(String, String)>, FormatError> {
+        let id = match &self.id {
+            Some(id) => required(id, record, "id")?,
+            None => record.id.to_string(),
+        };
+        let mut values = vec![
+            ("specversion".to_string(), "1.0".to_string()),
+            ("id".to_string(), id),
+            (
+                "source".to_string(),
+                required(&self.source, record, "source")?,
+            ),
+            (
+                "type".to_string(),
+                required(&self.event_type, record, "type")?,
+            ),
+        ];
+        if let Some(time) = self.time(record)? {
+            values.push(("time".to_string(), time));
+        }
+        for (name, value) in &self.attributes {
+            if let Some(text) = value.resolve(record) {
+                values.push((name.clone(), text.into_owned()));
+            }
+        }
+        Ok(values)
+    }
+
+    fn time(&self, record: &DataRecord) -> Result<Option<String>, FormatError> {
+        let Some(name) = &self.time_field else {
+            return Ok(None);
+        };
+        match lookup(record, name).map(|f| f.get_value()) {
+            None | Some(Value::Null | Value::Ignore(_)) => Ok(None),
+            Some(Value::Time(t)) => {
+                let rfc3339 = match self.json.opts().time.offset() {
+                    Some(offset) => TimeFormat::rfc3339().with_offset(offset),
+                    None => TimeFormat::rfc3339(),
+                };
+                Ok(Some(rfc3339.try_format(t)?))
+            }
+            Some(value) => Err(FormatError::unsupported("cloudevents", value)),
+        }
+    }
+
+    fn write_data(&self, record: &DataRecord, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        if !self.remove_fields {
+            return self.json.write_record_compact(record, out);
+        }
+        let removed: Vec<&str> = [self.id.as_ref(), Some(&self.source), Some(&self.event_type)]
+            .into_iter()
+            .flatten()
+            .chain(self.attributes.iter().map(|(_, value)| value))
+            .filter_map(|value| value.field_name())
+            .chain(self.time_field.as_deref())
+            .collect();
+        self.json
+            .write_record_compact(&without_fields(record, &removed), out)
+    }
+}
+
+fn required(meta: &MetaValue, record: &DataRecord, name: &str) -> Result<String, FormatError> {
+    meta.resolve(record)
+        .map(|text| text.into_owned())
+        .ok_or_else(|| FormatError::MissingField {
+            format: "cloudevents",
+            name: meta.field_name().unwrap_or(name).to_string(),
+        })
+}
+
+impl ValueWriter for CloudEvent {
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        self.json.write_value(value, out)
+    }
+}
+
+impl RecordWriter for CloudEvent {
+    fn write_field(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        self.json.write_field(field, out)
+    }
+
+    /// 结构化模式
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        out.write_char('{')?;
+        for (name, value) in self.attribute_values(record)? {
+            write_json_str(&name, out)?;
+            out.write_char(':')?;
+            write_json_str(&value, out)?;
+            out.write_char(',')?;
+        }
+        out.write_str("\"datacontenttype\":\"application/json\",\"data\":")?;
+        self.write_data(record, out)?;
+        out.write_char('}')?;
+        Ok(())
+    }
+}
+
+impl ValueFormatter for CloudEvent {
+    type Output = String;
+
+    fn format_value(&self, value: &Value) -> String {
+        self.try_format_value(value).unwrap_or_default()
+    }
+
+    fn try_format_value(&self, value: &Value) -> Result<String, FormatError> {
+        render(|out| self.write_value(value, out))
+    }
+}
+
+impl RecordFormatter for CloudEvent {
+    fn fmt_field(&self, field: &FieldStorage) -> String {
+        self.try_fmt_field(field).unwrap_or_default()
+    }
+
+    fn fmt_record(&self, record: &DataRecord) -> String {
+        self.try_fmt_record(record).unwrap_or_default()
+    }
+
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<String, FormatError> {
+        render(|out| self.write_field(field, out))
+    }
+
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<String, FormatError> {
+        render(|out| self.write_record_fmt(record, out))
+    }
+}
+
+/// HTTP 绑定的消息头值编码：空格、`"`、`%` 以及可打印 ASCII 以外的字符按 UTF-8 字节
+/// 编码为 `%XX`
+fn percent_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '!'..='~') && c != '"' && c != '%' {
+            out.push(c);
+        } else {
+            let mut buf = [0; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                out.push_str(&format!("%{:02X}", b));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wp_model_core::model::DataField;
+
+    fn make_record() -> DataRecord {
+        let ts = chrono::NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S")
+            .unwrap();
+        DataRecord {
+            id: 42,
+            items: vec![
+                FieldStorage::from_owned(DataField::from_time("ts", ts)),
+                FieldStorage::from_owned(DataField::from_chars("host", "web01")),
+                FieldStorage::from_owned(DataField::from_digit("status", 200)),
+            ],
+        }
+    }
+
+    fn make_event() -> CloudEvent {
+        CloudEvent::new(
+            MetaValue::text("/wp/nginx"),
+            MetaValue::text("com.example.access"),
+        )
+    }
+
+    #[test]
+    fn test_structured() {
+        assert_eq!(
+            make_event().fmt_record(&make_record()),
+            concat!(
+                r#"{"specversion":"1.0","id":"42","source":"/wp/nginx","type":"com.example.access","#,
+                r#""datacontenttype":"application/json","#,
+                r#""data":{"ts":"2019-08-06 12:12:19","host":"web01","status":200}}"#
+            )
+        );
+
+        let event = make_event()
+            .with_time_field("ts")
+            .with_attribute("subject", MetaValue::field("host"))
+            .unwrap()
+            .with_attribute("traceparent", MetaValue::field("missing"))
+            .unwrap()
+            .with_remove_fields(true);
+        assert_eq!(
+            event.fmt_record(&make_record()),
+            concat!(
+                r#"{"specversion":"1.0","id":"42","source":"/wp/nginx","type":"com.example.access","#,
+                r#""time":"2019-08-06T12:12:19Z","subject":"web01","#,
+                r#""datacontenttype":"application/json","data":{"status":200}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_binary() {
+        let event = make_event()
+            .with_id(MetaValue::field("status"))
+            .with_header_prefix("ce_");
+        let binary = event.binary(&make_record()).unwrap();
+        let headers: Vec<(&str, &str)> = binary
+            .headers
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(
+            headers,
+            [
+                ("ce_specversion", "1.0"),
+                ("ce_id", "200"),
+                ("ce_source", "/wp/nginx"),
+                ("ce_type", "com.example.access"),
+                ("content-type", "application/json"),
+            ]
+        );
+        assert_eq!(
+            binary.data,
+            r#"{"ts":"2019-08-06 12:12:19","host":"web01","status":200}"#
+        );
+    }
+
+    #[test]
+    fn test_binary_http_encoding() {
+        let event = make_event()
+            .with_id(MetaValue::text("a b"))
+            .with_attribute("subject", MetaValue::text("\"100%\" café\n"))
+            .unwrap();
+        let binary = event.binary(&make_record()).unwrap();
+        let header = |name: &str| {
+            binary
+                .headers
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(header("ce-id"), Some("a%20b"));
+        assert_eq!(header("ce-subject"), Some("%22100%25%22%20caf%C3%A9%0A"));
+        assert_eq!(header("ce-source"), Some("/wp/nginx"));
+
+        // Kafka 消息头保持原值
+        let kafka = event
+            .with_header_prefix("ce_")
+            .binary(&make_record())
+            .unwrap();
+        assert!(
+            kafka
+                .headers
+                .contains(&("ce_id".to_string(), "a b".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(
+            make_event()
+                .with_attribute("Subject", MetaValue::text("x"))
+                .is_err()
+        );
+        assert!(
+            make_event()
+                .with_attribute("time", MetaValue::text("x"))
+                .is_err()
+        );
+
+        let event = CloudEvent::new(MetaValue::field("src"), MetaValue::text("t"));
+        let err = event.try_fmt_record(&make_record()).unwrap_err();
+        assert!(matches!(err, FormatError::MissingField { ref name, .. } if name == "src"));
+    }
+}
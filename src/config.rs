@@ -0,0 +1,727 @@
+use chrono::FixedOffset;
+use serde::{Deserialize, Serialize};
+use wp_model_core::model::fmt_def::TextFmt;
+
+use crate::error::ConfigError;
+use crate::options::DEFAULT_MAX_DEPTH;
+use crate::{
+    Collision, Csv, Flatten, FloatPolicy, FormatType, Json, KeyValue, NonFinite, NullPolicy,
+    ProtoTxt, Raw, SqlDialect, SqlFormat, SqlInsert, TimeFormat,
+};
+
+/// 可反序列化的格式化器配置
+///
+/// 以 `kind` 字段区分格式，未填写的选项取各格式化器的默认值，未知选项会被拒绝：
+///
+/// ```toml
+/// format = { kind = "csv", delimiter = ";", quote = "'" }
+/// ```
+///
+/// 所有格式都支持 `time`、`float` 和 `null` 三个共享选项，CSV、KV 和 SQL 另外支持 `flatten`：
+///
+/// ```toml
+/// [format]
+/// kind = "csv"
+/// time = { format = "rfc3339", offset = "+08:00" }
+/// float = { precision = 2, non_finite = "null" }
+/// null = { placeholder = "-" }
+/// flatten = { separator = "/", collision = "suffix" }
+/// ```
+///
+/// JSON 的缩进、键名、整数、类型标注和反展平等选项目前只能通过代码设置。
+///
+/// 调用 `build()` 校验选项并生成 `FormatType`。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum FormatterConfig {
+    Json {
+        #[serde(default)]
+        strict: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_depth: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time: Option<TimeConfig>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        float: Option<FloatConfig>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        null: Option<NullPolicy>,
+    },
+    Csv {
+        /// 字段分隔符，必须是单个字符
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        delimiter: Option<String>,
+        /// 引号字符，必须是单个字符
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        quote: Option<String>,
+        /// 引号转义字符，必须是单个字符
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        escape: Option<String>,
+        #[serde(default)]
+        strict: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_depth: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time: Option<TimeConfig>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        float: Option<FloatConfig>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        null: Option<NullPolicy>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        flatten: Option<FlattenConfig>,
+    },
+    Kv {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pair_separator: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key_value_separator: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        quote_strings: Option<bool>,
+        #[serde(default)]
+        strict: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_depth: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time: Option<TimeConfig>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        float: Option<FloatConfig>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        null: Option<NullPolicy>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        flatten: Option<FlattenConfig>,
+    },
+    #[serde(alias = "show")]
+    Raw {
+        #[serde(default)]
+        strict: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_depth: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time: Option<TimeConfig>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        float: Option<FloatConfig>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        null: Option<NullPolicy>,
+    },
+    #[serde(alias = "proto")]
+    ProtoText {
+        #[serde(default)]
+        strict: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_depth: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time: Option<TimeConfig>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        float: Option<FloatConfig>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        null: Option<NullPolicy>,
+    },
+    Sql {
+        table: String,
+        #[serde(default)]
+        dialect: SqlDialect,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        quote_identifiers: Option<bool>,
+        /// 对象/数组列的编码格式：json、kv、raw 或 proto-text，默认 json
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        object_format: Option<String>,
+        #[serde(default)]
+        strict: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time: Option<TimeConfig>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        float: Option<FloatConfig>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        null: Option<NullPolicy>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        flatten: Option<FlattenConfig>,
+    },
+}
+
+/// `TimeFormat` 的配置形式
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TimeConfig {
+    /// native、rfc3339、epoch-seconds、epoch-millis、epoch-nanos，或含 `%` 的 strftime 模式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// `Value::Time` 所在的时区，如 `+08:00` 或 `Z`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<String>,
+}
+
+/// `FloatPolicy` 的配置形式，`precision` 与 `significant_digits` 只能二选一
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FloatConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precision: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub significant_digits: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub non_finite: Option<NonFinite>,
+}
+
+/// `Flatten` 的配置形式，出现即启用展平，`flatten = {}` 使用默认值
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlattenConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub separator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collision: Option<Collision>,
+}
+
+impl FormatterConfig {
+    /// 配置中的格式名称
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FormatterConfig::Json { .. } => "json",
+            FormatterConfig::Csv { .. } => "csv",
+            FormatterConfig::Kv { .. } => "kv",
+            FormatterConfig::Raw { .. } => "raw",
+            FormatterConfig::ProtoText { .. } => "proto-text",
+            FormatterConfig::Sql { .. } => "sql",
+        }
+    }
+
+    /// 校验选项并构建格式化器
+    pub fn build(&self) -> Result<FormatType, ConfigError> {
+        let kind = self.kind();
+        let fmt = match self {
+            FormatterConfig::Json {
+                strict,
+                max_depth,
+                time,
+                float,
+                null,
+            } => FormatType::Json(
+                Json::default()
+                    .with_strict(*strict)
+                    .with_max_depth(max_depth_or_default(kind, *max_depth)?)
+                    .with_time_format(time_format(kind, time)?)
+                    .with_float_policy(float_policy(kind, float)?)
+                    .with_null_policy(null.clone().unwrap_or_default()),
+            ),
+            FormatterConfig::Csv {
+                delimiter,
+                quote,
+                escape,
+                strict,
+                max_depth,
+                time,
+                float,
+                null,
+                flatten,
+            } => {
+                let mut csv = Csv::default()
+                    .with_strict(*strict)
+                    .with_max_depth(max_depth_or_default(kind, *max_depth)?)
+                    .with_time_format(time_format(kind, time)?)
+                    .with_float_policy(float_policy(kind, float)?)
+                    .with_null_policy(null.clone().unwrap_or_default());
+                let delimiter = single_char(kind, "delimiter", delimiter)?;
+                let quote = single_char(kind, "quote", quote)?;
+                if let Some(c) = delimiter {
+                    csv = csv.with_delimiter(c);
+                }
+                if let Some(c) = quote {
+                    csv = csv.with_quote_char(c);
+                }
+                if let Some(c) = single_char(kind, "escape", escape)? {
+                    csv = csv.with_escape_char(c);
+                }
+                if let (Some(d), Some(q)) = (delimiter, quote)
+                    && d == q
+                {
+                    return Err(invalid(kind, "quote", "must differ from `delimiter`"));
+                }
+                if let Some(flatten) = flatten_or_none(kind, flatten)? {
+                    csv = csv.with_flatten(flatten);
+                }
+                FormatType::Csv(csv)
+            }
+            FormatterConfig::Kv {
+                pair_separator,
+                key_value_separator,
+                quote_strings,
+                strict,
+                max_depth,
+                time,
+                float,
+                null,
+                flatten,
+            } => {
+                let mut kv = KeyValue::default()
+                    .with_strict(*strict)
+                    .with_max_depth(max_depth_or_default(kind, *max_depth)?)
+                    .with_time_format(time_format(kind, time)?)
+                    .with_float_policy(float_policy(kind, float)?)
+                    .with_null_policy(null.clone().unwrap_or_default());
+                if let Some(sep) = non_empty(kind, "pair_separator", pair_separator)? {
+                    kv = kv.with_pair_separator(sep);
+                }
+                if let Some(sep) = non_empty(kind, "key_value_separator", key_value_separator)? {
+                    kv = kv.with_key_value_separator(sep);
+                }
+                if let Some(quote) = quote_strings {
+                    kv = kv.with_quote_strings(*quote);
+                }
+                if let Some(flatten) = flatten_or_none(kind, flatten)? {
+                    kv = kv.with_flatten(flatten);
+                }
+                FormatType::Kv(kv)
+            }
+            FormatterConfig::Raw {
+                strict,
+                max_depth,
+                time,
+                float,
+                null,
+            } => FormatType::Raw(
+                Raw::default()
+                    .with_strict(*strict)
+                    .with_max_depth(max_depth_or_default(kind, *max_depth)?)
+                    .with_time_format(time_format(kind, time)?)
+                    .with_float_policy(float_policy(kind, float)?)
+                    .with_null_policy(null.clone().unwrap_or_default()),
+            ),
+            FormatterConfig::ProtoText {
+                strict,
+                max_depth,
+                time,
+                float,
+                null,
+            } => FormatType::ProtoText(
+                ProtoTxt::default()
+                    .with_strict(*strict)
+                    .with_max_depth(max_depth_or_default(kind, *max_depth)?)
+                    .with_time_format(time_format(kind, time)?)
+                    .with_float_policy(float_policy(kind, float)?)
+                    .with_null_policy(null.clone().unwrap_or_default()),
+            ),
+            FormatterConfig::Sql {
+                table,
+                dialect,
+                quote_identifiers,
+                object_format,
+                strict,
+                time,
+                float,
+                null,
+                flatten,
+            } => {
+                if table.trim().is_empty() {
+                    return Err(invalid(kind, "table", "must not be empty"));
+                }
+                let mut sql = SqlInsert::new_with_json(table.as_str())
+                    .with_dialect(*dialect)
+                    .with_strict(*strict)
+                    .with_time_format(time_format(kind, time)?)
+                    .with_float_policy(float_policy(kind, float)?)
+                    .with_null_policy(null.clone().unwrap_or_default());
+                if let Some(quote) = quote_identifiers {
+                    sql.quote_identifiers = *quote;
+                }
+                if let Some(name) = object_format {
+                    sql.obj_formatter = match name.as_str() {
+                        "json" | "kv" | "raw" | "proto-text" => {
+                            SqlFormat::from(&TextFmt::from(name.as_str()))
+                        }
+                        _ => {
+                            return Err(invalid(
+                                kind,
+                                "object_format",
+                                format!(
+                                    "unknown format `{}`, expected json, kv, raw or proto-text",
+                                    name
+                                ),
+                            ));
+                        }
+                    };
+                }
+                if let Some(flatten) = flatten_or_none(kind, flatten)? {
+                    sql = sql.with_flatten(flatten);
+                }
+                FormatType::Sql(sql)
+            }
+        };
+        Ok(fmt)
+    }
+}
+
+impl TryFrom<&FormatterConfig> for FormatType {
+    type Error = ConfigError;
+
+    fn try_from(config: &FormatterConfig) -> Result<Self, Self::Error> {
+        config.build()
+    }
+}
+
+fn invalid(kind: &'static str, option: &'static str, reason: impl Into<String>) -> ConfigError {
+    ConfigError::InvalidOption {
+        kind,
+        option,
+        reason: reason.into(),
+    }
+}
+
+fn single_char(
+    kind: &'static str,
+    option: &'static str,
+    value: &Option<String>,
+) -> Result<Option<char>, ConfigError> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some('\n' | '\r'), None) => Err(invalid(kind, option, "must not be a line break")),
+        (Some(c), None) => Ok(Some(c)),
+        _ => Err(invalid(
+            kind,
+            option,
+            format!("expected a single character, got {:?}", value),
+        )),
+    }
+}
+
+fn non_empty<'a>(
+    kind: &'static str,
+    option: &'static str,
+    value: &'a Option<String>,
+) -> Result<Option<&'a str>, ConfigError> {
+    match value.as_deref() {
+        Some("") => Err(invalid(kind, option, "must not be empty")),
+        other => Ok(other),
+    }
+}
+
+fn max_depth_or_default(
+    kind: &'static str,
+    max_depth: Option<usize>,
+) -> Result<usize, ConfigError> {
+    match max_depth {
+        Some(0) => Err(invalid(kind, "max_depth", "must be at least 1")),
+        Some(depth) => Ok(depth),
+        None => Ok(DEFAULT_MAX_DEPTH),
+    }
+}
+
+fn time_format(kind: &'static str, time: &Option<TimeConfig>) -> Result<TimeFormat, ConfigError> {
+    let Some(time) = time else {
+        return Ok(TimeFormat::default());
+    };
+    let mut format = match time.format.as_deref() {
+        None | Some("native") => TimeFormat::native(),
+        Some("rfc3339") => TimeFormat::rfc3339(),
+        Some("epoch-seconds") => TimeFormat::epoch_seconds(),
+        Some("epoch-millis") => TimeFormat::epoch_millis(),
+        Some("epoch-nanos") => TimeFormat::epoch_nanos(),
+        Some(pattern) if pattern.contains('%') => TimeFormat::custom(pattern).map_err(|_| {
+            invalid(
+                kind,
+                "time.format",
+                format!("invalid strftime pattern {:?}", pattern),
+            )
+        })?,
+        Some(other) => {
+            return Err(invalid(
+                kind,
+                "time.format",
+                format!(
+                    "unknown format `{}`, expected native, rfc3339, epoch-seconds, \
+                     epoch-millis, epoch-nanos or a strftime pattern",
+                    other
+                ),
+            ));
+        }
+    };
+    if let Some(offset) = &time.offset {
+        let offset = match offset.as_str() {
+            "Z" | "z" => FixedOffset::east_opt(0),
+            text => text.parse::<FixedOffset>().ok(),
+        }
+        .ok_or_else(|| {
+            invalid(
+                kind,
+                "time.offset",
+                format!("expected an offset such as \"+08:00\", got {:?}", offset),
+            )
+        })?;
+        format = format.with_offset(offset);
+    }
+    Ok(format)
+}
+
+fn float_policy(
+    kind: &'static str,
+    float: &Option<FloatConfig>,
+) -> Result<FloatPolicy, ConfigError> {
+    let Some(float) = float else {
+        return Ok(FloatPolicy::default());
+    };
+    let mut policy = FloatPolicy::new();
+    match (float.precision, float.significant_digits) {
+        (Some(_), Some(_)) => {
+            return Err(invalid(
+                kind,
+                "float.significant_digits",
+                "conflicts with `float.precision`",
+            ));
+        }
+        (Some(decimals), None) => policy = policy.with_precision(decimals),
+        (None, Some(0)) => {
+            return Err(invalid(
+                kind,
+                "float.significant_digits",
+                "must be at least 1",
+            ));
+        }
+        (None, Some(digits)) => policy = policy.with_significant_digits(digits),
+        (None, None) => {}
+    }
+    if let Some(non_finite) = float.non_finite {
+        policy = policy.with_non_finite(non_finite);
+    }
+    Ok(policy)
+}
+
+fn flatten_or_none(
+    kind: &'static str,
+    flatten: &Option<FlattenConfig>,
+) -> Result<Option<Flatten>, ConfigError> {
+    let Some(config) = flatten else {
+        return Ok(None);
+    };
+    let mut flatten = Flatten::new();
+    if let Some(sep) = non_empty(kind, "flatten.separator", &config.separator)? {
+        flatten = flatten.with_separator(sep);
+    }
+    match config.max_depth {
+        Some(0) => return Err(invalid(kind, "flatten.max_depth", "must be at least 1")),
+        Some(depth) => flatten = flatten.with_max_depth(depth),
+        None => {}
+    }
+    if let Some(collision) = config.collision {
+        flatten = flatten.with_collision(collision);
+    }
+    Ok(Some(flatten))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RecordFormatter;
+    use wp_model_core::model::types::value::ObjectValue;
+    use wp_model_core::model::{DataField, DataRecord, DataType, FieldStorage, Value};
+
+    fn make_record() -> DataRecord {
+        DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_chars("name", "a;b")),
+                FieldStorage::from_owned(DataField::from_digit("age", 30)),
+            ],
+        }
+    }
+
+    fn parse(text: &str) -> FormatterConfig {
+        serde_json::from_str(text).unwrap()
+    }
+
+    fn build_err(text: &str) -> ConfigError {
+        match parse(text).build() {
+            Ok(_) => panic!("expected invalid config: {}", text),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn test_csv_config() {
+        let config = parse(r#"{"kind":"csv","delimiter":";","quote":"'"}"#);
+        let fmt = config.build().unwrap();
+        assert_eq!(fmt.fmt_record(&make_record()), "'a;b';30");
+    }
+
+    #[test]
+    fn test_csv_config_invalid() {
+        let err = build_err(r#"{"kind":"csv","delimiter":";;"}"#);
+        assert_eq!(
+            err.to_string(),
+            r#"csv: invalid `delimiter`: expected a single character, got ";;""#
+        );
+        let err = build_err(r#"{"kind":"csv","delimiter":"'","quote":"'"}"#);
+        assert!(matches!(
+            err,
+            ConfigError::InvalidOption {
+                option: "quote",
+                ..
+            }
+        ));
+        assert!(parse(r#"{"kind":"csv","delimiter":"\n"}"#).build().is_err());
+    }
+
+    #[test]
+    fn test_kv_config() {
+        let config = parse(
+            r#"{"kind":"kv","pair_separator":" ","key_value_separator":"=","quote_strings":false}"#,
+        );
+        let fmt = config.build().unwrap();
+        assert_eq!(fmt.fmt_record(&make_record()), "name=a;b age=30");
+
+        let err = build_err(r#"{"kind":"kv","pair_separator":""}"#);
+        assert!(matches!(
+            err,
+            ConfigError::InvalidOption {
+                option: "pair_separator",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_sql_config() {
+        let config = parse(r#"{"kind":"sql","table":"nginx","dialect":"mysql"}"#);
+        let fmt = config.build().unwrap();
+        assert_eq!(
+            fmt.fmt_record(&make_record()),
+            "INSERT INTO `nginx` (`name`, `age`) VALUES ('a;b', 30);"
+        );
+
+        let err = build_err(r#"{"kind":"sql","table":""}"#);
+        assert_eq!(err.to_string(), "sql: invalid `table`: must not be empty");
+        let err = build_err(r#"{"kind":"sql","table":"t","object_format":"csv"}"#);
+        assert!(matches!(
+            err,
+            ConfigError::InvalidOption {
+                option: "object_format",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_aliases_and_defaults() {
+        assert_eq!(
+            parse(r#"{"kind":"proto"}"#),
+            FormatterConfig::ProtoText {
+                strict: false,
+                max_depth: None,
+                time: None,
+                float: None,
+                null: None,
+            }
+        );
+        assert!(matches!(
+            parse(r#"{"kind":"show"}"#).build().unwrap(),
+            FormatType::Raw(_)
+        ));
+        let err = build_err(r#"{"kind":"json","max_depth":0}"#);
+        assert!(matches!(
+            err,
+            ConfigError::InvalidOption {
+                option: "max_depth",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_shared_options_config() {
+        let mut ext = ObjectValue::new();
+        ext.insert(
+            "ua".to_string(),
+            FieldStorage::from_owned(DataField::from_chars("ua", "curl")),
+        );
+        let time =
+            chrono::NaiveDateTime::parse_from_str("2024-01-15 10:30:45", "%Y-%m-%d %H:%M:%S")
+                .unwrap();
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_time("t", time)),
+                FieldStorage::from_owned(DataField::from_float("rt", 0.12345)),
+                FieldStorage::from_owned(DataField::new(DataType::default(), "u", Value::Null)),
+                FieldStorage::from_owned(DataField::from_obj("ext", ext)),
+            ],
+        };
+
+        let config = parse(
+            r#"{"kind":"csv","time":{"format":"rfc3339","offset":"+08:00"},
+                "float":{"precision":2},"null":{"placeholder":"-"},
+                "flatten":{"separator":"/"}}"#,
+        );
+        assert_eq!(
+            config.build().unwrap().fmt_record(&record),
+            "2024-01-15T10:30:45+08:00,0.12,-,curl"
+        );
+
+        let config = parse(
+            r#"{"kind":"json","time":{"format":"epoch-seconds","offset":"Z"},
+                "float":{"significant_digits":1},"null":"omit"}"#,
+        );
+        assert_eq!(
+            config.build().unwrap().fmt_record(&record),
+            r#"{"t":1705314645,"rt":0.1,"ext":{"ua":"curl"}}"#
+        );
+
+        let config = parse(r#"{"kind":"kv","time":{"format":"%Y/%m/%d"},"flatten":{}}"#);
+        assert_eq!(
+            config.build().unwrap().fmt_record(&record),
+            r#"t: 2024/01/15, rt: 0.12345, u: , ext.ua: "curl""#
+        );
+    }
+
+    #[test]
+    fn test_shared_options_invalid() {
+        let err = build_err(r#"{"kind":"raw","time":{"format":"iso"}}"#);
+        assert!(matches!(
+            err,
+            ConfigError::InvalidOption {
+                option: "time.format",
+                ..
+            }
+        ));
+        let err = build_err(r#"{"kind":"json","time":{"offset":"Asia/Shanghai"}}"#);
+        assert_eq!(
+            err.to_string(),
+            r#"json: invalid `time.offset`: expected an offset such as "+08:00", got "Asia/Shanghai""#
+        );
+        let err = build_err(
+            r#"{"kind":"sql","table":"t","float":{"precision":2,"significant_digits":3}}"#,
+        );
+        assert!(matches!(
+            err,
+            ConfigError::InvalidOption {
+                option: "float.significant_digits",
+                ..
+            }
+        ));
+        let err = build_err(r#"{"kind":"csv","flatten":{"max_depth":0}}"#);
+        assert!(matches!(
+            err,
+            ConfigError::InvalidOption {
+                option: "flatten.max_depth",
+                ..
+            }
+        ));
+        // flatten 只适用于列式格式
+        assert!(
+            serde_json::from_str::<FormatterConfig>(r#"{"kind":"json","flatten":{}}"#).is_err()
+        );
+        assert!(
+            serde_json::from_str::<FormatterConfig>(r#"{"kind":"raw","float":{"digits":2}}"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_unknown_option_rejected() {
+        let err =
+            serde_json::from_str::<FormatterConfig>(r#"{"kind":"json","indent":2}"#).unwrap_err();
+        assert!(err.to_string().contains("indent"));
+        assert!(serde_json::from_str::<FormatterConfig>(r#"{"kind":"xml"}"#).is_err());
+    }
+}
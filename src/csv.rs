@@ -1,15 +1,23 @@
+use crate::error::FormatError;
+use crate::flatten::Flatten;
+use crate::float::{FloatAction, FloatPolicy};
 #[allow(deprecated)]
 use crate::formatter::DataFormat;
+use crate::null::NullPolicy;
+use crate::options::FmtOptions;
+use crate::time::TimeFormat;
 use crate::{
     Json,
-    formatter::{RecordFormatter, ValueFormatter},
+    formatter::{RecordFormatter, RecordWriter, ValueFormatter, ValueWriter, render},
 };
+use std::fmt;
 use wp_model_core::model::{DataRecord, DataType, FieldStorage, Value, types::value::ObjectValue};
 
 pub struct Csv {
     delimiter: char,
     quote_char: char,
     escape_char: char,
+    opts: FmtOptions,
 }
 
 impl Default for Csv {
@@ -18,6 +26,7 @@ impl Default for Csv {
             delimiter: ',',
             quote_char: '"',
             escape_char: '"',
+            opts: FmtOptions::default(),
         }
     }
 }
@@ -38,31 +47,78 @@ impl Csv {
         self.escape_char = escape_char;
         self
     }
+    /// 严格模式：不支持的值类型返回错误，而不是输出其文本形式
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.opts.strict = strict;
+        self
+    }
+    /// 嵌套值（以 JSON 单元格输出）允许的最大层数
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.opts.max_depth = max_depth;
+        self
+    }
+    /// `Value::Time` 的输出方式，默认与 `NaiveDateTime` 的 `Display` 一致
+    pub fn with_time_format(mut self, time: TimeFormat) -> Self {
+        self.opts.time = time;
+        self
+    }
+    /// 浮点数的位数、科学计数法和 NaN/Infinity 处理方式
+    pub fn with_float_policy(mut self, float: FloatPolicy) -> Self {
+        self.opts.float = float;
+        self
+    }
+    /// 空值字段的处理方式：原生空值、省略或占位文本
+    pub fn with_null_policy(mut self, null: NullPolicy) -> Self {
+        self.opts.null = null;
+        self
+    }
+    /// 将嵌套对象和数组展开为多个顶层字段，如 `extends.ssl_cipher`、`tags[0]`
+    pub fn with_flatten(mut self, flatten: Flatten) -> Self {
+        self.opts.flatten = Some(flatten);
+        self
+    }
 
-    fn escape_string(&self, value: &str, output: &mut String) {
+    fn escape_string(&self, value: &str, output: &mut dyn fmt::Write) -> fmt::Result {
         let needs_quoting = value.contains(self.delimiter)
             || value.contains('\n')
             || value.contains('\r')
             || value.contains(self.quote_char);
         if needs_quoting {
-            output.push(self.quote_char);
+            output.write_char(self.quote_char)?;
             for c in value.chars() {
                 if c == self.quote_char {
-                    output.push(self.escape_char);
+                    output.write_char(self.escape_char)?;
                 }
-                output.push(c);
+                output.write_char(c)?;
             }
-            output.push(self.quote_char);
+            output.write_char(self.quote_char)
         } else {
-            output.push_str(value);
+            output.write_str(value)
+        }
+    }
+
+    fn write_null(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        match self.opts.null.text() {
+            Some(text) => self.escape_string(text, out),
+            None => Ok(()),
         }
     }
 
-    fn format_json_cell(&self, value: &Value) -> String {
-        let json = Json;
-        let mut output = String::new();
-        self.escape_string(&json.format_value(value), &mut output);
-        output
+    /// 嵌套值先渲染为 JSON，再作为一个整体单元格转义
+    fn write_json_cell(
+        &self,
+        write: impl FnOnce(&Json, &mut String) -> Result<(), FormatError>,
+        output: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        let json = Json::new()
+            .with_strict(self.opts.strict)
+            .with_max_depth(self.opts.max_depth)
+            .with_time_format(self.opts.time.clone())
+            .with_float_policy(self.opts.float)
+            .with_null_policy(self.opts.null.clone());
+        let cell = render(|o| write(&json, o))?;
+        self.escape_string(&cell, output)?;
+        Ok(())
     }
 }
 
@@ -70,52 +126,39 @@ impl Csv {
 impl DataFormat for Csv {
     type Output = String;
     fn format_null(&self) -> String {
-        "".to_string()
+        self.format_value(&Value::Null)
     }
     fn format_bool(&self, value: &bool) -> String {
         if *value { "true" } else { "false" }.to_string()
     }
     fn format_string(&self, value: &str) -> String {
-        let mut o = String::new();
-        self.escape_string(value, &mut o);
-        o
+        render(|out| Ok(self.escape_string(value, out)?)).unwrap_or_default()
     }
     fn format_i64(&self, value: &i64) -> String {
         value.to_string()
     }
     fn format_f64(&self, value: &f64) -> String {
-        value.to_string()
+        self.format_value(&Value::Float(*value))
     }
     fn format_ip(&self, value: &std::net::IpAddr) -> String {
-        self.format_string(&value.to_string())
+        self.format_value(&Value::IpAddr(*value))
     }
     fn format_datetime(&self, value: &chrono::NaiveDateTime) -> String {
-        self.format_string(&value.to_string())
+        self.format_value(&Value::Time(*value))
     }
     fn format_object(&self, value: &ObjectValue) -> String {
-        self.format_json_cell(&Value::Obj(value.clone()))
+        render(|out| self.write_json_cell(|json, o| json.write_object(value, o), out))
+            .unwrap_or_default()
     }
     fn format_array(&self, value: &[FieldStorage]) -> String {
-        self.format_json_cell(&Value::Array(value.to_vec()))
+        render(|out| self.write_json_cell(|json, o| json.write_array(value, o), out))
+            .unwrap_or_default()
     }
     fn format_field(&self, field: &FieldStorage) -> String {
-        self.fmt_value(field.get_value())
+        self.fmt_field(field)
     }
     fn format_record(&self, record: &DataRecord) -> String {
-        let mut output = String::new();
-        let mut first = true;
-        for field in record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-        {
-            if !first {
-                output.push(self.delimiter);
-            }
-            first = false;
-            output.push_str(&self.format_field(field));
-        }
-        output
+        self.fmt_record(record)
     }
 }
 
@@ -325,6 +368,23 @@ mod tests {
         let record = make_record_with_obj();
         assert_eq!(csv.format_record(&record), csv.fmt_record(&record));
     }
+
+    #[test]
+    fn test_strict_ignore() {
+        let csv = Csv::default().with_strict(true);
+        let err = csv
+            .try_format_value(&Value::Ignore(Default::default()))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            FormatError::UnsupportedType { format: "csv", .. }
+        ));
+        // 非有限浮点数在 CSV 中可以用文本表示，严格模式下也不报错
+        assert_eq!(
+            csv.try_format_value(&Value::Float(f64::NAN)).unwrap(),
+            "NaN"
+        );
+    }
 }
 
 // ============================================================================
@@ -332,59 +392,93 @@ mod tests {
 // ============================================================================
 
 #[allow(clippy::items_after_test_module)]
-impl ValueFormatter for Csv {
-    type Output = String;
-
-    fn format_value(&self, value: &Value) -> String {
+impl ValueWriter for Csv {
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
         match value {
-            Value::Null => String::new(),
-            Value::Bool(v) => if *v { "true" } else { "false" }.to_string(),
-            Value::Chars(v) => {
-                let mut o = String::new();
-                self.escape_string(v, &mut o);
-                o
-            }
-            Value::Digit(v) => v.to_string(),
-            Value::Float(v) => v.to_string(),
-            Value::IpAddr(v) => {
-                let mut o = String::new();
-                self.escape_string(&v.to_string(), &mut o);
-                o
+            Value::Null => self.write_null(out)?,
+            Value::Bool(v) => out.write_str(if *v { "true" } else { "false" })?,
+            Value::Chars(v) => self.escape_string(v, out)?,
+            Value::Digit(v) => write!(out, "{}", v)?,
+            Value::Float(v) => match self.opts.float.action(*v) {
+                FloatAction::Native => write!(out, "{}", v)?,
+                FloatAction::Finite => self.opts.float.write_finite(*v, out)?,
+                FloatAction::Null => self.write_null(out)?,
+                FloatAction::Text(text) => self.escape_string(text, out)?,
+                FloatAction::Error => return Err(FormatError::non_finite("csv", *v)),
+            },
+            Value::Obj(_) | Value::Array(_) => {
+                self.write_json_cell(|json, o| json.write_value(value, o), out)?
             }
-            Value::Time(v) => {
-                let mut o = String::new();
-                self.escape_string(&v.to_string(), &mut o);
-                o
-            }
-            Value::Obj(_) | Value::Array(_) => self.format_json_cell(value),
-            _ => {
-                let mut o = String::new();
-                self.escape_string(&value.to_string(), &mut o);
-                o
+            Value::Time(v) => self
+                .opts
+                .time
+                .write_quoted(v, out, |s, o| self.escape_string(s, o))?,
+            Value::IpNet(_)
+            | Value::IpAddr(_)
+            | Value::Domain(_)
+            | Value::Url(_)
+            | Value::Email(_)
+            | Value::IdCard(_)
+            | Value::MobilePhone(_)
+            | Value::Hex(_)
+            | Value::Symbol(_) => self.escape_string(&value.to_string(), out)?,
+            Value::Ignore(_) => {
+                self.opts
+                    .substitute(|| FormatError::unsupported("csv", value))?;
             }
         }
+        Ok(())
     }
 }
 
-impl RecordFormatter for Csv {
-    fn fmt_field(&self, field: &FieldStorage) -> String {
-        self.format_value(field.get_value())
-    }
-
-    fn fmt_record(&self, record: &DataRecord) -> String {
-        let mut output = String::new();
-        let mut first = true;
-        for field in record
+impl RecordWriter for Csv {
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        let record = self.opts.flattened(record)?;
+        for (i, field) in record
             .items
             .iter()
             .filter(|f| *f.get_meta() != DataType::Ignore)
+            .enumerate()
         {
-            if !first {
-                output.push(self.delimiter);
+            if i > 0 {
+                out.write_char(self.delimiter)?;
             }
-            first = false;
-            output.push_str(&self.fmt_field(field));
+            self.write_field(field, out)?;
         }
-        output
+        Ok(())
+    }
+}
+
+impl ValueFormatter for Csv {
+    type Output = String;
+
+    fn format_value(&self, value: &Value) -> String {
+        self.try_format_value(value).unwrap_or_default()
+    }
+
+    fn try_format_value(&self, value: &Value) -> Result<String, FormatError> {
+        render(|out| self.write_value(value, out))
+    }
+}
+
+impl RecordFormatter for Csv {
+    fn fmt_field(&self, field: &FieldStorage) -> String {
+        self.try_fmt_field(field).unwrap_or_default()
+    }
+
+    fn fmt_record(&self, record: &DataRecord) -> String {
+        self.try_fmt_record(record).unwrap_or_default()
+    }
+
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<String, FormatError> {
+        render(|out| self.write_field(field, out))
+    }
+
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<String, FormatError> {
+        render(|out| self.write_record_fmt(record, out))
     }
 }
@@ -0,0 +1,120 @@
+use std::{fmt, io};
+
+use thiserror::Error;
+use wp_model_core::model::Value;
+
+/// 格式化错误
+///
+/// 宽松模式（默认）下只会出现 `DepthExceeded` 和 I/O 相关错误；
+/// 严格模式下，原本会被静默替换的值也会以错误形式返回。
+#[derive(Debug, Error)]
+pub enum FormatError {
+    /// 值无法在目标格式中表示，例如 JSON 中的 NaN
+    #[error("{format}: cannot represent {detail}")]
+    Unrepresentable {
+        format: &'static str,
+        detail: String,
+    },
+    /// 目标格式不支持该值类型
+    #[error("{format}: unsupported value type `{type_name}`")]
+    UnsupportedType {
+        format: &'static str,
+        type_name: &'static str,
+    },
+    /// 嵌套层数超过限制
+    #[error("nesting depth exceeds limit {limit}")]
+    DepthExceeded { limit: usize },
+    /// 展平后出现同名字段
+    #[error("duplicate field `{name}` after flattening")]
+    DuplicateField { name: String },
+    /// 缺少输出所需的字段，或字段值为空
+    #[error("{format}: missing required field `{name}`")]
+    MissingField { format: &'static str, name: String },
+    /// 写入 `io::Write` 失败
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    /// 写入 `fmt::Write` 失败
+    #[error("write error")]
+    Fmt(#[from] fmt::Error),
+}
+
+impl FormatError {
+    pub(crate) fn unsupported(format: &'static str, value: &Value) -> Self {
+        FormatError::UnsupportedType {
+            format,
+            type_name: value_type_name(value),
+        }
+    }
+
+    pub(crate) fn non_finite(format: &'static str, value: f64) -> Self {
+        FormatError::Unrepresentable {
+            format,
+            detail: format!("non-finite float {}", value),
+        }
+    }
+}
+
+/// 格式化器配置错误
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ConfigError {
+    /// 选项取值不合法
+    #[error("{kind}: invalid `{option}`: {reason}")]
+    InvalidOption {
+        kind: &'static str,
+        option: &'static str,
+        reason: String,
+    },
+}
+
+/// 值类型名称，用于错误信息
+pub(crate) fn value_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Chars(_) => "chars",
+        Value::Float(_) => "float",
+        Value::Digit(_) => "digit",
+        Value::Time(_) => "time",
+        Value::IpNet(_) => "ip_net",
+        Value::IpAddr(_) => "ip",
+        Value::Domain(_) => "domain",
+        Value::Url(_) => "url",
+        Value::Email(_) => "email",
+        Value::IdCard(_) => "id_card",
+        Value::MobilePhone(_) => "mobile_phone",
+        Value::Hex(_) => "hex",
+        Value::Obj(_) => "obj",
+        Value::Array(_) => "array",
+        Value::Symbol(_) => "symbol",
+        Value::Ignore(_) => "ignore",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_display() {
+        let err = FormatError::Unrepresentable {
+            format: "json",
+            detail: "NaN".into(),
+        };
+        assert_eq!(err.to_string(), "json: cannot represent NaN");
+
+        let err = FormatError::UnsupportedType {
+            format: "csv",
+            type_name: value_type_name(&Value::Ignore(Default::default())),
+        };
+        assert_eq!(err.to_string(), "csv: unsupported value type `ignore`");
+
+        let err = FormatError::DepthExceeded { limit: 2 };
+        assert_eq!(err.to_string(), "nesting depth exceeds limit 2");
+    }
+
+    #[test]
+    fn test_error_from_io() {
+        let err: FormatError = io::Error::other("closed").into();
+        assert!(matches!(err, FormatError::Io(_)));
+    }
+}
@@ -0,0 +1,119 @@
+use std::fmt;
+
+use sha2::{Digest, Sha256};
+use wp_model_core::model::DataRecord;
+
+use crate::Json;
+use crate::error::FormatError;
+use crate::formatter::{IoSink, RecordWriter};
+
+/// 记录指纹：规范 JSON 编码的 SHA-256
+///
+/// 内容相同的记录得到相同指纹，与字段顺序和对象键的存储顺序无关；
+/// 记录 `id` 不参与计算。`Display` 输出 64 位小写十六进制。
+///
+/// ```
+/// use wp_data_fmt::{Fingerprint, IntegerPolicy, Json};
+/// # let record = wp_model_core::model::DataRecord { id: Default::default(), items: vec![] };
+///
+/// let id = Fingerprint::of(&record).unwrap().to_string();
+/// let json = Json::default().with_integer_policy(IntegerPolicy::unsafe_as_string());
+/// let strict = json.fingerprint(&record).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint([u8; 32]);
+
+impl Fingerprint {
+    /// 以 `Json::canonical()` 的编码计算指纹
+    pub fn of(record: &DataRecord) -> Result<Self, FormatError> {
+        Json::canonical().fingerprint(record)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+impl Json {
+    /// 以当前选项的规范编码（强制 `with_canonical(true)`）计算记录指纹，
+    /// 编码直接写入摘要，不生成中间字符串
+    pub fn fingerprint(&self, record: &DataRecord) -> Result<Fingerprint, FormatError> {
+        let canonical;
+        let json = if self.is_canonical() {
+            self
+        } else {
+            canonical = self.clone().with_canonical(true);
+            &canonical
+        };
+        let mut hasher = Sha256::new();
+        let mut sink = IoSink::new(&mut hasher);
+        json.write_record_fmt(record, &mut sink)
+            .map_err(|e| sink.map_error(e))?;
+        Ok(Fingerprint(hasher.finalize().into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IntegerPolicy;
+    use wp_model_core::model::types::value::ObjectValue;
+    use wp_model_core::model::{DataField, FieldStorage};
+
+    fn make_record(reversed: bool) -> DataRecord {
+        let mut items = vec![
+            FieldStorage::from_owned(DataField::from_chars("host", "web01")),
+            FieldStorage::from_owned(DataField::from_digit("status", 200)),
+        ];
+        if reversed {
+            items.reverse();
+        }
+        DataRecord {
+            id: if reversed { 2 } else { 1 },
+            items,
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_stable() {
+        let a = Fingerprint::of(&make_record(false)).unwrap();
+        let b = Fingerprint::of(&make_record(true)).unwrap();
+        assert_eq!(a, b);
+        // sha256(`{"host":"web01","status":200}`)
+        let expected = {
+            let digest = Sha256::digest(br#"{"host":"web01","status":200}"#);
+            Fingerprint(digest.into())
+        };
+        assert_eq!(a, expected);
+        assert_eq!(a.to_string().len(), 64);
+        assert_eq!(Json::pretty(2).fingerprint(&make_record(true)).unwrap(), a);
+    }
+
+    #[test]
+    fn test_fingerprint_differs() {
+        let mut record = make_record(false);
+        let a = Fingerprint::of(&record).unwrap();
+        record.items[1] = FieldStorage::from_owned(DataField::from_digit("status", 404));
+        assert_ne!(Fingerprint::of(&record).unwrap(), a);
+
+        let quoted = Json::default().with_integer_policy(IntegerPolicy::all_as_string());
+        assert_ne!(quoted.fingerprint(&make_record(false)).unwrap(), a);
+
+        let mut obj = ObjectValue::new();
+        obj.insert(
+            "status".to_string(),
+            FieldStorage::from_owned(DataField::from_digit("status", 200)),
+        );
+        let nested = DataRecord {
+            id: 1,
+            items: vec![FieldStorage::from_owned(DataField::from_obj("http", obj))],
+        };
+        assert_ne!(Fingerprint::of(&nested).unwrap(), a);
+    }
+}
@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use wp_model_core::model::{DataRecord, DataType, FieldStorage, Value};
+
+use crate::error::FormatError;
+use crate::options::DEFAULT_MAX_DEPTH;
+
+/// 展平后字段重名时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Collision {
+    /// 返回 `FormatError::DuplicateField`
+    #[default]
+    Error,
+    /// 保留先出现的字段
+    KeepFirst,
+    /// 保留后出现的值，位置不变
+    KeepLast,
+    /// 后出现的字段追加 `_2`、`_3` 等后缀
+    Suffix,
+}
+
+/// 嵌套对象展平策略，供 CSV、SQL 和 KV 使用
+///
+/// 对象成员以分隔符连接，如 `extends.ssl_cipher`；数组元素以下标命名，如 `tags[0]`。
+/// 超过 `max_depth` 的嵌套值以及空对象/空数组保持原样，由格式化器按原有方式输出。
+///
+/// ```
+/// use wp_data_fmt::{Collision, Csv, Flatten};
+///
+/// let csv = Csv::default().with_flatten(
+///     Flatten::new()
+///         .with_separator("/")
+///         .with_max_depth(2)
+///         .with_collision(Collision::Suffix),
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Flatten {
+    separator: String,
+    max_depth: usize,
+    collision: Collision,
+}
+
+impl Default for Flatten {
+    fn default() -> Self {
+        Self {
+            separator: ".".to_string(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            collision: Collision::default(),
+        }
+    }
+}
+
+impl Flatten {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 对象成员的名称分隔符，默认 `.`
+    pub fn with_separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// 最多展开的嵌套层数，`1` 表示只展开顶层字段的直接成员
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_collision(mut self, collision: Collision) -> Self {
+        self.collision = collision;
+        self
+    }
+
+    /// 展平记录，Ignore 字段会被丢弃
+    pub fn flatten(&self, record: &DataRecord) -> Result<DataRecord, FormatError> {
+        let mut out = Output::default();
+        for field in record
+            .items
+            .iter()
+            .filter(|f| *f.get_meta() != DataType::Ignore)
+        {
+            self.expand(field.get_name().to_string(), field, 0, &mut out)?;
+        }
+        Ok(DataRecord {
+            id: record.id,
+            items: out.items,
+        })
+    }
+
+    fn expand(
+        &self,
+        name: String,
+        field: &FieldStorage,
+        depth: usize,
+        out: &mut Output,
+    ) -> Result<(), FormatError> {
+        if depth < self.max_depth {
+            match field.get_value() {
+                Value::Obj(obj) if !obj.is_empty() => {
+                    for (key, child) in obj.iter() {
+                        if *child.get_meta() != DataType::Ignore {
+                            let child_name = format!("{}{}{}", name, self.separator, key);
+                            self.expand(child_name, child, depth + 1, out)?;
+                        }
+                    }
+                    return Ok(());
+                }
+                Value::Array(arr) if !arr.is_empty() => {
+                    for (i, child) in arr.iter().enumerate() {
+                        self.expand(format!("{}[{}]", name, i), child, depth + 1, out)?;
+                    }
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+        self.push(name, field, out)
+    }
+
+    fn push(
+        &self,
+        name: String,
+        field: &FieldStorage,
+        out: &mut Output,
+    ) -> Result<(), FormatError> {
+        let mut field = field.clone();
+        let Some(&pos) = out.index.get(&name) else {
+            out.insert(name, field);
+            return Ok(());
+        };
+        match self.collision {
+            Collision::Error => Err(FormatError::DuplicateField { name }),
+            Collision::KeepFirst => Ok(()),
+            Collision::KeepLast => {
+                field.set_name(name);
+                out.items[pos] = field;
+                Ok(())
+            }
+            Collision::Suffix => {
+                let name = (2..)
+                    .map(|n| format!("{}_{}", name, n))
+                    .find(|candidate| !out.index.contains_key(candidate))
+                    .unwrap_or(name);
+                out.insert(name, field);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// 展平结果及名称索引
+#[derive(Default)]
+struct Output {
+    items: Vec<FieldStorage>,
+    index: HashMap<String, usize>,
+}
+
+impl Output {
+    fn insert(&mut self, name: String, mut field: FieldStorage) {
+        field.set_name(name.as_str());
+        self.index.insert(name, self.items.len());
+        self.items.push(field);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wp_model_core::model::DataField;
+    use wp_model_core::model::types::value::ObjectValue;
+
+    fn make_record() -> DataRecord {
+        let mut inner = ObjectValue::new();
+        inner.insert(
+            "cipher".to_string(),
+            FieldStorage::from_owned(DataField::from_chars("cipher", "AES")),
+        );
+        let mut extends = ObjectValue::new();
+        extends.insert(
+            "ssl".to_string(),
+            FieldStorage::from_owned(DataField::from_obj("ssl", inner)),
+        );
+        extends.insert(
+            "port".to_string(),
+            FieldStorage::from_owned(DataField::from_digit("port", 443)),
+        );
+        DataRecord {
+            id: 3,
+            items: vec![
+                FieldStorage::from_owned(DataField::from_chars("host", "web01")),
+                FieldStorage::from_owned(DataField::from_obj("extends", extends)),
+                FieldStorage::from_owned(DataField::from_arr(
+                    "tags",
+                    vec![
+                        DataField::from_chars("", "a"),
+                        DataField::from_chars("", "b"),
+                    ],
+                )),
+            ],
+        }
+    }
+
+    fn names(record: &DataRecord) -> Vec<&str> {
+        record.items.iter().map(|f| f.get_name()).collect()
+    }
+
+    #[test]
+    fn test_flatten_default() {
+        let flat = Flatten::new().flatten(&make_record()).unwrap();
+        assert_eq!(flat.id, 3);
+        assert_eq!(
+            names(&flat),
+            [
+                "host",
+                "extends.port",
+                "extends.ssl.cipher",
+                "tags[0]",
+                "tags[1]"
+            ]
+        );
+        assert_eq!(flat.items[2].get_value(), &Value::Chars("AES".into()));
+    }
+
+    #[test]
+    fn test_flatten_separator_and_depth() {
+        let flat = Flatten::new()
+            .with_separator("/")
+            .with_max_depth(1)
+            .flatten(&make_record())
+            .unwrap();
+        assert_eq!(
+            names(&flat),
+            ["host", "extends/port", "extends/ssl", "tags[0]", "tags[1]"]
+        );
+        assert!(matches!(flat.items[2].get_value(), Value::Obj(_)));
+    }
+
+    #[test]
+    fn test_empty_containers_kept() {
+        let record = DataRecord {
+            id: 0,
+            items: vec![FieldStorage::from_owned(DataField::from_obj(
+                "empty",
+                ObjectValue::new(),
+            ))],
+        };
+        let flat = Flatten::new().flatten(&record).unwrap();
+        assert_eq!(names(&flat), ["empty"]);
+    }
+
+    #[test]
+    fn test_collision() {
+        let mut record = make_record();
+        record
+            .items
+            .push(FieldStorage::from_owned(DataField::from_digit(
+                "extends.port",
+                8080,
+            )));
+
+        let err = Flatten::new().flatten(&record).unwrap_err();
+        assert!(matches!(err, FormatError::DuplicateField { ref name } if name == "extends.port"));
+
+        let first = Flatten::new()
+            .with_collision(Collision::KeepFirst)
+            .flatten(&record)
+            .unwrap();
+        assert_eq!(first.items.len(), 5);
+        assert_eq!(first.items[1].get_value(), &Value::Digit(443));
+
+        let last = Flatten::new()
+            .with_collision(Collision::KeepLast)
+            .flatten(&record)
+            .unwrap();
+        assert_eq!(last.items[1].get_name(), "extends.port");
+        assert_eq!(last.items[1].get_value(), &Value::Digit(8080));
+
+        let suffix = Flatten::new()
+            .with_collision(Collision::Suffix)
+            .flatten(&record)
+            .unwrap();
+        assert_eq!(suffix.items[5].get_name(), "extends.port_2");
+    }
+}
@@ -0,0 +1,358 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// 有限浮点数的位数控制
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum Digits {
+    /// 各格式的原生输出（最短往返表示）
+    #[default]
+    Shortest,
+    /// 固定小数位数
+    Fixed(usize),
+    /// 有效数字位数
+    Significant(usize),
+}
+
+/// NaN / Infinity 的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NonFinite {
+    /// 保持各格式原有行为：JSON 输出 `null`/`"Infinity"`，SQL 输出 `NULL`/`'Infinity'`，
+    /// CSV/KV/Raw/ProtoText 输出 `NaN`/`inf`
+    #[default]
+    Native,
+    /// 输出该格式的空值
+    Null,
+    /// 以字符串输出 `NaN`、`Infinity` 或 `-Infinity`
+    String,
+    /// 返回 `FormatError::Unrepresentable`
+    Error,
+    /// 省略所在字段（对象中省略键，数组中省略元素）；
+    /// CSV 和 SQL 需要保持列对齐，按 `Null` 处理
+    Skip,
+}
+
+/// 浮点数输出策略，由所有格式化器共享
+///
+/// 默认策略与各格式原有输出完全一致。
+///
+/// ```
+/// use wp_data_fmt::{FloatPolicy, NonFinite};
+///
+/// let policy = FloatPolicy::new()
+///     .with_precision(2)
+///     .with_scientific(1e-4, 1e15)
+///     .with_non_finite(NonFinite::Null);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloatPolicy {
+    digits: Digits,
+    scientific: Option<(f64, f64)>,
+    non_finite: NonFinite,
+}
+
+/// 格式化器对单个浮点数应采取的动作
+pub(crate) enum FloatAction {
+    /// 使用格式原有的输出
+    Native,
+    /// 通过 `FloatPolicy::write_finite` 输出
+    Finite,
+    /// 输出该格式的空值
+    Null,
+    /// 以字符串输出
+    Text(&'static str),
+    /// 报错
+    Error,
+}
+
+impl Default for FloatPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl FloatPolicy {
+    pub(crate) const DEFAULT: Self = Self {
+        digits: Digits::Shortest,
+        scientific: None,
+        non_finite: NonFinite::Native,
+    };
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 固定小数位数，如精度 2 时 `12.3456` 输出 `12.35`
+    pub fn with_precision(mut self, decimals: usize) -> Self {
+        self.digits = Digits::Fixed(decimals);
+        self
+    }
+
+    /// 保留有效数字位数（至少 1 位），如 3 位时 `123456.0` 输出 `123000`；
+    /// 绝对值不小于 `1e21` 或小于 `1e-6` 时改用科学计数法，如 `1.23e25`，`0` 输出 `0`
+    pub fn with_significant_digits(mut self, digits: usize) -> Self {
+        self.digits = Digits::Significant(digits.max(1));
+        self
+    }
+
+    /// 绝对值小于 `lower` 或不小于 `upper` 的非零值使用科学计数法，如 `1.5e-7`
+    pub fn with_scientific(mut self, lower: f64, upper: f64) -> Self {
+        self.scientific = Some((lower, upper));
+        self
+    }
+
+    pub fn with_non_finite(mut self, non_finite: NonFinite) -> Self {
+        self.non_finite = non_finite;
+        self
+    }
+
+    pub fn non_finite(&self) -> NonFinite {
+        self.non_finite
+    }
+
+    /// 值是否应从所在的对象、数组或记录中省略
+    pub(crate) fn skips(&self, value: f64) -> bool {
+        !value.is_finite() && self.non_finite == NonFinite::Skip
+    }
+
+    pub(crate) fn action(&self, value: f64) -> FloatAction {
+        if value.is_finite() {
+            if self.digits == Digits::Shortest && self.scientific.is_none() {
+                FloatAction::Native
+            } else {
+                FloatAction::Finite
+            }
+        } else {
+            match self.non_finite {
+                NonFinite::Native => FloatAction::Native,
+                NonFinite::Null | NonFinite::Skip => FloatAction::Null,
+                NonFinite::String => FloatAction::Text(non_finite_name(value)),
+                NonFinite::Error => FloatAction::Error,
+            }
+        }
+    }
+
+    /// 按位数和科学计数法设置写出有限值
+    pub(crate) fn write_finite(&self, value: f64, out: &mut dyn fmt::Write) -> fmt::Result {
+        let scientific = self
+            .scientific
+            .is_some_and(|(lower, upper)| value != 0.0 && !(lower..upper).contains(&value.abs()));
+        match (self.digits, scientific) {
+            (Digits::Shortest, false) => write!(out, "{}", value),
+            (Digits::Shortest, true) => write!(out, "{:e}", value),
+            (Digits::Fixed(decimals), false) => write!(out, "{:.*}", decimals, value),
+            (Digits::Fixed(decimals), true) => write!(out, "{:.*e}", decimals, value),
+            (Digits::Significant(digits), true) => write!(out, "{:.*e}", digits - 1, value),
+            (Digits::Significant(_), false) if value == 0.0 => out.write_char('0'),
+            (Digits::Significant(digits), false) => {
+                // 先按科学计数法舍入，再用舍入后的有效数字和指数拼出定点小数，
+                // 不经过 f64 回转，避免 `1.23e25` 展开出二进制误差位
+                let rounded = format!("{:.*e}", digits - 1, value.abs());
+                let (mantissa, exp) = rounded.split_once('e').unwrap_or((&rounded, "0"));
+                let digits_text = mantissa.replace('.', "");
+                // 小数点位于第 n 位有效数字之后
+                let n = exp.parse::<i64>().unwrap_or(0) + 1;
+                if !(-6 < n && n <= 21) {
+                    return write!(out, "{:.*e}", digits - 1, value);
+                }
+                if value < 0.0 {
+                    out.write_char('-')?;
+                }
+                write_point(&digits_text, n, out)
+            }
+        }
+    }
+}
+
+/// 在第 `n` 位有效数字之后放置小数点，`n <= 0` 时补前导零，超出有效数字的整数位补零
+fn write_point(digits: &str, n: i64, out: &mut dyn fmt::Write) -> fmt::Result {
+    let k = digits.len() as i64;
+    if k <= n {
+        out.write_str(digits)?;
+        (k..n).try_for_each(|_| out.write_char('0'))
+    } else if n > 0 {
+        let (int, frac) = digits.split_at(n as usize);
+        write!(out, "{}.{}", int, frac)
+    } else {
+        out.write_str("0.")?;
+        (n..0).try_for_each(|_| out.write_char('0'))?;
+        out.write_str(digits)
+    }
+}
+
+/// 非有限值的字符串形式
+pub(crate) fn non_finite_name(value: f64) -> &'static str {
+    if value.is_nan() {
+        "NaN"
+    } else if value.is_sign_positive() {
+        "Infinity"
+    } else {
+        "-Infinity"
+    }
+}
+
+/// 按 ECMAScript `Number.prototype.toString` 写出有限值，即 RFC 8785 (JCS) 的数字格式：
+/// 最短往返表示，`1e21` 及以上或小于 `1e-6` 时使用 `1e+21` 形式的科学计数法，`-0` 输出 `0`
+pub(crate) fn write_ecmascript(value: f64, out: &mut dyn fmt::Write) -> fmt::Result {
+    if value == 0.0 {
+        return out.write_char('0');
+    }
+    if value < 0.0 {
+        out.write_char('-')?;
+    }
+    // `{:e}` 给出最短往返的有效数字，如 `1.2345e-7`
+    let sci = format!("{:e}", value.abs());
+    let (mantissa, exp) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i64;
+    // 小数点位于第 n 位有效数字之后
+    let n = exp.parse::<i64>().unwrap_or(0) + 1;
+    if k <= n && n <= 21 {
+        out.write_str(&digits)?;
+        (k..n).try_for_each(|_| out.write_char('0'))
+    } else if 0 < n && n <= 21 {
+        let (int, frac) = digits.split_at(n as usize);
+        write!(out, "{}.{}", int, frac)
+    } else if -6 < n && n <= 0 {
+        out.write_str("0.")?;
+        (n..0).try_for_each(|_| out.write_char('0'))?;
+        out.write_str(&digits)
+    } else {
+        let (first, rest) = digits.split_at(1);
+        out.write_str(first)?;
+        if !rest.is_empty() {
+            write!(out, ".{}", rest)?;
+        }
+        write!(out, "e{}{}", if n > 0 { '+' } else { '-' }, (n - 1).abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(policy: FloatPolicy, value: f64) -> String {
+        let mut out = String::new();
+        policy.write_finite(value, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_default_is_native() {
+        let policy = FloatPolicy::default();
+        assert!(matches!(policy.action(1.5), FloatAction::Native));
+        assert!(matches!(policy.action(f64::NAN), FloatAction::Native));
+        assert!(!policy.skips(f64::NAN));
+    }
+
+    #[test]
+    fn test_precision() {
+        let policy = FloatPolicy::new().with_precision(2);
+        assert_eq!(render(policy, 12.3456), "12.35");
+        assert_eq!(render(policy, 1.0), "1.00");
+        assert_eq!(render(policy, -0.005), "-0.01");
+    }
+
+    #[test]
+    fn test_significant_digits() {
+        let policy = FloatPolicy::new().with_significant_digits(3);
+        assert_eq!(render(policy, 123456.0), "123000");
+        assert_eq!(render(policy, 0.00012345), "0.000123");
+        assert_eq!(render(policy, 9.996), "10.0");
+        assert_eq!(render(policy, -0.5), "-0.500");
+        assert_eq!(render(policy, 0.0), "0");
+        assert_eq!(render(policy, -0.0), "0");
+    }
+
+    #[test]
+    fn test_significant_digits_extremes() {
+        let policy = FloatPolicy::new().with_significant_digits(3);
+        // 大数不展开二进制误差位
+        assert_eq!(render(policy, 1.23456e20), "123000000000000000000");
+        assert_eq!(render(policy, 1.23456e25), "1.23e25");
+        assert_eq!(render(policy, 1e300), "1.00e300");
+        assert_eq!(render(policy, -9.999e20), "-1.00e21");
+        // 极小值
+        assert_eq!(render(policy, 1.5e-6), "0.00000150");
+        assert_eq!(render(policy, -1.5e-7), "-1.50e-7");
+        assert_eq!(render(policy, 5e-324), "4.94e-324");
+    }
+
+    #[test]
+    fn test_scientific() {
+        let policy = FloatPolicy::new().with_scientific(1e-3, 1e6);
+        assert_eq!(render(policy, 1.5e-7), "1.5e-7");
+        assert_eq!(render(policy, 2.5e8), "2.5e8");
+        assert_eq!(render(policy, 42.5), "42.5");
+        assert_eq!(render(policy, 0.0), "0");
+
+        let policy = policy.with_significant_digits(2);
+        assert_eq!(render(policy, 123456789.0), "1.2e8");
+        let policy = FloatPolicy::new()
+            .with_precision(1)
+            .with_scientific(1e-3, 1e6);
+        assert_eq!(render(policy, 1.24e-5), "1.2e-5");
+    }
+
+    #[test]
+    fn test_non_finite_actions() {
+        let policy = FloatPolicy::new().with_non_finite(NonFinite::String);
+        assert!(matches!(
+            policy.action(f64::NEG_INFINITY),
+            FloatAction::Text("-Infinity")
+        ));
+        let policy = FloatPolicy::new().with_non_finite(NonFinite::Skip);
+        assert!(policy.skips(f64::NAN));
+        assert!(!policy.skips(1.0));
+        assert!(matches!(policy.action(f64::NAN), FloatAction::Null));
+        let policy = FloatPolicy::new().with_non_finite(NonFinite::Error);
+        assert!(matches!(policy.action(f64::INFINITY), FloatAction::Error));
+    }
+
+    #[test]
+    fn test_ecmascript() {
+        let render = |value: f64| {
+            let mut out = String::new();
+            write_ecmascript(value, &mut out).unwrap();
+            out
+        };
+        assert_eq!(render(0.0), "0");
+        assert_eq!(render(-0.0), "0");
+        assert_eq!(render(1.0), "1");
+        assert_eq!(render(-1.5), "-1.5");
+        assert_eq!(render(123.456), "123.456");
+        assert_eq!(render(1e20), "100000000000000000000");
+        assert_eq!(render(1e21), "1e+21");
+        assert_eq!(render(1.5e300), "1.5e+300");
+        assert_eq!(render(0.000001), "0.000001");
+        assert_eq!(render(1.5e-7), "1.5e-7");
+        assert_eq!(render(5e-324), "5e-324");
+        assert_eq!(render(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(render(9007199254740993.0), "9007199254740992");
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_format_f64_matches_policy() {
+        use crate::formatter::{DataFormat, ValueFormatter};
+        use crate::{Csv, FormatType, KeyValue, ProtoTxt, Raw};
+        use wp_model_core::model::Value;
+
+        let policy = FloatPolicy::new().with_precision(2);
+        let formats = [
+            FormatType::Csv(Csv::default().with_float_policy(policy)),
+            FormatType::Kv(KeyValue::default().with_float_policy(policy)),
+            FormatType::Raw(Raw::default().with_float_policy(policy)),
+            FormatType::ProtoText(ProtoTxt::default().with_float_policy(policy)),
+        ];
+        for fmt in &formats {
+            for v in [1.2345, f64::NAN, f64::INFINITY] {
+                assert_eq!(
+                    DataFormat::format_f64(fmt, &v),
+                    ValueFormatter::format_value(fmt, &Value::Float(v))
+                );
+            }
+        }
+        assert_eq!(DataFormat::format_f64(&formats[0], &1.2345), "1.23");
+    }
+}
@@ -1,6 +1,8 @@
+use std::{fmt, io};
+
 use wp_model_core::model::{DataRecord, FieldStorage, Value, types::value::ObjectValue};
 
-use crate::{FormatType, SqlFormat};
+use crate::{BoxedFormatter, FormatError, FormatType, SqlFormat};
 
 // ============================================================================
 // 新设计：简化统一的 Formatter trait
@@ -14,6 +16,14 @@ pub trait ValueFormatter {
 
     /// 格式化任意值
     fn format_value(&self, value: &Value) -> Self::Output;
+
+    /// 可失败的格式化
+    ///
+    /// 默认实现直接包装 `format_value`；内置格式化器会在此返回严格模式、
+    /// 嵌套深度等错误，而 `format_value` 在出错时返回空输出。
+    fn try_format_value(&self, value: &Value) -> Result<Self::Output, FormatError> {
+        Ok(self.format_value(value))
+    }
 }
 
 /// 扩展 trait：记录格式化器
@@ -35,6 +45,104 @@ pub trait RecordFormatter: ValueFormatter {
     /// - CSV: `Alice,30`
     /// - KV: `name: "Alice", age: 30`
     fn fmt_record(&self, record: &DataRecord) -> Self::Output;
+
+    /// 可失败的字段格式化，默认实现直接包装 `fmt_field`
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<Self::Output, FormatError> {
+        Ok(self.fmt_field(field))
+    }
+
+    /// 可失败的记录格式化，默认实现直接包装 `fmt_record`
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<Self::Output, FormatError> {
+        Ok(self.fmt_record(record))
+    }
+}
+
+// ============================================================================
+// 流式接口：直接写入调用方提供的 sink
+// ============================================================================
+
+/// 流式值格式化器
+///
+/// 与 `ValueFormatter` 语义一致，但直接写入 `fmt::Write`，不为每个值分配 `String`。
+/// 内置格式化器的 `format_value` 都是对 `write_value` 的薄封装。
+pub trait ValueWriter {
+    /// 将值写入 `out`
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError>;
+}
+
+/// 流式记录格式化器
+///
+/// 在 `ValueWriter` 基础上提供字段与记录的流式输出。
+pub trait RecordWriter: ValueWriter {
+    /// 将单个字段写入 `out`
+    ///
+    /// 默认实现：只写字段的值，忽略字段名
+    fn write_field(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        self.write_value(field.get_value(), out)
+    }
+
+    /// 将整条记录写入 `fmt::Write`
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError>;
+
+    /// 将整条记录写入 `io::Write`
+    ///
+    /// 每个片段都会直接写入 `out`，写文件或 socket 时建议包一层 `BufWriter`。
+    /// 底层写入失败时返回 `FormatError::Io`。
+    fn write_record(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn io::Write,
+    ) -> Result<(), FormatError> {
+        let mut sink = IoSink::new(out);
+        self.write_record_fmt(record, &mut sink)
+            .map_err(|e| sink.map_error(e))
+    }
+}
+
+/// 将 `io::Write` 适配为 `fmt::Write`，并保留底层的 I/O 错误
+pub(crate) struct IoSink<'a> {
+    inner: &'a mut dyn io::Write,
+    error: Option<io::Error>,
+}
+
+impl<'a> IoSink<'a> {
+    pub(crate) fn new(inner: &'a mut dyn io::Write) -> Self {
+        Self { inner, error: None }
+    }
+
+    /// 将写入失败引起的 `Fmt` 错误还原为底层的 I/O 错误
+    pub(crate) fn map_error(&mut self, err: FormatError) -> FormatError {
+        match (err, self.error.take()) {
+            (FormatError::Fmt(_), Some(io)) => FormatError::Io(io),
+            (err, _) => err,
+        }
+    }
+}
+
+impl fmt::Write for IoSink<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+/// 通过流式接口生成 `String`
+pub(crate) fn render(
+    write: impl FnOnce(&mut String) -> Result<(), FormatError>,
+) -> Result<String, FormatError> {
+    let mut out = String::new();
+    write(&mut out)?;
+    Ok(out)
 }
 
 // ============================================================================
@@ -140,10 +248,50 @@ impl AsDataFormatter for FormatType {
             FormatType::Sql(f) => f,
             FormatType::Raw(f) => f,
             FormatType::ProtoText(f) => f,
+            FormatType::Custom(f) => f,
         }
     }
 }
 
+/// 自定义格式化器只实现了新接口，旧接口通过构造 `Value` 转发
+#[allow(deprecated)]
+impl DataFormat for BoxedFormatter {
+    type Output = String;
+    fn format_null(&self) -> String {
+        (**self).format_value(&Value::Null)
+    }
+    fn format_bool(&self, value: &bool) -> String {
+        (**self).format_value(&Value::Bool(*value))
+    }
+    fn format_string(&self, value: &str) -> String {
+        (**self).format_value(&Value::Chars(value.into()))
+    }
+    fn format_i64(&self, value: &i64) -> String {
+        (**self).format_value(&Value::Digit(*value))
+    }
+    fn format_f64(&self, value: &f64) -> String {
+        (**self).format_value(&Value::Float(*value))
+    }
+    fn format_ip(&self, value: &std::net::IpAddr) -> String {
+        (**self).format_value(&Value::IpAddr(*value))
+    }
+    fn format_datetime(&self, value: &chrono::NaiveDateTime) -> String {
+        (**self).format_value(&Value::Time(*value))
+    }
+    fn format_object(&self, value: &ObjectValue) -> String {
+        (**self).format_value(&Value::Obj(value.clone()))
+    }
+    fn format_array(&self, value: &[FieldStorage]) -> String {
+        (**self).format_value(&Value::Array(value.to_vec()))
+    }
+    fn format_field(&self, field: &FieldStorage) -> String {
+        (**self).fmt_field(field)
+    }
+    fn format_record(&self, record: &DataRecord) -> String {
+        (**self).fmt_record(record)
+    }
+}
+
 #[allow(deprecated)]
 impl AsDataFormatter for SqlFormat {
     fn as_formatter(&self) -> &dyn DataFormat<Output = String> {
@@ -202,38 +350,29 @@ impl ValueFormatter for FormatType {
     type Output = String;
 
     fn format_value(&self, value: &Value) -> String {
-        match self {
-            FormatType::Csv(f) => f.format_value(value),
-            FormatType::Json(f) => f.format_value(value),
-            FormatType::Kv(f) => f.format_value(value),
-            FormatType::Sql(f) => f.format_value(value),
-            FormatType::Raw(f) => f.format_value(value),
-            FormatType::ProtoText(f) => f.format_value(value),
-        }
+        self.try_format_value(value).unwrap_or_default()
+    }
+
+    fn try_format_value(&self, value: &Value) -> Result<String, FormatError> {
+        render(|out| self.write_value(value, out))
     }
 }
 
 impl RecordFormatter for FormatType {
     fn fmt_field(&self, field: &FieldStorage) -> String {
-        match self {
-            FormatType::Csv(f) => f.fmt_field(field),
-            FormatType::Json(f) => f.fmt_field(field),
-            FormatType::Kv(f) => f.fmt_field(field),
-            FormatType::Sql(f) => f.fmt_field(field),
-            FormatType::Raw(f) => f.fmt_field(field),
-            FormatType::ProtoText(f) => f.fmt_field(field),
-        }
+        self.try_fmt_field(field).unwrap_or_default()
     }
 
     fn fmt_record(&self, record: &DataRecord) -> String {
-        match self {
-            FormatType::Csv(f) => f.fmt_record(record),
-            FormatType::Json(f) => f.fmt_record(record),
-            FormatType::Kv(f) => f.fmt_record(record),
-            FormatType::Sql(f) => f.fmt_record(record),
-            FormatType::Raw(f) => f.fmt_record(record),
-            FormatType::ProtoText(f) => f.fmt_record(record),
-        }
+        self.try_fmt_record(record).unwrap_or_default()
+    }
+
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<String, FormatError> {
+        render(|out| self.write_field(field, out))
+    }
+
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<String, FormatError> {
+        render(|out| self.write_record_fmt(record, out))
     }
 }
 
@@ -241,31 +380,119 @@ impl ValueFormatter for SqlFormat {
     type Output = String;
 
     fn format_value(&self, value: &Value) -> String {
-        match self {
-            SqlFormat::Json(f) => f.format_value(value),
-            SqlFormat::Kv(f) => f.format_value(value),
-            SqlFormat::Raw(f) => f.format_value(value),
-            SqlFormat::ProtoText(f) => f.format_value(value),
-        }
+        self.try_format_value(value).unwrap_or_default()
+    }
+
+    fn try_format_value(&self, value: &Value) -> Result<String, FormatError> {
+        render(|out| self.write_value(value, out))
     }
 }
 
 impl RecordFormatter for SqlFormat {
     fn fmt_field(&self, field: &FieldStorage) -> String {
+        self.try_fmt_field(field).unwrap_or_default()
+    }
+
+    fn fmt_record(&self, record: &DataRecord) -> String {
+        self.try_fmt_record(record).unwrap_or_default()
+    }
+
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<String, FormatError> {
+        render(|out| self.write_field(field, out))
+    }
+
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<String, FormatError> {
+        render(|out| self.write_record_fmt(record, out))
+    }
+}
+
+// ============================================================================
+// 流式 trait 实现：FormatType 和 SqlFormat
+// ============================================================================
+
+impl ValueWriter for FormatType {
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
         match self {
-            SqlFormat::Json(f) => f.fmt_field(field),
-            SqlFormat::Kv(f) => f.fmt_field(field),
-            SqlFormat::Raw(f) => f.fmt_field(field),
-            SqlFormat::ProtoText(f) => f.fmt_field(field),
+            FormatType::Csv(f) => f.write_value(value, out),
+            FormatType::Json(f) => f.write_value(value, out),
+            FormatType::Kv(f) => f.write_value(value, out),
+            FormatType::Sql(f) => f.write_value(value, out),
+            FormatType::Raw(f) => f.write_value(value, out),
+            FormatType::ProtoText(f) => f.write_value(value, out),
+            FormatType::Custom(f) => Ok(out.write_str(&f.try_format_value(value)?)?),
         }
     }
+}
 
-    fn fmt_record(&self, record: &DataRecord) -> String {
+impl RecordWriter for FormatType {
+    fn write_field(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        match self {
+            FormatType::Csv(f) => f.write_field(field, out),
+            FormatType::Json(f) => f.write_field(field, out),
+            FormatType::Kv(f) => f.write_field(field, out),
+            FormatType::Sql(f) => f.write_field(field, out),
+            FormatType::Raw(f) => f.write_field(field, out),
+            FormatType::ProtoText(f) => f.write_field(field, out),
+            FormatType::Custom(f) => Ok(out.write_str(&f.try_fmt_field(field)?)?),
+        }
+    }
+
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        match self {
+            FormatType::Csv(f) => f.write_record_fmt(record, out),
+            FormatType::Json(f) => f.write_record_fmt(record, out),
+            FormatType::Kv(f) => f.write_record_fmt(record, out),
+            FormatType::Sql(f) => f.write_record_fmt(record, out),
+            FormatType::Raw(f) => f.write_record_fmt(record, out),
+            FormatType::ProtoText(f) => f.write_record_fmt(record, out),
+            FormatType::Custom(f) => Ok(out.write_str(&f.try_fmt_record(record)?)?),
+        }
+    }
+}
+
+impl ValueWriter for SqlFormat {
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        match self {
+            SqlFormat::Json(f) => f.write_value(value, out),
+            SqlFormat::Kv(f) => f.write_value(value, out),
+            SqlFormat::Raw(f) => f.write_value(value, out),
+            SqlFormat::ProtoText(f) => f.write_value(value, out),
+        }
+    }
+}
+
+impl RecordWriter for SqlFormat {
+    fn write_field(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        match self {
+            SqlFormat::Json(f) => f.write_field(field, out),
+            SqlFormat::Kv(f) => f.write_field(field, out),
+            SqlFormat::Raw(f) => f.write_field(field, out),
+            SqlFormat::ProtoText(f) => f.write_field(field, out),
+        }
+    }
+
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
         match self {
-            SqlFormat::Json(f) => f.fmt_record(record),
-            SqlFormat::Kv(f) => f.fmt_record(record),
-            SqlFormat::Raw(f) => f.fmt_record(record),
-            SqlFormat::ProtoText(f) => f.fmt_record(record),
+            SqlFormat::Json(f) => f.write_record_fmt(record, out),
+            SqlFormat::Kv(f) => f.write_record_fmt(record, out),
+            SqlFormat::Raw(f) => f.write_record_fmt(record, out),
+            SqlFormat::ProtoText(f) => f.write_record_fmt(record, out),
         }
     }
 }
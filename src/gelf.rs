@@ -0,0 +1,417 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use wp_model_core::model::{DataRecord, FieldStorage, Value};
+
+use crate::Json;
+use crate::error::FormatError;
+use crate::flatten::{Collision, Flatten};
+use crate::formatter::{RecordFormatter, RecordWriter, ValueFormatter, ValueWriter, render};
+use crate::json::write_json_str;
+use crate::meta::{MetaValue, without_fields};
+use crate::projected::lookup;
+use crate::time::TimeFormat;
+
+/// GELF 1.1（Graylog Extended Log Format）消息
+///
+/// 每条记录输出一个 JSON 对象：`version`、`host`、`short_message`、可选的
+/// `full_message`、`timestamp`，其余字段作为 `_` 前缀的附加字段。
+///
+/// - `host` 默认取字段 `host`，`short_message` 默认取字段 `message`；取不到值时返回
+///   `FormatError::MissingField`。作为来源的字段不再重复输出为附加字段。
+/// - `timestamp` 取自 `with_time_field` 指定的 `Value::Time` 字段，输出为带小数的
+///   epoch 秒，时区取 `Json` 的 `TimeFormat::with_offset`；未设置或字段缺失时使用当前时间。
+/// - GELF 不允许嵌套，对象和数组按 `Flatten` 展开，如 `http.status`、`tags.0`；
+///   字段名中 `[\w.-]` 以外的字符替换为 `_`，展开或替换后重名的字段依次追加 `_2`、`_3` 等后缀。
+///   超过展开层数的值以 JSON 文本输出。
+/// - 整数和浮点数保持为数字（遵循 `Json` 的整数和浮点数策略），布尔值输出为字符串，
+///   空值字段省略。
+/// - 保留字段 `_id` 不可用，`id` 字段改名为 `with_id_name` 指定的名称，默认 `_id_`。
+///
+/// ```
+/// use wp_data_fmt::{Gelf, MetaValue};
+///
+/// let gelf = Gelf::new()
+///     .with_host(MetaValue::field("hostname"))
+///     .with_short_message(MetaValue::field("msg"))
+///     .with_time_field("timestamp");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Gelf {
+    json: Json,
+    host: MetaValue,
+    short_message: MetaValue,
+    full_message: Option<MetaValue>,
+    time_field: Option<String>,
+    flatten: Flatten,
+    id_name: String,
+}
+
+impl Default for Gelf {
+    fn default() -> Self {
+        Self {
+            json: Json::default(),
+            host: MetaValue::field("host"),
+            short_message: MetaValue::field("message"),
+            full_message: None,
+            time_field: None,
+            flatten: Flatten::new().with_collision(Collision::Suffix),
+            id_name: "id_".to_string(),
+        }
+    }
+}
+
+impl Gelf {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 附加字段值的 JSON 选项
+    pub fn with_json(mut self, json: Json) -> Self {
+        self.json = json;
+        self
+    }
+
+    pub fn with_host(mut self, host: MetaValue) -> Self {
+        self.host = host;
+        self
+    }
+
+    pub fn with_short_message(mut self, short_message: MetaValue) -> Self {
+        self.short_message = short_message;
+        self
+    }
+
+    pub fn with_full_message(mut self, full_message: MetaValue) -> Self {
+        self.full_message = Some(full_message);
+        self
+    }
+
+    pub fn with_time_field(mut self, field: impl Into<String>) -> Self {
+        self.time_field = Some(field.into());
+        self
+    }
+
+    /// 嵌套字段的展开方式，默认以 `.` 连接，展开后重名时追加后缀
+    pub fn with_flatten(mut self, flatten: Flatten) -> Self {
+        self.flatten = flatten;
+        self
+    }
+
+    /// `id` 字段改用的名称（不含 `_` 前缀）
+    pub fn with_id_name(mut self, id_name: impl Into<String>) -> Self {
+        self.id_name = id_name.into();
+        self
+    }
+
+    fn required(
+        &self,
+        meta: &MetaValue,
+        record: &DataRecord,
+        key: &str,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        let text = meta
+            .resolve(record)
+            .ok_or_else(|| FormatError::MissingField {
+                format: "gelf",
+                name: meta.field_name().unwrap_or(key).to_string(),
+            })?;
+        write!(out, ",\"{}\":", key)?;
+        write_json_str(&text, out)?;
+        Ok(())
+    }
+
+    fn write_timestamp(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        out.write_str(",\"timestamp\":")?;
+        let value = self
+            .time_field
+            .as_deref()
+            .and_then(|name| lookup(record, name))
+            .map(|f| f.get_value());
+        match value {
+            Some(Value::Time(t)) => self.json.opts().time.write_epoch_fraction(t, out)?,
+            None | Some(Value::Null | Value::Ignore(_)) => {
+                let now = chrono::Utc::now().naive_utc();
+                TimeFormat::default().write_epoch_fraction(&now, out)?
+            }
+            Some(value) => return Err(FormatError::unsupported("gelf", value)),
+        }
+        Ok(())
+    }
+
+    /// 写出一个附加字段，空值、空对象和空数组省略
+    fn write_additional(
+        &self,
+        field: &FieldStorage,
+        used: &mut HashSet<String>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        let value = field.get_value();
+        match value {
+            Value::Null | Value::Ignore(_) => return Ok(()),
+            Value::Obj(obj) if obj.is_empty() => return Ok(()),
+            Value::Array(arr) if arr.is_empty() => return Ok(()),
+            _ => {}
+        }
+        let name = unique_name(
+            match field.get_name() {
+                "id" => self.id_name.clone(),
+                name => field_name(name),
+            },
+            used,
+        );
+        out.write_char(',')?;
+        write_json_str(&format!("_{}", name), out)?;
+        out.write_char(':')?;
+        match value {
+            Value::Bool(v) => write_json_str(if *v { "true" } else { "false" }, out)?,
+            Value::Obj(_) | Value::Array(_) => {
+                write_json_str(&self.json.try_format_value(value)?, out)?
+            }
+            _ => self.json.write_value(value, out)?,
+        }
+        Ok(())
+    }
+}
+
+/// 与已输出的字段重名时追加 `_2`、`_3` 等后缀，并记录最终名称
+fn unique_name(name: String, used: &mut HashSet<String>) -> String {
+    let name = if used.contains(&name) {
+        (2..)
+            .map(|n| format!("{}_{}", name, n))
+            .find(|candidate| !used.contains(candidate))
+            .unwrap_or_default()
+    } else {
+        name
+    };
+    used.insert(name.clone());
+    name
+}
+
+/// GELF 字段名：数组下标 `[0]` 改为 `.0`，其余非法字符替换为 `_`
+fn field_name(name: &str) -> String {
+    name.replace('[', ".")
+        .replace(']', "")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+impl ValueWriter for Gelf {
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        self.json.write_value(value, out)
+    }
+}
+
+impl RecordWriter for Gelf {
+    fn write_field(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        self.json.write_field(field, out)
+    }
+
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        out.write_str("{\"version\":\"1.1\"")?;
+        self.required(&self.host, record, "host", out)?;
+        self.required(&self.short_message, record, "short_message", out)?;
+        if let Some(full) = &self.full_message
+            && let Some(text) = full.resolve(record)
+        {
+            out.write_str(",\"full_message\":")?;
+            write_json_str(&text, out)?;
+        }
+        self.write_timestamp(record, out)?;
+
+        let promoted: Vec<&str> = [Some(&self.host), Some(&self.short_message)]
+            .into_iter()
+            .chain([self.full_message.as_ref()])
+            .filter_map(|meta| meta?.field_name())
+            .chain(self.time_field.as_deref())
+            .collect();
+        let rest = without_fields(record, &promoted);
+        let flat = self.flatten.flatten(&rest)?;
+        let mut used = HashSet::new();
+        for field in &flat.items {
+            self.write_additional(field, &mut used, out)?;
+        }
+        out.write_char('}')?;
+        Ok(())
+    }
+}
+
+impl ValueFormatter for Gelf {
+    type Output = String;
+
+    fn format_value(&self, value: &Value) -> String {
+        self.try_format_value(value).unwrap_or_default()
+    }
+
+    fn try_format_value(&self, value: &Value) -> Result<String, FormatError> {
+        render(|out| self.write_value(value, out))
+    }
+}
+
+impl RecordFormatter for Gelf {
+    fn fmt_field(&self, field: &FieldStorage) -> String {
+        self.try_fmt_field(field).unwrap_or_default()
+    }
+
+    fn fmt_record(&self, record: &DataRecord) -> String {
+        self.try_fmt_record(record).unwrap_or_default()
+    }
+
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<String, FormatError> {
+        render(|out| self.write_field(field, out))
+    }
+
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<String, FormatError> {
+        render(|out| self.write_record_fmt(record, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wp_model_core::model::DataField;
+    use wp_model_core::model::types::value::ObjectValue;
+
+    fn make_record() -> DataRecord {
+        let ts = chrono::NaiveDateTime::parse_from_str(
+            "2019-08-06 12:12:19.250",
+            "%Y-%m-%d %H:%M:%S%.f",
+        )
+        .unwrap();
+        let mut http = ObjectValue::new();
+        http.insert(
+            "status".to_string(),
+            FieldStorage::from_owned(DataField::from_digit("status", 200)),
+        );
+        DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_time("ts", ts)),
+                FieldStorage::from_owned(DataField::from_chars("host", "web01")),
+                FieldStorage::from_owned(DataField::from_chars("message", "GET /index")),
+                FieldStorage::from_owned(DataField::from_chars("id", "abc")),
+                FieldStorage::from_owned(DataField::from_obj("http", http)),
+                FieldStorage::from_owned(DataField::from_arr(
+                    "tags",
+                    vec![DataField::from_chars("", "a")],
+                )),
+                FieldStorage::from_owned(DataField::from_float("rt", 0.25)),
+                FieldStorage::from_owned(DataField::from_bool("ok", true)),
+                FieldStorage::from_owned(DataField::from_chars("user agent", "curl")),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_gelf_message() {
+        let gelf = Gelf::new().with_time_field("ts");
+        assert_eq!(
+            gelf.fmt_record(&make_record()),
+            concat!(
+                r#"{"version":"1.1","host":"web01","short_message":"GET /index","#,
+                r#""timestamp":1565093539.25,"_id_":"abc","_http.status":200,"_tags.0":"a","#,
+                r#""_rt":0.25,"_ok":"true","_user_agent":"curl"}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_gelf_options() {
+        let gelf = Gelf::new()
+            .with_host(MetaValue::text("collector"))
+            .with_short_message(MetaValue::field("id"))
+            .with_full_message(MetaValue::field("message"))
+            .with_time_field("ts")
+            .with_flatten(Flatten::new().with_max_depth(0))
+            .with_id_name("event_id");
+        assert_eq!(
+            gelf.fmt_record(&make_record()),
+            concat!(
+                r#"{"version":"1.1","host":"collector","short_message":"abc","#,
+                r#""full_message":"GET /index","timestamp":1565093539.25,"_host":"web01","#,
+                r#""_http":"{\"status\":200}","_tags":"[\"a\"]","_rt":0.25,"_ok":"true","#,
+                r#""_user_agent":"curl"}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_gelf_flatten_collision() {
+        let mut http = ObjectValue::new();
+        http.insert(
+            "status".to_string(),
+            FieldStorage::from_owned(DataField::from_digit("status", 200)),
+        );
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_chars("host", "web01")),
+                FieldStorage::from_owned(DataField::from_chars("message", "m")),
+                FieldStorage::from_owned(DataField::from_obj("http", http)),
+                FieldStorage::from_owned(DataField::from_digit("http.status", 404)),
+            ],
+        };
+        let line = Gelf::new().try_fmt_record(&record).unwrap();
+        assert!(
+            line.ends_with(r#""_http.status":200,"_http.status_2":404}"#),
+            "{}",
+            line
+        );
+    }
+
+    #[test]
+    fn test_gelf_duplicate_names() {
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_chars("host", "web01")),
+                FieldStorage::from_owned(DataField::from_chars("message", "m")),
+                FieldStorage::from_owned(DataField::from_digit("a b", 1)),
+                FieldStorage::from_owned(DataField::from_digit("a_b", 2)),
+                FieldStorage::from_owned(DataField::from_digit("a?b", 3)),
+                FieldStorage::from_owned(DataField::from_chars("id", "x")),
+                FieldStorage::from_owned(DataField::from_chars("id_", "y")),
+            ],
+        };
+        let line = Gelf::new().fmt_record(&record);
+        assert!(
+            line.ends_with(r#""_a_b":1,"_a_b_2":2,"_a_b_3":3,"_id_":"x","_id__2":"y"}"#),
+            "{}",
+            line
+        );
+    }
+
+    #[test]
+    fn test_gelf_required_fields() {
+        let err = Gelf::new()
+            .with_host(MetaValue::field("hostname"))
+            .try_fmt_record(&make_record())
+            .unwrap_err();
+        assert!(matches!(err, FormatError::MissingField { ref name, .. } if name == "hostname"));
+
+        // 未指定时间字段时使用当前时间
+        let text = Gelf::new().fmt_record(&make_record());
+        assert!(text.contains(r#""timestamp":"#));
+        assert!(text.contains(r#""_ts":"2019-08-06 12:12:19.250""#));
+    }
+}
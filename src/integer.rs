@@ -0,0 +1,121 @@
+use std::collections::BTreeSet;
+
+/// JavaScript 可精确表示的最大整数 `2^53 - 1`
+pub const MAX_SAFE_INTEGER: i64 = 9_007_199_254_740_991;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Quote {
+    #[default]
+    Never,
+    Unsafe,
+    All,
+}
+
+/// `Value::Digit` 在 JSON 中的输出方式
+///
+/// 默认输出为数字。JavaScript 和部分 Elasticsearch 客户端在超过 2^53 时会丢失精度，
+/// 可以改为以字符串输出超出安全范围的整数或全部整数；`with_fields` 把作用范围限定为
+/// 指定名称的字段及其嵌套成员。
+///
+/// ```
+/// use wp_data_fmt::{IntegerPolicy, Json};
+///
+/// let json = Json::default().with_integer_policy(
+///     IntegerPolicy::unsafe_as_string().with_fields(["trace_id", "span_id"]),
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegerPolicy {
+    quote: Quote,
+    fields: Option<BTreeSet<String>>,
+}
+
+impl Default for IntegerPolicy {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl IntegerPolicy {
+    pub(crate) const DEFAULT: Self = Self {
+        quote: Quote::Never,
+        fields: None,
+    };
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 超出 `±MAX_SAFE_INTEGER` 的整数以字符串输出
+    pub fn unsafe_as_string() -> Self {
+        Self {
+            quote: Quote::Unsafe,
+            fields: None,
+        }
+    }
+
+    /// 所有整数以字符串输出
+    pub fn all_as_string() -> Self {
+        Self {
+            quote: Quote::All,
+            fields: None,
+        }
+    }
+
+    /// 只作用于这些名称的字段（任意层级的对象成员名同样匹配）及其嵌套成员
+    pub fn with_fields<I, S>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.fields = Some(fields.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// 顶层是否处于作用范围内
+    pub(crate) fn root_scope(&self) -> bool {
+        self.fields.is_none()
+    }
+
+    /// 进入名为 `name` 的成员后是否处于作用范围内
+    pub(crate) fn enter(&self, name: &str, scoped: bool) -> bool {
+        scoped || self.fields.as_ref().is_some_and(|f| f.contains(name))
+    }
+
+    /// 整数是否需要以字符串输出
+    pub(crate) fn quotes(&self, value: i64, scoped: bool) -> bool {
+        scoped
+            && match self.quote {
+                Quote::Never => false,
+                Quote::Unsafe => !(-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&value),
+                Quote::All => true,
+            }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quotes() {
+        let policy = IntegerPolicy::default();
+        assert!(!policy.quotes(i64::MAX, true));
+        let policy = IntegerPolicy::unsafe_as_string();
+        assert!(policy.root_scope());
+        assert!(!policy.quotes(MAX_SAFE_INTEGER, true));
+        assert!(policy.quotes(MAX_SAFE_INTEGER + 1, true));
+        assert!(policy.quotes(-MAX_SAFE_INTEGER - 1, true));
+        assert!(IntegerPolicy::all_as_string().quotes(1, true));
+    }
+
+    #[test]
+    fn test_fields_scope() {
+        let policy = IntegerPolicy::all_as_string().with_fields(["id"]);
+        assert!(!policy.root_scope());
+        assert!(policy.enter("id", false));
+        assert!(!policy.enter("n", false));
+        assert!(policy.enter("n", true));
+        assert!(!policy.quotes(1, false));
+    }
+}
@@ -1,11 +1,150 @@
+use crate::error::{FormatError, value_type_name};
+use crate::float::{FloatAction, FloatPolicy, write_ecmascript};
 #[allow(deprecated)]
 use crate::formatter::StaticDataFormatter;
-use crate::formatter::{RecordFormatter, ValueFormatter};
-use serde_json::{Value as JsonValue, json};
+use crate::formatter::{RecordFormatter, RecordWriter, ValueFormatter, ValueWriter, render};
+use crate::integer::IntegerPolicy;
+use crate::null::NullPolicy;
+use crate::options::FmtOptions;
+use crate::time::TimeFormat;
+use crate::unflatten::{Node, Unflatten};
+use std::borrow::Cow;
+use std::fmt;
 use wp_model_core::model::{DataRecord, DataType, FieldStorage, Value, types::value::ObjectValue};
 
-#[derive(Debug, Default)]
-pub struct Json;
+/// JSON 对象键的处理方式
+///
+/// 引号、反斜杠和控制字符视为不安全字符。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyPolicy {
+    /// 按 JSON 字符串规则转义
+    #[default]
+    Escape,
+    /// 键含不安全字符时返回 `FormatError::Unrepresentable`
+    Validate,
+    /// 不安全字符替换为指定字符，如 `_`
+    Replace(char),
+}
+
+/// 类型信息的输出方式，类型名取自 `FieldStorage::get_meta()`（`auto` 时按值推断），
+/// 与 `DataType` 的名称一致，如 `ip`、`time`、`http/status`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TypeStyle {
+    /// 不输出类型信息
+    #[default]
+    Plain,
+    /// 每个字段输出为 `{"name":..,"type":..,"value":..}`，记录、对象和数组输出为三元组数组
+    Triples,
+    /// JSON 无法区分的类型包装为 `{"$ip":"10.0.0.1"}`；
+    /// `chars`、`digit`、`float`、`bool`、`obj`、`array` 和 `auto` 保持原样
+    Wrappers,
+}
+
+#[derive(Debug, Clone)]
+pub struct Json {
+    opts: FmtOptions,
+    unflatten: Option<Unflatten>,
+    keys: KeyPolicy,
+    /// 美化输出的缩进空格数，`None` 为紧凑单行输出
+    indent: Option<usize>,
+    integers: IntegerPolicy,
+    types: TypeStyle,
+    canonical: bool,
+}
+
+/// 默认配置的 `Json`，与 `Json::default()` 相同
+///
+/// `Json` 原先是单元结构体，`let f = Json;`、`FormatType::Json(Json)` 等写法继续可用。
+#[allow(non_upper_case_globals)]
+pub const Json: Json = Json {
+    opts: FmtOptions::DEFAULT,
+    unflatten: None,
+    keys: KeyPolicy::Escape,
+    indent: None,
+    integers: IntegerPolicy::DEFAULT,
+    types: TypeStyle::Plain,
+    canonical: false,
+};
+
+impl Default for Json {
+    fn default() -> Self {
+        Json
+    }
+}
+
+impl Json {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// 规范输出，见 `with_canonical`
+    pub fn canonical() -> Self {
+        Self::default().with_canonical(true)
+    }
+    /// 多行美化输出，每层缩进 `indent` 个空格
+    pub fn pretty(indent: usize) -> Self {
+        Self::default().with_pretty(indent)
+    }
+    /// 切换为多行美化输出；默认的紧凑模式保证不含换行
+    pub fn with_pretty(mut self, indent: usize) -> Self {
+        self.indent = Some(indent);
+        self
+    }
+    /// 参照 RFC 8785 (JCS) 的规范输出，相同内容总是得到相同字节，便于去重和签名：
+    /// 记录字段和对象键按 UTF-16 码元排序，浮点数使用 ECMAScript 格式，字符串只做最少转义，
+    /// 并忽略 `with_pretty` 和浮点数的位数设置
+    ///
+    /// 整数按 `i64` 原样输出；需要与按 IEEE 双精度解析的 JCS 实现互通时，
+    /// 可配合 `IntegerPolicy::unsafe_as_string()`
+    pub fn with_canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+    /// 严格模式：NaN/Infinity 等无法用 JSON 表示的值返回错误，而不是替换为 `null` 或字符串
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.opts.strict = strict;
+        self
+    }
+    /// 最大嵌套层数，超过时返回 `FormatError::DepthExceeded`
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.opts.max_depth = max_depth;
+        self
+    }
+    /// `Value::Time` 的输出方式，默认与 `NaiveDateTime` 的 `Display` 一致
+    pub fn with_time_format(mut self, time: TimeFormat) -> Self {
+        self.opts.time = time;
+        self
+    }
+    /// 浮点数的位数、科学计数法和 NaN/Infinity 处理方式
+    pub fn with_float_policy(mut self, float: FloatPolicy) -> Self {
+        self.opts.float = float;
+        self
+    }
+    /// 空值字段的处理方式：原生空值、省略或占位文本
+    pub fn with_null_policy(mut self, null: NullPolicy) -> Self {
+        self.opts.null = null;
+        self
+    }
+    /// `Value::Digit` 的输出方式：数字（默认），或超出安全范围/全部以字符串输出
+    pub fn with_integer_policy(mut self, integers: IntegerPolicy) -> Self {
+        self.integers = integers;
+        self
+    }
+    /// 输出字段类型，便于在节点之间传递记录时保留 `DataType`；启用后忽略 `with_unflatten`
+    pub fn with_type_style(mut self, types: TypeStyle) -> Self {
+        self.types = types;
+        self
+    }
+    /// 对象键的处理方式：转义（默认）、校验或替换不安全字符
+    pub fn with_key_policy(mut self, keys: KeyPolicy) -> Self {
+        self.keys = keys;
+        self
+    }
+    /// 按分隔符拆分字段名，输出嵌套对象，如 `http/status` 输出为 `{"http":{"status":..}}`
+    pub fn with_unflatten(mut self, unflatten: Unflatten) -> Self {
+        self.unflatten = Some(unflatten);
+        self
+    }
+}
 
 #[allow(deprecated)]
 impl StaticDataFormatter for Json {
@@ -14,143 +153,609 @@ impl StaticDataFormatter for Json {
         "null".to_string()
     }
     fn stdfmt_bool(value: &bool) -> String {
-        json!(value).to_string()
+        value.to_string()
     }
     fn stdfmt_string(value: &str) -> String {
-        serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+        render(|out| Ok(write_json_str(value, out)?)).unwrap_or_default()
     }
     fn stdfmt_i64(value: &i64) -> String {
-        json!(value).to_string()
+        value.to_string()
     }
     fn stdfmt_f64(value: &f64) -> String {
-        if value.is_nan() {
-            "null".to_string()
-        } else if value.is_infinite() {
-            if value.is_sign_positive() {
-                "\"Infinity\"".to_string()
-            } else {
-                "\"-Infinity\"".to_string()
-            }
-        } else {
-            json!(value).to_string()
-        }
+        Json::default().format_value(&Value::Float(*value))
     }
     fn stdfmt_ip_addr(value: &std::net::IpAddr) -> String {
-        json!(value.to_string()).to_string()
+        Json::default().format_value(&Value::IpAddr(*value))
     }
     fn stdfmt_datetime(value: &chrono::NaiveDateTime) -> String {
-        json!(value.to_string()).to_string()
+        Json::default().format_value(&Value::Time(*value))
     }
     fn stdfmt_object(value: &ObjectValue) -> String {
-        let mut json_obj = serde_json::Map::new();
-        for (k, v) in value.iter() {
-            json_obj.insert(k.to_string(), to_json_value(v.get_value()));
-        }
-        json!(json_obj).to_string()
+        render(|out| Json::default().write_object(value, out)).unwrap_or_default()
     }
     fn stdfmt_array(value: &[FieldStorage]) -> String {
-        let items: Vec<String> = value
-            .iter()
-            .map(|field| match field.get_value() {
-                Value::Chars(s) => Self::stdfmt_string(s),
-                _ => Self::stdfmt_value(field.get_value()),
-            })
-            .collect();
-        format!("[{}]", items.join(","))
+        render(|out| Json::default().write_array(value, out)).unwrap_or_default()
     }
     fn stdfmt_field(field: &FieldStorage) -> String {
-        if field.get_name().is_empty() {
-            Self::stdfmt_value(field.get_value())
-        } else {
-            format!(
-                "\"{}\":{}",
-                field.get_name(),
-                Self::stdfmt_value(field.get_value())
-            )
-        }
+        Json::default().fmt_field(field)
     }
     fn stdfmt_record(record: &DataRecord) -> String {
-        let mut items = Vec::new();
-        for field in record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-        {
-            items.push(Self::stdfmt_field(field));
-        }
-        format!("{{{}}}", items.join(","))
+        Json::default().fmt_record(record)
     }
 }
 
+/// 旧接口同样遵循实例上的选项（严格模式、空值、时间、浮点数、缩进等）
 #[allow(deprecated)]
 impl crate::formatter::DataFormat for Json {
     type Output = String;
     fn format_null(&self) -> String {
-        Self::stdfmt_null()
+        self.format_value(&Value::Null)
     }
     fn format_bool(&self, v: &bool) -> String {
-        Self::stdfmt_bool(v)
+        self.format_value(&Value::Bool(*v))
     }
     fn format_string(&self, v: &str) -> String {
-        Self::stdfmt_string(v)
+        render(|out| Ok(write_json_str(v, out)?)).unwrap_or_default()
     }
     fn format_i64(&self, v: &i64) -> String {
-        Self::stdfmt_i64(v)
+        self.format_value(&Value::Digit(*v))
     }
     fn format_f64(&self, v: &f64) -> String {
-        Self::stdfmt_f64(v)
+        self.format_value(&Value::Float(*v))
     }
     fn format_ip(&self, v: &std::net::IpAddr) -> String {
-        Self::stdfmt_ip_addr(v)
+        self.format_value(&Value::IpAddr(*v))
     }
     fn format_datetime(&self, v: &chrono::NaiveDateTime) -> String {
-        Self::stdfmt_datetime(v)
+        self.format_value(&Value::Time(*v))
     }
     fn format_object(&self, v: &ObjectValue) -> String {
-        Self::stdfmt_object(v)
+        render(|out| self.write_object(v, out)).unwrap_or_default()
     }
     fn format_array(&self, v: &[FieldStorage]) -> String {
-        Self::stdfmt_array(v)
+        render(|out| self.write_array(v, out)).unwrap_or_default()
     }
     fn format_field(&self, f: &FieldStorage) -> String {
-        Self::stdfmt_field(f)
+        self.fmt_field(f)
     }
     fn format_record(&self, r: &DataRecord) -> String {
-        Self::stdfmt_record(r)
+        self.fmt_record(r)
     }
 }
 
-fn to_json_value(value: &Value) -> JsonValue {
-    match value {
-        Value::Bool(v) => JsonValue::Bool(*v),
-        Value::Chars(v) => JsonValue::String(v.to_string()),
-        Value::Digit(v) => JsonValue::Number((*v).into()),
-        Value::Float(v) => {
-            if v.is_nan() {
-                JsonValue::Null
-            } else if v.is_infinite() {
-                if v.is_sign_positive() {
-                    JsonValue::String("Infinity".to_string())
-                } else {
-                    JsonValue::String("-Infinity".to_string())
-                }
+/// 写出带引号的 JSON 字符串，转义规则与 serde_json 一致
+pub(crate) fn write_json_str(value: &str, out: &mut dyn fmt::Write) -> fmt::Result {
+    out.write_char('"')?;
+    let mut start = 0;
+    for (i, b) in value.bytes().enumerate() {
+        let escaped = match b {
+            b'"' => "\\\"",
+            b'\\' => "\\\\",
+            b'\n' => "\\n",
+            b'\r' => "\\r",
+            b'\t' => "\\t",
+            0x08 => "\\b",
+            0x0c => "\\f",
+            0x00..=0x1f => "",
+            _ => continue,
+        };
+        out.write_str(&value[start..i])?;
+        if escaped.is_empty() {
+            write!(out, "\\u{:04x}", b)?;
+        } else {
+            out.write_str(escaped)?;
+        }
+        start = i + 1;
+    }
+    out.write_str(&value[start..])?;
+    out.write_char('"')
+}
+
+/// 在紧凑 JSON 文本流中插入换行和缩进
+///
+/// 紧凑输出总是合法的 JSON，只需跟踪是否位于字符串内即可识别结构字符。
+struct Pretty<'a> {
+    out: &'a mut dyn fmt::Write,
+    indent: usize,
+    level: usize,
+    in_string: bool,
+    escaped: bool,
+    /// 刚写出 `{` 或 `[`：换行推迟到下一个字符，空容器保持 `{}`/`[]`
+    opened: bool,
+}
+
+impl<'a> Pretty<'a> {
+    fn new(out: &'a mut dyn fmt::Write, indent: usize) -> Self {
+        Self {
+            out,
+            indent,
+            level: 0,
+            in_string: false,
+            escaped: false,
+            opened: false,
+        }
+    }
+
+    fn newline(&mut self) -> fmt::Result {
+        self.out.write_char('\n')?;
+        for _ in 0..self.level * self.indent {
+            self.out.write_char(' ')?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Write for Pretty<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.chars().try_for_each(|c| self.write_char(c))
+    }
+
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        if self.opened {
+            self.opened = false;
+            if c == '}' || c == ']' {
+                self.level = self.level.saturating_sub(1);
+                return self.out.write_char(c);
+            }
+            self.newline()?;
+        }
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if c == '\\' {
+                self.escaped = true;
+            } else if c == '"' {
+                self.in_string = false;
+            }
+            return self.out.write_char(c);
+        }
+        match c {
+            '"' => {
+                self.in_string = true;
+                self.out.write_char(c)
+            }
+            '{' | '[' => {
+                self.level += 1;
+                self.opened = true;
+                self.out.write_char(c)
+            }
+            '}' | ']' => {
+                self.level = self.level.saturating_sub(1);
+                self.newline()?;
+                self.out.write_char(c)
+            }
+            ',' => {
+                self.out.write_char(c)?;
+                self.newline()
+            }
+            ':' => self.out.write_str(": "),
+            _ => self.out.write_char(c),
+        }
+    }
+}
+
+/// 原有顺序或规范模式下排序后的条目
+enum Ordered<I: Iterator> {
+    Source(I),
+    Sorted(std::vec::IntoIter<I::Item>),
+}
+
+impl<I: Iterator> Iterator for Ordered<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Ordered::Source(items) => items.next(),
+            Ordered::Sorted(items) => items.next(),
+        }
+    }
+}
+
+/// 字段的类型名；`auto` 时按值推断
+fn type_name(field: &FieldStorage) -> Cow<'_, str> {
+    match (field.get_meta(), field.get_value()) {
+        (DataType::Auto, Value::Null) => Cow::Borrowed("auto"),
+        (DataType::Auto, value) => Cow::Borrowed(value_type_name(value)),
+        (meta, _) => Cow::Owned(meta.to_string()),
+    }
+}
+
+/// JSON 本身即可区分的类型，包装模式下不加包装
+fn is_native_type(name: &str) -> bool {
+    matches!(name, "auto" | "chars" | "digit" | "float" | "bool" | "obj")
+        || name.starts_with("array")
+}
+
+/// 键中需要校验或替换的字符
+fn is_unsafe_key_char(c: char) -> bool {
+    c == '"' || c == '\\' || c.is_control()
+}
+
+impl Json {
+    /// 按 `KeyPolicy` 写出对象键及其后的冒号
+    fn write_key(&self, key: &str, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        write_json_str(&self.checked_key(key)?, out)?;
+        out.write_char(':')?;
+        Ok(())
+    }
+
+    /// 按 `KeyPolicy` 校验或替换对象键，返回待转义的键文本
+    pub(crate) fn checked_key<'k>(&self, key: &'k str) -> Result<Cow<'k, str>, FormatError> {
+        match self.keys {
+            KeyPolicy::Validate if key.contains(is_unsafe_key_char) => {
+                Err(FormatError::Unrepresentable {
+                    format: "json",
+                    detail: format!("key {:?}", key),
+                })
+            }
+            KeyPolicy::Replace(c) if key.contains(is_unsafe_key_char) => {
+                Ok(Cow::Owned(key.replace(is_unsafe_key_char, &c.to_string())))
+            }
+            KeyPolicy::Escape | KeyPolicy::Validate | KeyPolicy::Replace(_) => {
+                Ok(Cow::Borrowed(key))
+            }
+        }
+    }
+
+    pub(crate) fn opts(&self) -> &FmtOptions {
+        &self.opts
+    }
+
+    pub(crate) fn is_canonical(&self) -> bool {
+        self.canonical
+    }
+
+    pub(crate) fn integers(&self) -> &IntegerPolicy {
+        &self.integers
+    }
+
+    pub(crate) fn type_style(&self) -> TypeStyle {
+        self.types
+    }
+
+    pub(crate) fn unflatten(&self) -> Option<&Unflatten> {
+        self.unflatten.as_ref()
+    }
+
+    /// 紧凑模式直接写出；美化模式经 `Pretty` 插入换行和缩进
+    pub(crate) fn layout(
+        &self,
+        out: &mut dyn fmt::Write,
+        write: impl FnOnce(&mut dyn fmt::Write) -> Result<(), FormatError>,
+    ) -> Result<(), FormatError> {
+        match self.indent {
+            Some(indent) if !self.canonical => write(&mut Pretty::new(out, indent)),
+            _ => write(out),
+        }
+    }
+
+    /// 规范模式下按键排序，否则保持原有顺序
+    fn ordered<I: Iterator>(&self, items: I, key: fn(&I::Item) -> &str) -> Ordered<I> {
+        if !self.canonical {
+            return Ordered::Source(items);
+        }
+        let mut items: Vec<_> = items.collect();
+        items.sort_by(|a, b| key(a).encode_utf16().cmp(key(b).encode_utf16()));
+        Ordered::Sorted(items.into_iter())
+    }
+
+    fn write_field_compact(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        let scoped = self
+            .integers
+            .enter(field.get_name(), self.integers.root_scope());
+        if self.types == TypeStyle::Triples {
+            return self.write_triple(field.get_name(), field, out, 0, scoped);
+        }
+        if !field.get_name().is_empty() {
+            self.write_key(field.get_name(), out)?;
+        }
+        self.write_typed_value(field, out, 0, scoped)
+    }
+
+    pub(crate) fn write_record_compact(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        if self.types != TypeStyle::Plain {
+            let fields = record
+                .items
+                .iter()
+                .filter(|f| *f.get_meta() != DataType::Ignore && !self.opts.omits(f.get_value()))
+                .map(|f| (f.get_name(), f));
+            let fields = self.ordered(fields, |(k, _)| k);
+            return self.write_typed_fields(fields, out, 0, self.integers.root_scope());
+        }
+        if let Some(unflatten) = &self.unflatten {
+            let nodes = unflatten.build(record, |v| self.opts.omits(v))?;
+            return self.write_nodes(&nodes, out, 0, self.integers.root_scope());
+        }
+        let fields = record
+            .items
+            .iter()
+            .filter(|f| *f.get_meta() != DataType::Ignore && !self.opts.omits(f.get_value()));
+        out.write_char('{')?;
+        for (i, field) in self.ordered(fields, |f| f.get_name()).enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            self.write_field_compact(field, out)?;
+        }
+        out.write_char('}')?;
+        Ok(())
+    }
+
+    /// 写出一组带类型的字段：三元组模式为数组，包装模式为对象；字段位于第 `depth` 层
+    fn write_typed_fields<'f>(
+        &self,
+        fields: impl Iterator<Item = (&'f str, &'f FieldStorage)>,
+        out: &mut dyn fmt::Write,
+        depth: usize,
+        scoped: bool,
+    ) -> Result<(), FormatError> {
+        let triples = self.types == TypeStyle::Triples;
+        out.write_char(if triples { '[' } else { '{' })?;
+        for (i, (name, field)) in fields.enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            let scoped = self.integers.enter(name, scoped);
+            if triples {
+                self.write_triple(name, field, out, depth, scoped)?;
             } else {
-                JsonValue::Number(serde_json::Number::from_f64(*v).unwrap_or(0.into()))
+                self.write_key(name, out)?;
+                self.write_typed_value(field, out, depth, scoped)?;
+            }
+        }
+        out.write_char(if triples { ']' } else { '}' })?;
+        Ok(())
+    }
+
+    fn write_triple(
+        &self,
+        name: &str,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+        depth: usize,
+        scoped: bool,
+    ) -> Result<(), FormatError> {
+        out.write_str("{\"name\":")?;
+        write_json_str(&self.checked_key(name)?, out)?;
+        out.write_str(",\"type\":")?;
+        write_json_str(&type_name(field), out)?;
+        out.write_str(",\"value\":")?;
+        self.write_typed_value(field, out, depth, scoped)?;
+        out.write_char('}')?;
+        Ok(())
+    }
+
+    /// 按 `TypeStyle` 写出字段值，对象和数组的成员同样带类型
+    fn write_typed_value(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+        depth: usize,
+        scoped: bool,
+    ) -> Result<(), FormatError> {
+        let value = field.get_value();
+        match (self.types, value) {
+            (TypeStyle::Plain, _) => self.write_value_at(value, out, depth, scoped),
+            (_, Value::Obj(obj)) => {
+                self.opts.check_depth(depth + 1)?;
+                let fields = obj
+                    .iter()
+                    .filter(|(_, f)| !self.opts.omits(f.get_value()))
+                    .map(|(k, f)| (k.as_str(), f));
+                let fields = self.ordered(fields, |(k, _)| k);
+                self.write_typed_fields(fields, out, depth + 1, scoped)
+            }
+            (TypeStyle::Triples, Value::Array(arr)) => {
+                self.opts.check_depth(depth + 1)?;
+                let fields = arr
+                    .iter()
+                    .filter(|f| !self.opts.omits(f.get_value()))
+                    .map(|f| (f.get_name(), f));
+                self.write_typed_fields(fields, out, depth + 1, scoped)
+            }
+            (TypeStyle::Wrappers, Value::Array(arr)) => {
+                self.opts.check_depth(depth + 1)?;
+                out.write_char('[')?;
+                for (i, item) in arr
+                    .iter()
+                    .filter(|f| !self.opts.omits(f.get_value()))
+                    .enumerate()
+                {
+                    if i > 0 {
+                        out.write_char(',')?;
+                    }
+                    self.write_typed_value(item, out, depth + 1, scoped)?;
+                }
+                out.write_char(']')?;
+                Ok(())
+            }
+            (TypeStyle::Triples, _) => self.write_value_at(value, out, depth, scoped),
+            (TypeStyle::Wrappers, _) => {
+                let name = type_name(field);
+                if is_native_type(&name) {
+                    return self.write_value_at(value, out, depth, scoped);
+                }
+                out.write_char('{')?;
+                write_json_str(&format!("${}", name), out)?;
+                out.write_char(':')?;
+                self.write_value_at(value, out, depth, scoped)?;
+                out.write_char('}')?;
+                Ok(())
+            }
+        }
+    }
+
+    fn write_null(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        match self.opts.null.text() {
+            Some(text) => write_json_str(text, out),
+            None => out.write_str("null"),
+        }
+    }
+
+    /// `scoped` 表示当前位置是否处于 `IntegerPolicy` 的作用范围内
+    fn write_value_at(
+        &self,
+        value: &Value,
+        out: &mut dyn fmt::Write,
+        depth: usize,
+        scoped: bool,
+    ) -> Result<(), FormatError> {
+        match value {
+            Value::Null => self.write_null(out)?,
+            Value::Bool(v) => out.write_str(if *v { "true" } else { "false" })?,
+            Value::Chars(v) => write_json_str(v, out)?,
+            Value::Digit(v) if self.integers.quotes(*v, scoped) => write!(out, "\"{}\"", v)?,
+            Value::Digit(v) => write!(out, "{}", v)?,
+            Value::Float(v) => self.write_f64(*v, out)?,
+            Value::IpAddr(v) => write!(out, "\"{}\"", v)?,
+            Value::Time(v) => self.opts.time.write_quoted(v, out, write_json_str)?,
+            Value::Obj(v) => self.write_object_at(v, out, depth + 1, scoped)?,
+            Value::Array(v) => self.write_array_at(v, out, depth + 1, scoped)?,
+            Value::IpNet(_)
+            | Value::Domain(_)
+            | Value::Url(_)
+            | Value::Email(_)
+            | Value::IdCard(_)
+            | Value::MobilePhone(_)
+            | Value::Hex(_)
+            | Value::Symbol(_) => write_json_str(&value.to_string(), out)?,
+            Value::Ignore(_) => {
+                self.opts
+                    .substitute(|| FormatError::unsupported("json", value))?;
+                out.write_str("\"\"")?
+            }
+        }
+        Ok(())
+    }
+
+    fn write_f64(&self, value: f64, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        match self.opts.float.action(value) {
+            FloatAction::Native | FloatAction::Finite if self.canonical && value.is_finite() => {
+                write_ecmascript(value, out)?
+            }
+            FloatAction::Native => self.write_f64_native(value, out)?,
+            FloatAction::Finite => self.opts.float.write_finite(value, out)?,
+            FloatAction::Null => self.write_null(out)?,
+            FloatAction::Text(text) => write_json_str(text, out)?,
+            FloatAction::Error => return Err(FormatError::non_finite("json", value)),
+        }
+        Ok(())
+    }
+
+    /// NaN 输出 `null`，无穷大输出带引号的 `Infinity`；严格模式下返回错误
+    fn write_f64_native(&self, value: f64, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        if let Some(n) = serde_json::Number::from_f64(value) {
+            write!(out, "{}", n)?;
+            return Ok(());
+        }
+        self.opts
+            .substitute(|| FormatError::non_finite("json", value))?;
+        if value.is_nan() {
+            out.write_str("null")?;
+        } else if value.is_sign_positive() {
+            out.write_str("\"Infinity\"")?;
+        } else {
+            out.write_str("\"-Infinity\"")?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn write_object(
+        &self,
+        value: &ObjectValue,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        self.write_object_at(value, out, 1, self.integers.root_scope())
+    }
+
+    pub(crate) fn write_array(
+        &self,
+        value: &[FieldStorage],
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        self.write_array_at(value, out, 1, self.integers.root_scope())
+    }
+
+    fn write_object_at(
+        &self,
+        value: &ObjectValue,
+        out: &mut dyn fmt::Write,
+        depth: usize,
+        scoped: bool,
+    ) -> Result<(), FormatError> {
+        self.opts.check_depth(depth)?;
+        let fields = value
+            .iter()
+            .filter(|(_, f)| !self.opts.omits(f.get_value()));
+        out.write_char('{')?;
+        for (i, (k, field)) in self.ordered(fields, |(k, _)| k.as_str()).enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
             }
+            self.write_key(k, out)?;
+            let scoped = self.integers.enter(k, scoped);
+            self.write_value_at(field.get_value(), out, depth, scoped)?;
         }
-        Value::IpAddr(v) => JsonValue::String(v.to_string()),
-        Value::Time(v) => JsonValue::String(v.to_string()),
-        Value::Obj(v) => {
-            let mut map = serde_json::Map::new();
-            for (k, field) in v.iter() {
-                map.insert(k.to_string(), to_json_value(field.get_value()));
+        out.write_char('}')?;
+        Ok(())
+    }
+
+    /// 写出还原后的对象树，顶层位于第 `depth` 层
+    fn write_nodes(
+        &self,
+        nodes: &[(String, Node<'_>)],
+        out: &mut dyn fmt::Write,
+        depth: usize,
+        scoped: bool,
+    ) -> Result<(), FormatError> {
+        out.write_char('{')?;
+        for (i, (k, node)) in self.ordered(nodes.iter(), |(k, _)| k).enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            self.write_key(k, out)?;
+            let scoped = self.integers.enter(k, scoped);
+            match node {
+                Node::Leaf(value) => self.write_value_at(value, out, depth, scoped)?,
+                Node::Branch(children) => {
+                    self.opts.check_depth(depth + 1)?;
+                    self.write_nodes(children, out, depth + 1, scoped)?;
+                }
             }
-            JsonValue::Object(map)
         }
-        Value::Array(v) => {
-            JsonValue::Array(v.iter().map(|f| to_json_value(f.get_value())).collect())
+        out.write_char('}')?;
+        Ok(())
+    }
+
+    fn write_array_at(
+        &self,
+        value: &[FieldStorage],
+        out: &mut dyn fmt::Write,
+        depth: usize,
+        scoped: bool,
+    ) -> Result<(), FormatError> {
+        self.opts.check_depth(depth)?;
+        out.write_char('[')?;
+        for (i, field) in value
+            .iter()
+            .filter(|f| !self.opts.omits(f.get_value()))
+            .enumerate()
+        {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            self.write_value_at(field.get_value(), out, depth, scoped)?;
         }
-        _ => JsonValue::Null,
+        out.write_char(']')?;
+        Ok(())
     }
 }
 
@@ -158,7 +763,11 @@ fn to_json_value(value: &Value) -> JsonValue {
 #[allow(deprecated)]
 mod tests {
     use super::*;
+    use crate::Collision;
+    use crate::SerializeValue;
     use crate::formatter::DataFormat;
+    use crate::integer::IntegerPolicy;
+    use serde_json::Value as JsonValue;
     use std::net::IpAddr;
     use std::str::FromStr;
     use wp_model_core::model::DataField;
@@ -273,6 +882,7 @@ mod tests {
             to_json_value(&Value::Digit(42)),
             JsonValue::Number(42.into())
         );
+        assert_eq!(Json::default().format_value(&Value::Digit(42)), "42");
     }
 
     #[test]
@@ -346,6 +956,494 @@ mod tests {
         let record = make_record_with_obj();
         assert_eq!(json.format_record(&record), json.fmt_record(&record));
     }
+
+    #[test]
+    fn test_strict_non_finite() {
+        let json = Json::default().with_strict(true);
+        let err = json.try_format_value(&Value::Float(f64::NAN)).unwrap_err();
+        assert!(matches!(
+            err,
+            FormatError::Unrepresentable { format: "json", .. }
+        ));
+        assert_eq!(json.try_format_value(&Value::Float(1.5)).unwrap(), "1.5");
+        // 宽松模式保持原有替换行为
+        assert_eq!(
+            Json::default().format_value(&Value::Float(f64::NAN)),
+            "null"
+        );
+    }
+
+    #[test]
+    fn test_strict_ignore() {
+        let json = Json::default().with_strict(true);
+        let err = json
+            .try_format_value(&Value::Ignore(Default::default()))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            FormatError::UnsupportedType {
+                format: "json",
+                type_name: "ignore"
+            }
+        ));
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let mut inner = ObjectValue::new();
+        inner.insert(
+            "x".to_string(),
+            FieldStorage::from_owned(DataField::from_digit("x", 1)),
+        );
+        let mut outer = ObjectValue::new();
+        outer.insert(
+            "inner".to_string(),
+            FieldStorage::from_owned(DataField::from_obj("inner", inner)),
+        );
+        let value = Value::Obj(outer);
+
+        let json = Json::default().with_max_depth(1);
+        let err = json.try_format_value(&value).unwrap_err();
+        assert!(matches!(err, FormatError::DepthExceeded { limit: 1 }));
+        assert_eq!(json.format_value(&value), "");
+        assert_eq!(
+            Json::default().with_max_depth(2).format_value(&value),
+            r#"{"inner":{"x":1}}"#
+        );
+    }
+
+    fn make_slash_record() -> DataRecord {
+        let mut http = ObjectValue::new();
+        http.insert(
+            "method".to_string(),
+            FieldStorage::from_owned(DataField::from_chars("method", "GET")),
+        );
+        DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_chars("http/request", "/index")),
+                FieldStorage::from_owned(DataField::from_chars("host", "web01")),
+                FieldStorage::from_owned(DataField::from_digit("http/status", 200)),
+                FieldStorage::from_owned(DataField::from_obj("http", http)),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_unflatten() {
+        let json = Json::default().with_unflatten(Unflatten::new());
+        assert_eq!(
+            json.fmt_record(&make_slash_record()),
+            r#"{"http":{"request":"/index","status":200,"method":"GET"},"host":"web01"}"#
+        );
+        let json = Json::default().with_unflatten(Unflatten::new().with_delimiter("."));
+        assert_eq!(
+            json.fmt_record(&make_slash_record()),
+            r#"{"http/request":"/index","host":"web01","http/status":200,"http":{"method":"GET"}}"#
+        );
+    }
+
+    #[test]
+    fn test_unflatten_existing_obj_first() {
+        let mut http = ObjectValue::new();
+        http.insert(
+            "status".to_string(),
+            FieldStorage::from_owned(DataField::from_digit("status", 200)),
+        );
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_obj("http", http)),
+                FieldStorage::from_owned(DataField::from_chars("http/tls/version", "1.3")),
+            ],
+        };
+        let json = Json::default().with_unflatten(Unflatten::new());
+        assert_eq!(
+            json.fmt_record(&record),
+            r#"{"http":{"status":200,"tls":{"version":"1.3"}}}"#
+        );
+    }
+
+    #[test]
+    fn test_unflatten_conflict() {
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_digit("http", 1)),
+                FieldStorage::from_owned(DataField::from_digit("http/status", 200)),
+            ],
+        };
+        let err = Json::default()
+            .with_unflatten(Unflatten::new())
+            .try_fmt_record(&record)
+            .unwrap_err();
+        assert!(matches!(err, FormatError::DuplicateField { ref name } if name == "http"));
+
+        let fmt = |collision| {
+            Json::default()
+                .with_unflatten(Unflatten::new().with_collision(collision))
+                .fmt_record(&record)
+        };
+        assert_eq!(fmt(Collision::KeepFirst), r#"{"http":1}"#);
+        assert_eq!(fmt(Collision::KeepLast), r#"{"http":{"status":200}}"#);
+        assert_eq!(
+            fmt(Collision::Suffix),
+            r#"{"http":1,"http_2":{"status":200}}"#
+        );
+    }
+
+    #[test]
+    fn test_unflatten_keep_last_replaces_subtree() {
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_chars("http/request", "GET")),
+                FieldStorage::from_owned(DataField::from_digit("http", 1)),
+                FieldStorage::from_owned(DataField::from_digit("http/status", 200)),
+                FieldStorage::from_owned(DataField::from_digit("tls/version", 3)),
+                FieldStorage::from_owned(DataField::from_digit("tls/version", 4)),
+            ],
+        };
+        let json =
+            Json::default().with_unflatten(Unflatten::new().with_collision(Collision::KeepLast));
+        // 标量 `http` 替换整个已合并的子树，只有冲突的叶子 `tls/version` 被单独替换
+        assert_eq!(
+            json.fmt_record(&record),
+            r#"{"http":{"status":200},"tls":{"version":4}}"#
+        );
+    }
+
+    #[test]
+    fn test_unflatten_omit_and_depth() {
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::new(DataType::default(), "a/b", Value::Null)),
+                FieldStorage::from_owned(DataField::from_digit("a/c/d", 1)),
+            ],
+        };
+        let json = Json::default()
+            .with_null_policy(NullPolicy::Omit)
+            .with_unflatten(Unflatten::new());
+        assert_eq!(json.fmt_record(&record), r#"{"a":{"c":{"d":1}}}"#);
+        let err = json.with_max_depth(1).try_fmt_record(&record).unwrap_err();
+        assert!(matches!(err, FormatError::DepthExceeded { limit: 1 }));
+    }
+
+    #[test]
+    fn test_key_escaping() {
+        let json = Json::default();
+        let field = FieldStorage::from_owned(DataField::from_digit("a\"b\\c\n", 1));
+        assert_eq!(json.fmt_field(&field), r#""a\"b\\c\n":1"#);
+        assert_eq!(Json::stdfmt_field(&field), r#""a\"b\\c\n":1"#);
+
+        let mut obj = ObjectValue::new();
+        obj.insert(
+            "x\ty".to_string(),
+            FieldStorage::from_owned(DataField::from_digit("x\ty", 2)),
+        );
+        assert_eq!(json.format_value(&Value::Obj(obj)), r#"{"x\ty":2}"#);
+
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![field],
+        };
+        let text = json.fmt_record(&record);
+        assert!(serde_json::from_str::<JsonValue>(&text).is_ok());
+    }
+
+    #[test]
+    fn test_key_policy() {
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_digit("ok", 1)),
+                FieldStorage::from_owned(DataField::from_digit("bad\"key\u{1}", 2)),
+            ],
+        };
+        let err = Json::default()
+            .with_key_policy(KeyPolicy::Validate)
+            .try_fmt_record(&record)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            FormatError::Unrepresentable { format: "json", .. }
+        ));
+
+        let json = Json::default().with_key_policy(KeyPolicy::Replace('_'));
+        assert_eq!(json.fmt_record(&record), r#"{"ok":1,"bad_key_":2}"#);
+    }
+
+    #[test]
+    fn test_pretty() {
+        let mut obj = ObjectValue::new();
+        obj.insert(
+            "k".to_string(),
+            FieldStorage::from_owned(DataField::from_chars("k", "a,b:{c}")),
+        );
+        obj.insert(
+            "empty".to_string(),
+            FieldStorage::from_owned(DataField::from_obj("empty", ObjectValue::new())),
+        );
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_chars("name", "q\"[x]")),
+                FieldStorage::from_owned(DataField::from_obj("obj", obj)),
+                FieldStorage::from_owned(DataField::from_arr(
+                    "arr",
+                    vec![DataField::from_digit("", 1), DataField::from_digit("", 2)],
+                )),
+                FieldStorage::from_owned(DataField::from_arr("none", vec![])),
+            ],
+        };
+        let text = Json::pretty(2).fmt_record(&record);
+        assert_eq!(
+            text,
+            r#"{
+  "name": "q\"[x]",
+  "obj": {
+    "empty": {},
+    "k": "a,b:{c}"
+  },
+  "arr": [
+    1,
+    2
+  ],
+  "none": []
+}"#
+        );
+        let compact = Json::default().fmt_record(&record);
+        assert_eq!(
+            serde_json::from_str::<JsonValue>(&text).unwrap(),
+            serde_json::from_str::<JsonValue>(&compact).unwrap()
+        );
+        assert!(!compact.contains('\n'));
+    }
+
+    #[test]
+    fn test_pretty_value_and_field() {
+        let json = Json::pretty(4);
+        let value = Value::Array(vec![FieldStorage::from_owned(DataField::from_chars(
+            "", "x",
+        ))]);
+        assert_eq!(json.format_value(&value), "[\n    \"x\"\n]");
+        assert_eq!(json.format_value(&Value::Digit(1)), "1");
+        let field = FieldStorage::from_owned(DataField::from_digit("n", 1));
+        assert_eq!(json.fmt_field(&field), r#""n": 1"#);
+    }
+
+    /// 经 `SerializeValue` 得到的 `serde_json::Value` 树
+    fn to_json_value(value: &Value) -> JsonValue {
+        serde_json::to_value(SerializeValue::new(value)).unwrap()
+    }
+
+    fn make_big_int_record() -> DataRecord {
+        let mut obj = ObjectValue::new();
+        obj.insert(
+            "id".to_string(),
+            FieldStorage::from_owned(DataField::from_digit("id", i64::MAX)),
+        );
+        obj.insert(
+            "n".to_string(),
+            FieldStorage::from_owned(DataField::from_digit("n", 1)),
+        );
+        DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_digit("small", 42)),
+                FieldStorage::from_owned(DataField::from_digit("big", 1 << 60)),
+                FieldStorage::from_owned(DataField::from_obj("obj", obj)),
+                FieldStorage::from_owned(DataField::from_arr(
+                    "arr",
+                    vec![DataField::from_digit("", -(1 << 55))],
+                )),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_integer_policy() {
+        let record = make_big_int_record();
+        assert_eq!(
+            Json::default().fmt_record(&record),
+            r#"{"small":42,"big":1152921504606846976,"obj":{"id":9223372036854775807,"n":1},"arr":[-36028797018963968]}"#
+        );
+        let json = Json::default().with_integer_policy(IntegerPolicy::unsafe_as_string());
+        assert_eq!(
+            json.fmt_record(&record),
+            r#"{"small":42,"big":"1152921504606846976","obj":{"id":"9223372036854775807","n":1},"arr":["-36028797018963968"]}"#
+        );
+        let json = Json::default().with_integer_policy(IntegerPolicy::all_as_string());
+        assert_eq!(json.format_value(&Value::Digit(1)), r#""1""#);
+    }
+
+    #[test]
+    fn test_integer_policy_fields() {
+        let record = make_big_int_record();
+        let json = Json::default()
+            .with_integer_policy(IntegerPolicy::all_as_string().with_fields(["obj", "small"]));
+        assert_eq!(
+            json.fmt_record(&record),
+            r#"{"small":"42","big":1152921504606846976,"obj":{"id":"9223372036854775807","n":"1"},"arr":[-36028797018963968]}"#
+        );
+        // 对象成员名同样匹配
+        let policy = IntegerPolicy::unsafe_as_string().with_fields(["id"]);
+        let json = Json::default().with_integer_policy(policy.clone());
+        assert_eq!(
+            json.fmt_record(&record),
+            r#"{"small":42,"big":1152921504606846976,"obj":{"id":"9223372036854775807","n":1},"arr":[-36028797018963968]}"#
+        );
+        let obj = record.items[2].get_value();
+        assert_eq!(
+            serde_json::to_value(SerializeValue::new(obj).with_format(&json)).unwrap(),
+            serde_json::json!({"id": "9223372036854775807", "n": 1})
+        );
+        let json = Json::default().with_integer_policy(IntegerPolicy::unsafe_as_string());
+        assert_eq!(
+            json.format_value(&Value::Digit(1 << 60)),
+            r#""1152921504606846976""#
+        );
+    }
+
+    fn make_typed_record() -> DataRecord {
+        let ts = chrono::NaiveDateTime::parse_from_str("2024-01-15 10:30:45", "%Y-%m-%d %H:%M:%S")
+            .unwrap();
+        let mut obj = ObjectValue::new();
+        obj.insert(
+            "src".to_string(),
+            FieldStorage::from_owned(DataField::from_ip(
+                "src",
+                IpAddr::from_str("10.0.0.2").unwrap(),
+            )),
+        );
+        DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_ip(
+                    "ip",
+                    IpAddr::from_str("10.0.0.1").unwrap(),
+                )),
+                FieldStorage::from_owned(DataField::from_time("ts", ts)),
+                FieldStorage::from_owned(DataField::from_chars("host", "web01")),
+                FieldStorage::from_owned(DataField::from_obj("net", obj)),
+                FieldStorage::from_owned(DataField::from_arr(
+                    "ports",
+                    vec![DataField::from_digit("", 80)],
+                )),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_type_style_triples() {
+        let json = Json::default().with_type_style(TypeStyle::Triples);
+        assert_eq!(
+            json.fmt_record(&make_typed_record()),
+            concat!(
+                r#"[{"name":"ip","type":"ip","value":"10.0.0.1"},"#,
+                r#"{"name":"ts","type":"time","value":"2024-01-15 10:30:45"},"#,
+                r#"{"name":"host","type":"chars","value":"web01"},"#,
+                r#"{"name":"net","type":"obj","value":[{"name":"src","type":"ip","value":"10.0.0.2"}]},"#,
+                r#"{"name":"ports","type":"array/digit","value":[{"name":"","type":"digit","value":80}]}]"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_type_style_wrappers() {
+        let json = Json::default().with_type_style(TypeStyle::Wrappers);
+        assert_eq!(
+            json.fmt_record(&make_typed_record()),
+            concat!(
+                r#"{"ip":{"$ip":"10.0.0.1"},"ts":{"$time":"2024-01-15 10:30:45"},"#,
+                r#""host":"web01","net":{"src":{"$ip":"10.0.0.2"}},"ports":[80]}"#
+            )
+        );
+
+        let status = FieldStorage::from_owned(DataField::new(
+            DataType::HttpStatus,
+            "status",
+            Value::Digit(200),
+        ));
+        assert_eq!(json.fmt_field(&status), r#""status":{"$http/status":200}"#);
+
+        let inferred = FieldStorage::from_owned(DataField::new(
+            DataType::Auto,
+            "peer",
+            Value::IpAddr(IpAddr::from_str("::1").unwrap()),
+        ));
+        assert_eq!(json.fmt_field(&inferred), r#""peer":{"$ip":"::1"}"#);
+    }
+
+    #[test]
+    fn test_type_style_field() {
+        let ip = FieldStorage::from_owned(DataField::from_ip(
+            "ip",
+            IpAddr::from_str("10.0.0.1").unwrap(),
+        ));
+        let json = Json::default().with_type_style(TypeStyle::Triples);
+        assert_eq!(
+            json.fmt_field(&ip),
+            r#"{"name":"ip","type":"ip","value":"10.0.0.1"}"#
+        );
+        let plain = Json::default();
+        assert_eq!(plain.fmt_field(&ip), r#""ip":"10.0.0.1""#);
+    }
+
+    #[test]
+    fn test_canonical() {
+        let mut obj = ObjectValue::new();
+        obj.insert(
+            "b".to_string(),
+            FieldStorage::from_owned(DataField::from_float("b", 1.0)),
+        );
+        obj.insert(
+            "a".to_string(),
+            FieldStorage::from_owned(DataField::from_float("a", 1e21)),
+        );
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_chars("zeta", "é\u{7f}\n")),
+                FieldStorage::from_owned(DataField::from_obj("obj", obj)),
+                FieldStorage::from_owned(DataField::from_chars("\u{1f600}", "emoji")),
+                FieldStorage::from_owned(DataField::from_chars("\u{ff61}", "halfwidth")),
+                FieldStorage::from_owned(DataField::from_float("pi", 2.5)),
+            ],
+        };
+        let expected = concat!(
+            r#"{"obj":{"a":1e+21,"b":1},"pi":2.5,"zeta":"é"#,
+            "\u{7f}",
+            r#"\n","😀":"emoji","｡":"halfwidth"}"#
+        );
+        assert_eq!(Json::canonical().fmt_record(&record), expected);
+        // 美化和浮点数位数设置在规范模式下不生效
+        let json = Json::pretty(2)
+            .with_float_policy(FloatPolicy::new().with_precision(3))
+            .with_canonical(true);
+        assert_eq!(json.fmt_record(&record), expected);
+    }
+
+    #[test]
+    fn test_canonical_nested_modes() {
+        let json = Json::canonical().with_unflatten(Unflatten::new());
+        assert_eq!(
+            json.fmt_record(&make_slash_record()),
+            r#"{"host":"web01","http":{"method":"GET","request":"/index","status":200}}"#
+        );
+
+        let typed = Json::canonical().with_type_style(TypeStyle::Triples);
+        let record = make_record_with_obj();
+        assert_eq!(
+            typed.fmt_record(&record),
+            concat!(
+                r#"[{"name":"extends","type":"obj","value":[{"name":"ssl_cipher","type":"chars","value":"ECDHE"}]},"#,
+                r#"{"name":"length","type":"digit","value":50},"#,
+                r#"{"name":"status","type":"digit","value":200}]"#
+            )
+        );
+    }
 }
 
 // ============================================================================
@@ -353,71 +1451,58 @@ mod tests {
 // ============================================================================
 
 #[allow(clippy::items_after_test_module)]
+impl ValueWriter for Json {
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        self.layout(out, |out| {
+            self.write_value_at(value, out, 0, self.integers.root_scope())
+        })
+    }
+}
+
+impl RecordWriter for Json {
+    fn write_field(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        self.layout(out, |out| self.write_field_compact(field, out))
+    }
+
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        self.layout(out, |out| self.write_record_compact(record, out))
+    }
+}
+
 impl ValueFormatter for Json {
     type Output = String;
 
     fn format_value(&self, value: &Value) -> String {
-        match value {
-            Value::Null => "null".to_string(),
-            Value::Bool(v) => json!(v).to_string(),
-            Value::Chars(v) => serde_json::to_string(v).unwrap_or_else(|_| "\"\"".to_string()),
-            Value::Digit(v) => json!(v).to_string(),
-            Value::Float(v) => {
-                if v.is_nan() {
-                    "null".to_string()
-                } else if v.is_infinite() {
-                    if v.is_sign_positive() {
-                        "\"Infinity\"".to_string()
-                    } else {
-                        "\"-Infinity\"".to_string()
-                    }
-                } else {
-                    json!(v).to_string()
-                }
-            }
-            Value::IpAddr(v) => json!(v.to_string()).to_string(),
-            Value::Time(v) => json!(v.to_string()).to_string(),
-            Value::Obj(v) => {
-                let mut json_obj = serde_json::Map::new();
-                for (k, field) in v.iter() {
-                    json_obj.insert(k.to_string(), to_json_value(field.get_value()));
-                }
-                json!(json_obj).to_string()
-            }
-            Value::Array(v) => {
-                let items: Vec<String> = v
-                    .iter()
-                    .map(|field| self.format_value(field.get_value()))
-                    .collect();
-                format!("[{}]", items.join(","))
-            }
-            _ => json!(value.to_string()).to_string(),
-        }
+        self.try_format_value(value).unwrap_or_default()
+    }
+
+    fn try_format_value(&self, value: &Value) -> Result<String, FormatError> {
+        render(|out| self.write_value(value, out))
     }
 }
 
 impl RecordFormatter for Json {
     fn fmt_field(&self, field: &FieldStorage) -> String {
-        if field.get_name().is_empty() {
-            self.format_value(field.get_value())
-        } else {
-            format!(
-                "\"{}\":{}",
-                field.get_name(),
-                self.format_value(field.get_value())
-            )
-        }
+        self.try_fmt_field(field).unwrap_or_default()
     }
 
     fn fmt_record(&self, record: &DataRecord) -> String {
-        let mut items = Vec::new();
-        for field in record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-        {
-            items.push(self.fmt_field(field));
-        }
-        format!("{{{}}}", items.join(","))
+        self.try_fmt_record(record).unwrap_or_default()
+    }
+
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<String, FormatError> {
+        render(|out| self.write_field(field, out))
+    }
+
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<String, FormatError> {
+        render(|out| self.write_record_fmt(record, out))
     }
 }
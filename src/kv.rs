@@ -1,13 +1,20 @@
+use crate::error::FormatError;
+use crate::flatten::Flatten;
+use crate::float::{FloatAction, FloatPolicy};
 #[allow(deprecated)]
 use crate::formatter::DataFormat;
-use crate::formatter::{RecordFormatter, ValueFormatter};
-use std::fmt::Write;
-use wp_model_core::model::{DataRecord, DataType, FieldStorage, types::value::ObjectValue};
+use crate::formatter::{RecordFormatter, RecordWriter, ValueFormatter, ValueWriter, render};
+use crate::null::NullPolicy;
+use crate::options::FmtOptions;
+use crate::time::TimeFormat;
+use std::fmt;
+use wp_model_core::model::{DataRecord, DataType, FieldStorage, Value, types::value::ObjectValue};
 
 pub struct KeyValue {
     pair_separator: String,
     key_value_separator: String,
     quote_strings: bool,
+    opts: FmtOptions,
 }
 
 impl Default for KeyValue {
@@ -16,6 +23,7 @@ impl Default for KeyValue {
             pair_separator: ", ".to_string(),
             key_value_separator: ": ".to_string(),
             quote_strings: true,
+            opts: FmtOptions::default(),
         }
     }
 }
@@ -36,13 +44,140 @@ impl KeyValue {
         self.quote_strings = quote;
         self
     }
+    /// 严格模式：不支持的值类型返回错误，而不是输出其文本形式
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.opts.strict = strict;
+        self
+    }
+    /// 嵌套对象/数组允许的最大层数
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.opts.max_depth = max_depth;
+        self
+    }
+    /// `Value::Time` 的输出方式，默认与 `NaiveDateTime` 的 `Display` 一致
+    pub fn with_time_format(mut self, time: TimeFormat) -> Self {
+        self.opts.time = time;
+        self
+    }
+    /// 浮点数的位数、科学计数法和 NaN/Infinity 处理方式
+    pub fn with_float_policy(mut self, float: FloatPolicy) -> Self {
+        self.opts.float = float;
+        self
+    }
+    /// 空值字段的处理方式：原生空值、省略或占位文本
+    pub fn with_null_policy(mut self, null: NullPolicy) -> Self {
+        self.opts.null = null;
+        self
+    }
+    /// 将嵌套对象和数组展开为多个顶层字段，如 `extends.ssl_cipher`、`tags[0]`
+    pub fn with_flatten(mut self, flatten: Flatten) -> Self {
+        self.opts.flatten = Some(flatten);
+        self
+    }
 
-    fn format_string_value(&self, value: &str) -> String {
-        if self.quote_strings {
-            format!("\"{}\"", value.replace('\"', "\\\""))
-        } else {
-            value.to_string()
+    fn write_string_value(&self, value: &str, out: &mut dyn fmt::Write) -> fmt::Result {
+        if !self.quote_strings {
+            return out.write_str(value);
+        }
+        out.write_char('"')?;
+        for (i, part) in value.split('"').enumerate() {
+            if i > 0 {
+                out.write_str("\\\"")?;
+            }
+            out.write_str(part)?;
         }
+        out.write_char('"')
+    }
+
+    fn write_null(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        match self.opts.null.text() {
+            Some(text) => out.write_str(text),
+            None => Ok(()),
+        }
+    }
+
+    fn write_value_at(
+        &self,
+        value: &Value,
+        out: &mut dyn fmt::Write,
+        depth: usize,
+    ) -> Result<(), FormatError> {
+        match value {
+            Value::Null => self.write_null(out)?,
+            Value::Bool(v) => out.write_str(if *v { "true" } else { "false" })?,
+            Value::Chars(v) => self.write_string_value(v, out)?,
+            Value::Digit(v) => write!(out, "{}", v)?,
+            Value::Float(v) => match self.opts.float.action(*v) {
+                FloatAction::Native => write!(out, "{}", v)?,
+                FloatAction::Finite => self.opts.float.write_finite(*v, out)?,
+                FloatAction::Null => self.write_null(out)?,
+                FloatAction::Text(text) => self.write_string_value(text, out)?,
+                FloatAction::Error => return Err(FormatError::non_finite("kv", *v)),
+            },
+            Value::Obj(obj) => self.write_object_at(obj, out, depth + 1)?,
+            Value::Array(arr) => self.write_array_at(arr, out, depth + 1)?,
+            Value::Time(v) => self.opts.time.write(v, out)?,
+            Value::IpNet(_)
+            | Value::IpAddr(_)
+            | Value::Domain(_)
+            | Value::Url(_)
+            | Value::Email(_)
+            | Value::IdCard(_)
+            | Value::MobilePhone(_)
+            | Value::Hex(_)
+            | Value::Symbol(_) => write!(out, "{}", value)?,
+            Value::Ignore(_) => {
+                self.opts
+                    .substitute(|| FormatError::unsupported("kv", value))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_object_at(
+        &self,
+        value: &ObjectValue,
+        out: &mut dyn fmt::Write,
+        depth: usize,
+    ) -> Result<(), FormatError> {
+        self.opts.check_depth(depth)?;
+        out.write_char('{')?;
+        for (i, (k, field)) in value
+            .iter()
+            .filter(|(_, f)| !self.opts.omits(f.get_value()))
+            .enumerate()
+        {
+            if i > 0 {
+                out.write_str(&self.pair_separator)?;
+            }
+            self.write_string_value(k, out)?;
+            out.write_str(&self.key_value_separator)?;
+            self.write_value_at(field.get_value(), out, depth)?;
+        }
+        out.write_char('}')?;
+        Ok(())
+    }
+
+    fn write_array_at(
+        &self,
+        value: &[FieldStorage],
+        out: &mut dyn fmt::Write,
+        depth: usize,
+    ) -> Result<(), FormatError> {
+        self.opts.check_depth(depth)?;
+        out.write_char('[')?;
+        for (i, field) in value
+            .iter()
+            .filter(|f| !self.opts.omits(f.get_value()))
+            .enumerate()
+        {
+            if i > 0 {
+                out.write_str(&self.pair_separator)?;
+            }
+            self.write_value_at(field.get_value(), out, depth)?;
+        }
+        out.write_char(']')?;
+        Ok(())
     }
 }
 
@@ -51,77 +186,41 @@ impl DataFormat for KeyValue {
     type Output = String;
 
     fn format_null(&self) -> String {
-        String::new()
+        self.format_value(&Value::Null)
     }
     fn format_bool(&self, v: &bool) -> String {
         if *v { "true".into() } else { "false".into() }
     }
     fn format_string(&self, v: &str) -> String {
-        self.format_string_value(v)
+        render(|out| Ok(self.write_string_value(v, out)?)).unwrap_or_default()
     }
     fn format_i64(&self, v: &i64) -> String {
         v.to_string()
     }
     fn format_f64(&self, v: &f64) -> String {
-        v.to_string()
+        self.format_value(&Value::Float(*v))
     }
     fn format_ip(&self, v: &std::net::IpAddr) -> String {
         v.to_string()
     }
     fn format_datetime(&self, v: &chrono::NaiveDateTime) -> String {
-        v.to_string()
+        self.format_value(&Value::Time(*v))
     }
 
     fn format_object(&self, value: &ObjectValue) -> String {
-        let mut output = String::new();
-        output.push('{');
-        for (i, (k, v)) in value.iter().enumerate() {
-            if i > 0 {
-                output.push_str(&self.pair_separator);
-            }
-            write!(
-                output,
-                "{}{}{}",
-                self.format_string(k),
-                self.key_value_separator,
-                self.fmt_value(v.get_value())
-            )
-            .unwrap();
-        }
-        output.push('}');
-        output
+        render(|out| self.write_object_at(value, out, 1)).unwrap_or_default()
     }
 
     fn format_array(&self, value: &[FieldStorage]) -> String {
-        let mut output = String::new();
-        output.push('[');
-        for (i, field) in value.iter().enumerate() {
-            if i > 0 {
-                output.push_str(&self.pair_separator);
-            }
-            output.push_str(&self.fmt_value(field.get_value()));
-        }
-        output.push(']');
-        output
+        render(|out| self.write_array_at(value, out, 1)).unwrap_or_default()
     }
 
     fn format_field(&self, field: &FieldStorage) -> String {
-        format!(
-            "{}{}{}",
-            field.get_name(),
-            self.key_value_separator,
-            self.fmt_value(field.get_value())
-        )
+        self.fmt_field(field)
     }
 
     fn format_record(&self, record: &DataRecord) -> String {
-        record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|field| self.format_field(field))
-            .collect::<Vec<_>>()
-            .join(&self.pair_separator)
+        self.fmt_record(record)
     }
 }
 
@@ -339,72 +438,70 @@ mod tests {
 // ============================================================================
 
 #[allow(clippy::items_after_test_module)]
-impl ValueFormatter for KeyValue {
-    type Output = String;
+impl ValueWriter for KeyValue {
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        self.write_value_at(value, out, 0)
+    }
+}
 
-    fn format_value(&self, value: &wp_model_core::model::Value) -> String {
-        use wp_model_core::model::Value;
-        match value {
-            Value::Null => String::new(),
-            Value::Bool(v) => if *v { "true" } else { "false" }.to_string(),
-            Value::Chars(v) => self.format_string_value(v),
-            Value::Digit(v) => v.to_string(),
-            Value::Float(v) => v.to_string(),
-            Value::IpAddr(v) => v.to_string(),
-            Value::Time(v) => v.to_string(),
-            Value::Obj(obj) => {
-                let mut output = String::new();
-                output.push('{');
-                for (i, (k, field)) in obj.iter().enumerate() {
-                    if i > 0 {
-                        output.push_str(&self.pair_separator);
-                    }
-                    write!(
-                        output,
-                        "{}{}{}",
-                        self.format_string_value(k),
-                        self.key_value_separator,
-                        self.format_value(field.get_value())
-                    )
-                    .unwrap();
-                }
-                output.push('}');
-                output
-            }
-            Value::Array(arr) => {
-                let mut output = String::new();
-                output.push('[');
-                for (i, field) in arr.iter().enumerate() {
-                    if i > 0 {
-                        output.push_str(&self.pair_separator);
-                    }
-                    output.push_str(&self.format_value(field.get_value()));
-                }
-                output.push(']');
-                output
+impl RecordWriter for KeyValue {
+    fn write_field(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        out.write_str(field.get_name())?;
+        out.write_str(&self.key_value_separator)?;
+        self.write_value(field.get_value(), out)
+    }
+
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        let record = self.opts.flattened(record)?;
+        for (i, field) in record
+            .items
+            .iter()
+            .filter(|f| *f.get_meta() != DataType::Ignore && !self.opts.omits(f.get_value()))
+            .enumerate()
+        {
+            if i > 0 {
+                out.write_str(&self.pair_separator)?;
             }
-            _ => value.to_string(),
+            self.write_field(field, out)?;
         }
+        Ok(())
+    }
+}
+
+impl ValueFormatter for KeyValue {
+    type Output = String;
+
+    fn format_value(&self, value: &Value) -> String {
+        self.try_format_value(value).unwrap_or_default()
+    }
+
+    fn try_format_value(&self, value: &Value) -> Result<String, FormatError> {
+        render(|out| self.write_value(value, out))
     }
 }
 
 impl RecordFormatter for KeyValue {
     fn fmt_field(&self, field: &FieldStorage) -> String {
-        format!(
-            "{}{}{}",
-            field.get_name(),
-            self.key_value_separator,
-            self.format_value(field.get_value())
-        )
+        self.try_fmt_field(field).unwrap_or_default()
     }
 
     fn fmt_record(&self, record: &DataRecord) -> String {
-        record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|field| self.fmt_field(field))
-            .collect::<Vec<_>>()
-            .join(&self.pair_separator)
+        self.try_fmt_record(record).unwrap_or_default()
+    }
+
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<String, FormatError> {
+        render(|out| self.write_field(field, out))
+    }
+
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<String, FormatError> {
+        render(|out| self.write_record_fmt(record, out))
     }
 }
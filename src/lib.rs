@@ -1,20 +1,69 @@
+mod batch;
+mod bulk;
+mod cef;
+mod cloudevents;
+mod config;
 mod csv;
+mod error;
+mod fingerprint;
+mod flatten;
+mod float;
 pub mod fmt_meta;
 mod formatter;
+mod gelf;
+mod integer;
 mod json;
 mod kv;
+mod loki;
+mod meta;
+mod null;
+mod options;
+mod otlp;
+mod projected;
 mod proto;
 mod raw;
+mod registry;
+mod serialize;
+mod splunk;
 mod sql;
+mod syslog;
+mod template;
+mod time;
+mod unflatten;
 
+pub use batch::{JsonBatch, JsonLayout};
+pub use bulk::{BulkAction, EsBulk};
+pub use cef::Cef;
+pub use cloudevents::{BinaryEvent, CloudEvent};
+pub use config::{FlattenConfig, FloatConfig, FormatterConfig, TimeConfig};
 pub use csv::Csv;
+pub use error::{ConfigError, FormatError};
+pub use fingerprint::Fingerprint;
+pub use flatten::{Collision, Flatten};
+pub use float::{FloatPolicy, NonFinite};
 #[allow(deprecated)]
-pub use formatter::{DataFormat, RecordFormatter, StaticDataFormatter, ValueFormatter};
-pub use json::Json;
+pub use formatter::{
+    DataFormat, RecordFormatter, RecordWriter, StaticDataFormatter, ValueFormatter, ValueWriter,
+};
+pub use gelf::Gelf;
+pub use integer::{IntegerPolicy, MAX_SAFE_INTEGER};
+pub use json::{Json, KeyPolicy, TypeStyle};
 pub use kv::KeyValue;
+pub use loki::{Loki, MAX_LABEL_VALUE_LEN};
+pub use meta::MetaValue;
+pub use null::NullPolicy;
+pub use options::DEFAULT_MAX_DEPTH;
+pub use otlp::OtlpLogs;
+pub use projected::Projected;
 pub use proto::ProtoTxt;
 pub use raw::Raw;
-pub use sql::SqlInsert;
+pub use registry::{BoxedFormatter, FormatterRegistry};
+pub use serialize::{SerializeRecord, SerializeValue};
+pub use splunk::SplunkHec;
+pub use sql::{SqlDialect, SqlInsert};
+pub use syslog::{Framing, Syslog, SyslogProtocol};
+pub use time::TimeFormat;
+pub use unflatten::Unflatten;
 
 use wp_model_core::model::fmt_def::TextFmt;
 
@@ -25,6 +74,18 @@ pub enum FormatType {
     Sql(SqlInsert),
     Raw(Raw),
     ProtoText(ProtoTxt),
+    /// 用户自定义格式，通常由 `FormatterRegistry` 创建
+    Custom(BoxedFormatter),
+}
+
+impl FormatType {
+    /// 包装自定义格式化器
+    pub fn custom<F>(formatter: F) -> Self
+    where
+        F: RecordFormatter<Output = String> + Send + Sync + 'static,
+    {
+        FormatType::Custom(Box::new(formatter))
+    }
 }
 
 impl From<&TextFmt> for FormatType {
@@ -240,4 +301,46 @@ mod tests {
         assert!(result.contains("name"));
         assert!(result.contains("Alice"));
     }
+
+    #[test]
+    fn test_custom_format_type() {
+        use wp_model_core::model::Value;
+        // 自定义格式化器同时支持新接口和旧的 DataFormat 接口
+        let fmt = FormatType::custom(KeyValue::default().with_key_value_separator("="));
+        let field = FieldStorage::from_owned(DataField::from_digit("n", 7));
+        assert_eq!(fmt.fmt_field(&field), "n=7");
+        assert_eq!(fmt.format_field(&field), "n=7");
+        assert_eq!(fmt.format_i64(&7), fmt.format_value(&Value::Digit(7)));
+    }
+
+    #[test]
+    fn test_configured_json_format_type() {
+        use wp_model_core::model::{DataType, Value};
+        // 旧的 DataFormat 接口同样遵循 Json 实例上的选项
+        let ts = chrono::NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S")
+            .unwrap();
+        let fmt = FormatType::Json(
+            Json::pretty(2)
+                .with_null_policy(NullPolicy::Omit)
+                .with_time_format(TimeFormat::epoch_seconds()),
+        );
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_chars("a", "x")),
+                FieldStorage::from_owned(DataField::new(DataType::default(), "n", Value::Null)),
+                FieldStorage::from_owned(DataField::from_time("t", ts)),
+            ],
+        };
+        assert_eq!(
+            fmt.format_record(&record),
+            "{\n  \"a\": \"x\",\n  \"t\": 1565093539\n}"
+        );
+        assert_eq!(fmt.format_datetime(&ts), "1565093539");
+
+        // 严格模式下出错时返回空文本，与 `fmt_record` 一致
+        let fmt = FormatType::Json(Json::default().with_strict(true));
+        assert_eq!(fmt.format_f64(&f64::NAN), "");
+        assert_eq!(fmt.format_f64(&1.5), "1.5");
+    }
 }
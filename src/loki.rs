@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+use std::{fmt, io};
+
+use chrono::FixedOffset;
+use wp_model_core::model::{DataRecord, Value};
+
+use crate::error::FormatError;
+use crate::formatter::{IoSink, RecordFormatter, render};
+use crate::json::write_json_str;
+use crate::meta::{MetaValue, without_fields};
+use crate::projected::lookup;
+use crate::time::TimeFormat;
+
+/// 标签值的最大字节数，与 Loki 的默认 `max_label_value_length` 一致
+pub const MAX_LABEL_VALUE_LEN: usize = 2048;
+
+/// 一组按名称排序的标签
+type Labels = Vec<(String, String)>;
+
+/// Grafana Loki push API 请求体
+///
+/// 按标签集合把记录分组为 `streams`，每条记录输出为 `["<unix-nanos>","<line>"]`，
+/// 行文本由内部的 `RecordFormatter`（如 `Json`、`KeyValue`、`Raw`）生成。
+/// stream 按首次出现的顺序排列，stream 内保持记录顺序。
+///
+/// - 标签名中 `[a-zA-Z0-9_]` 以外的字符替换为 `_`，数字开头时加 `_` 前缀；
+///   `__` 开头的名称由 Loki 保留，开头的连续下划线合并为一个
+/// - 多个标签同名（包括替换字符后同名）时，按配置顺序取第一个有值的标签
+/// - 标签值中的控制字符替换为空格，超过 `MAX_LABEL_VALUE_LEN` 字节时截断；
+///   字段缺失或值为空时省略该标签；Loki 不接受空标签集合，记录没有任何标签时返回
+///   `FormatError::Unrepresentable`，可用 `with_label` 添加固定标签避免
+/// - 时间取自 `with_time_field` 指定的 `Value::Time` 字段，时区由 `with_time_offset`
+///   指定（默认 UTC）；未设置或字段缺失时使用当前时间
+///
+/// ```
+/// use wp_data_fmt::{Json, Loki, MetaValue};
+///
+/// let loki = Loki::new(Json::default())
+///     .with_label_fields(["host", "app"])
+///     .with_label("job", MetaValue::text("nginx"))
+///     .with_time_field("timestamp");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Loki<F> {
+    inner: F,
+    labels: Vec<(String, MetaValue)>,
+    time_field: Option<String>,
+    time: TimeFormat,
+    remove_fields: bool,
+}
+
+impl<F> Loki<F> {
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            labels: Vec::new(),
+            time_field: None,
+            time: TimeFormat::epoch_nanos(),
+            remove_fields: false,
+        }
+    }
+
+    /// 添加标签，名称按 Loki 的规则处理
+    pub fn with_label(mut self, name: impl Into<String>, value: MetaValue) -> Self {
+        self.labels.push((label_name(&name.into()), value));
+        self
+    }
+
+    /// 以同名字段的值作为标签
+    pub fn with_label_fields<I, S>(self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        fields.into_iter().fold(self, |loki, field| {
+            let field = field.into();
+            loki.with_label(field.clone(), MetaValue::Field(field))
+        })
+    }
+
+    pub fn with_time_field(mut self, field: impl Into<String>) -> Self {
+        self.time_field = Some(field.into());
+        self
+    }
+
+    /// `Value::Time` 所在的时区
+    pub fn with_time_offset(mut self, offset: FixedOffset) -> Self {
+        self.time = self.time.with_offset(offset);
+        self
+    }
+
+    /// 行文本中去掉作为标签来源的顶层字段和时间字段
+    pub fn with_remove_fields(mut self, remove_fields: bool) -> Self {
+        self.remove_fields = remove_fields;
+        self
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    fn stream_labels(&self, record: &DataRecord) -> Labels {
+        let mut labels = Labels::new();
+        for (name, value) in &self.labels {
+            if labels.iter().any(|(n, _)| n == name) {
+                continue;
+            }
+            let Some(value) = value.resolve(record) else {
+                continue;
+            };
+            let value = label_value(&value);
+            if !value.is_empty() {
+                labels.push((name.clone(), value));
+            }
+        }
+        labels.sort();
+        labels
+    }
+
+    fn timestamp(&self, record: &DataRecord) -> Result<String, FormatError> {
+        let mut out = String::new();
+        let value = self
+            .time_field
+            .as_deref()
+            .and_then(|name| lookup(record, name))
+            .map(|f| f.get_value());
+        match value {
+            Some(Value::Time(t)) => self.time.write(t, &mut out)?,
+            None | Some(Value::Null | Value::Ignore(_)) => {
+                let now = chrono::Utc::now().naive_utc();
+                TimeFormat::epoch_nanos().write(&now, &mut out)?
+            }
+            Some(value) => return Err(FormatError::unsupported("loki", value)),
+        }
+        Ok(out)
+    }
+}
+
+impl<F: RecordFormatter<Output = String>> Loki<F> {
+    pub fn format_batch<'a>(&self, records: impl IntoIterator<Item = &'a DataRecord>) -> String {
+        self.try_format_batch(records).unwrap_or_default()
+    }
+
+    pub fn try_format_batch<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a DataRecord>,
+    ) -> Result<String, FormatError> {
+        render(|out| self.write_batch_fmt(records, out))
+    }
+
+    /// 分组后写出 `{"streams":[..]}`；需要先读取整批记录
+    pub fn write_batch_fmt<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a DataRecord>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        let mut streams: Vec<(Labels, Vec<(String, String)>)> = Vec::new();
+        let mut index: HashMap<Labels, usize> = HashMap::new();
+        let removed: Vec<&str> = self
+            .labels
+            .iter()
+            .filter_map(|(_, value)| value.field_name())
+            .chain(self.time_field.as_deref())
+            .collect();
+        for record in records {
+            let labels = self.stream_labels(record);
+            if labels.is_empty() {
+                return Err(FormatError::Unrepresentable {
+                    format: "loki",
+                    detail: "a record without stream labels".to_string(),
+                });
+            }
+            let line = if self.remove_fields {
+                self.inner
+                    .try_fmt_record(&without_fields(record, &removed))?
+            } else {
+                self.inner.try_fmt_record(record)?
+            };
+            let entry = (self.timestamp(record)?, line);
+            match index.get(&labels) {
+                Some(&i) => streams[i].1.push(entry),
+                None => {
+                    index.insert(labels.clone(), streams.len());
+                    streams.push((labels, vec![entry]));
+                }
+            }
+        }
+
+        out.write_str("{\"streams\":[")?;
+        for (i, (labels, values)) in streams.iter().enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            out.write_str("{\"stream\":{")?;
+            for (j, (name, value)) in labels.iter().enumerate() {
+                if j > 0 {
+                    out.write_char(',')?;
+                }
+                write_json_str(name, out)?;
+                out.write_char(':')?;
+                write_json_str(value, out)?;
+            }
+            out.write_str("},\"values\":[")?;
+            for (j, (ts, line)) in values.iter().enumerate() {
+                if j > 0 {
+                    out.write_char(',')?;
+                }
+                write!(out, "[\"{}\",", ts)?;
+                write_json_str(line, out)?;
+                out.write_char(']')?;
+            }
+            out.write_str("]}")?;
+        }
+        out.write_str("]}")?;
+        Ok(())
+    }
+
+    /// 写入 `io::Write`
+    pub fn write_batch<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a DataRecord>,
+        out: &mut dyn io::Write,
+    ) -> Result<(), FormatError> {
+        let mut sink = IoSink::new(out);
+        self.write_batch_fmt(records, &mut sink)
+            .map_err(|e| sink.map_error(e))
+    }
+}
+
+/// 标签名须匹配 `[a-zA-Z_][a-zA-Z0-9_]*`
+fn label_name(name: &str) -> String {
+    let mut label: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if label.is_empty() || label.starts_with(|c: char| c.is_ascii_digit()) {
+        label.insert(0, '_');
+    }
+    if label.starts_with("__") {
+        label = format!("_{}", label.trim_start_matches('_'));
+    }
+    label
+}
+
+/// 控制字符替换为空格，按字符边界截断到 `MAX_LABEL_VALUE_LEN` 字节并去掉首尾空白
+fn label_value(value: &str) -> String {
+    let mut label = String::with_capacity(value.len().min(MAX_LABEL_VALUE_LEN));
+    for c in value.chars() {
+        let c = if c.is_control() { ' ' } else { c };
+        if label.len() + c.len_utf8() > MAX_LABEL_VALUE_LEN {
+            break;
+        }
+        label.push(c);
+    }
+    label.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Json, KeyValue};
+    use wp_model_core::model::{DataField, FieldStorage};
+
+    fn make_record(host: &str, n: i64) -> DataRecord {
+        let ts = chrono::NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S")
+            .unwrap()
+            + chrono::Duration::seconds(n);
+        DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_time("ts", ts)),
+                FieldStorage::from_owned(DataField::from_chars("host", host)),
+                FieldStorage::from_owned(DataField::from_digit("n", n)),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_streams() {
+        let records = [
+            make_record("web01", 0),
+            make_record("web02", 1),
+            make_record("web01", 2),
+        ];
+        let loki = Loki::new(Json::default())
+            .with_label_fields(["host"])
+            .with_label("job", MetaValue::text("nginx"))
+            .with_time_field("ts")
+            .with_remove_fields(true);
+        assert_eq!(
+            loki.format_batch(&records),
+            concat!(
+                r#"{"streams":[{"stream":{"host":"web01","job":"nginx"},"values":["#,
+                r#"["1565093539000000000","{\"n\":0}"],["1565093541000000000","{\"n\":2}"]]},"#,
+                r#"{"stream":{"host":"web02","job":"nginx"},"values":["#,
+                r#"["1565093540000000000","{\"n\":1}"]]}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_inner_formatter_and_offset() {
+        let loki = Loki::new(KeyValue::default())
+            .with_label_fields(["missing"])
+            .with_time_field("ts")
+            .with_time_offset(FixedOffset::east_opt(3600).unwrap());
+        // 没有任何标签的 stream 会被 Loki 拒绝
+        assert!(matches!(
+            loki.try_format_batch(&[make_record("web01", 0)]),
+            Err(FormatError::Unrepresentable { format: "loki", .. })
+        ));
+        assert_eq!(loki.format_batch(&[]), r#"{"streams":[]}"#);
+
+        let loki = loki.with_label("job", MetaValue::text("nginx"));
+        assert_eq!(
+            loki.format_batch(&[make_record("web01", 0)]),
+            concat!(
+                r#"{"streams":[{"stream":{"job":"nginx"},"values":["#,
+                r#"["1565089939000000000","ts: 2019-08-06 12:12:19, host: \"web01\", n: 0"]]}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_duplicate_labels_keep_configured_order() {
+        let loki = Loki::new(Json::default())
+            .with_label("env", MetaValue::text("prod"))
+            .with_label("env", MetaValue::text("dev"))
+            .with_label("host-name", MetaValue::field("missing"))
+            .with_label("host_name", MetaValue::field("host"))
+            .with_label("host.name", MetaValue::text("fallback"));
+        assert_eq!(
+            loki.stream_labels(&make_record("web01", 0)),
+            vec![
+                ("env".to_string(), "prod".to_string()),
+                ("host_name".to_string(), "web01".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_label_sanitizing() {
+        assert_eq!(label_name("http.status"), "http_status");
+        assert_eq!(label_name("1st"), "_1st");
+        assert_eq!(label_name(""), "_");
+        assert_eq!(label_name("__name__"), "_name__");
+        assert_eq!(label_name("@@meta"), "_meta");
+        assert_eq!(label_name("__"), "_");
+        assert_eq!(label_value(" a\nb\t"), "a b");
+        assert_eq!(label_value(&"é".repeat(2000)).len(), MAX_LABEL_VALUE_LEN);
+    }
+}
@@ -0,0 +1,117 @@
+use std::borrow::Cow;
+
+use wp_model_core::model::{DataRecord, Value};
+
+use crate::projected::lookup;
+
+/// 信封元数据的取值：固定文本或记录中的字段
+///
+/// 字段按 `Projected` 的路径规则查找，字符串原样使用，其他值使用 `Display` 文本；
+/// 字段缺失或为空值时视为未设置。
+///
+/// ```
+/// use wp_data_fmt::MetaValue;
+///
+/// let host = MetaValue::field("hostname");
+/// let index = MetaValue::text("main");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetaValue {
+    Text(String),
+    Field(String),
+}
+
+impl MetaValue {
+    pub fn text(text: impl Into<String>) -> Self {
+        MetaValue::Text(text.into())
+    }
+
+    pub fn field(path: impl Into<String>) -> Self {
+        MetaValue::Field(path.into())
+    }
+
+    /// 取出记录对应的文本
+    pub(crate) fn resolve<'a>(&'a self, record: &'a DataRecord) -> Option<Cow<'a, str>> {
+        match self {
+            MetaValue::Text(text) => Some(Cow::Borrowed(text)),
+            MetaValue::Field(path) => match lookup(record, path)?.get_value() {
+                Value::Null | Value::Ignore(_) => None,
+                Value::Chars(s) => Some(Cow::Borrowed(s)),
+                value => Some(Cow::Owned(value.to_string())),
+            },
+        }
+    }
+
+    /// 引用的字段名
+    pub(crate) fn field_name(&self) -> Option<&str> {
+        match self {
+            MetaValue::Field(path) => Some(path),
+            MetaValue::Text(_) => None,
+        }
+    }
+}
+
+/// 去掉已提升为元数据的顶层字段；没有需要去掉的字段时不复制记录
+pub(crate) fn without_fields<'a>(record: &'a DataRecord, names: &[&str]) -> Cow<'a, DataRecord> {
+    if !record.items.iter().any(|f| names.contains(&f.get_name())) {
+        return Cow::Borrowed(record);
+    }
+    Cow::Owned(DataRecord {
+        id: record.id,
+        items: record
+            .items
+            .iter()
+            .filter(|f| !names.contains(&f.get_name()))
+            .cloned()
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wp_model_core::model::{DataField, FieldStorage};
+
+    fn make_record() -> DataRecord {
+        DataRecord {
+            id: 5,
+            items: vec![
+                FieldStorage::from_owned(DataField::from_chars("host", "web01")),
+                FieldStorage::from_owned(DataField::from_digit("port", 443)),
+                FieldStorage::from_owned(DataField::new(Default::default(), "empty", Value::Null)),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let record = make_record();
+        assert_eq!(
+            MetaValue::text("main").resolve(&record).as_deref(),
+            Some("main")
+        );
+        assert_eq!(
+            MetaValue::field("host").resolve(&record).as_deref(),
+            Some("web01")
+        );
+        assert_eq!(
+            MetaValue::field("port").resolve(&record).as_deref(),
+            Some("443")
+        );
+        assert_eq!(MetaValue::field("empty").resolve(&record), None);
+        assert_eq!(MetaValue::field("missing").resolve(&record), None);
+    }
+
+    #[test]
+    fn test_without_fields() {
+        let record = make_record();
+        assert!(matches!(
+            without_fields(&record, &["missing"]),
+            Cow::Borrowed(_)
+        ));
+        let body = without_fields(&record, &["host", "empty"]);
+        assert_eq!(body.id, 5);
+        assert_eq!(body.items.len(), 1);
+        assert_eq!(body.items[0].get_name(), "port");
+    }
+}
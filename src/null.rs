@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+/// `Value::Null` 字段的处理方式，由所有格式化器共享
+///
+/// 该策略同样作用于 `NonFinite::Null` 产生的空值。配置文件中写作 `"native"`、`"omit"`
+/// 或 `{ placeholder = "-" }`。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NullPolicy {
+    /// 输出该格式原生的空值：JSON `null`、SQL `NULL`，其余格式为空文本
+    #[default]
+    Native,
+    /// 省略字段（对象中省略键，数组中省略元素）；
+    /// CSV 和 SQL 需要保持列对齐，仍输出原生空值
+    Omit,
+    /// 输出占位文本，如 `-` 或 `\N`；JSON、ProtoText 和 SQL 中作为字符串加引号
+    Placeholder(String),
+}
+
+impl NullPolicy {
+    pub fn placeholder(text: impl Into<String>) -> Self {
+        NullPolicy::Placeholder(text.into())
+    }
+
+    pub(crate) fn omits(&self) -> bool {
+        *self == NullPolicy::Omit
+    }
+
+    /// 需要输出的占位文本，`None` 表示使用原生空值
+    pub(crate) fn text(&self) -> Option<&str> {
+        match self {
+            NullPolicy::Placeholder(text) => Some(text),
+            NullPolicy::Native | NullPolicy::Omit => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_null_policy() {
+        assert_eq!(NullPolicy::default(), NullPolicy::Native);
+        assert!(NullPolicy::Omit.omits());
+        assert_eq!(NullPolicy::Omit.text(), None);
+        let dash = NullPolicy::placeholder("-");
+        assert!(!dash.omits());
+        assert_eq!(dash.text(), Some("-"));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_format_null_matches_policy() {
+        use crate::formatter::{DataFormat, ValueFormatter};
+        use crate::{Csv, FormatType, KeyValue, ProtoTxt, Raw};
+        use wp_model_core::model::Value;
+
+        for null in [NullPolicy::Native, NullPolicy::placeholder("-")] {
+            let formats = [
+                FormatType::Csv(Csv::default().with_null_policy(null.clone())),
+                FormatType::Kv(KeyValue::default().with_null_policy(null.clone())),
+                FormatType::Raw(Raw::default().with_null_policy(null.clone())),
+                FormatType::ProtoText(ProtoTxt::default().with_null_policy(null.clone())),
+            ];
+            for fmt in &formats {
+                assert_eq!(
+                    DataFormat::format_null(fmt),
+                    ValueFormatter::format_value(fmt, &Value::Null)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::borrow::Cow;
+
+use wp_model_core::model::{DataRecord, Value};
+
+use crate::error::FormatError;
+use crate::flatten::Flatten;
+use crate::float::FloatPolicy;
+use crate::null::NullPolicy;
+use crate::time::TimeFormat;
+
+/// 默认的最大嵌套层数
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// 各格式化器共享的通用选项
+#[derive(Debug, Clone)]
+pub(crate) struct FmtOptions {
+    /// 严格模式：遇到无法表示的值时返回错误，而不是静默替换
+    pub(crate) strict: bool,
+    /// 允许的最大嵌套层数
+    pub(crate) max_depth: usize,
+    /// `Value::Time` 的输出方式
+    pub(crate) time: TimeFormat,
+    /// 浮点数输出策略
+    pub(crate) float: FloatPolicy,
+    /// 空值输出策略
+    pub(crate) null: NullPolicy,
+    /// 嵌套对象展平策略，仅 CSV、SQL 和 KV 使用
+    pub(crate) flatten: Option<Flatten>,
+}
+
+impl Default for FmtOptions {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl FmtOptions {
+    /// 默认选项，可用于 const 上下文
+    pub(crate) const DEFAULT: Self = Self {
+        strict: false,
+        max_depth: DEFAULT_MAX_DEPTH,
+        time: TimeFormat::DEFAULT,
+        float: FloatPolicy::DEFAULT,
+        null: NullPolicy::Native,
+        flatten: None,
+    };
+
+    /// 进入第 `depth` 层嵌套前检查是否超过限制
+    pub(crate) fn check_depth(&self, depth: usize) -> Result<(), FormatError> {
+        if depth > self.max_depth {
+            Err(FormatError::DepthExceeded {
+                limit: self.max_depth,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 值是否应从所在的对象、数组或记录中省略
+    pub(crate) fn omits(&self, value: &Value) -> bool {
+        match value {
+            Value::Null => self.null.omits(),
+            Value::Float(v) => self.float.skips(*v),
+            _ => false,
+        }
+    }
+
+    /// 设置了展平策略时返回展平后的记录，否则原样借用
+    pub(crate) fn flattened<'a>(
+        &self,
+        record: &'a DataRecord,
+    ) -> Result<Cow<'a, DataRecord>, FormatError> {
+        match &self.flatten {
+            Some(flatten) => Ok(Cow::Owned(flatten.flatten(record)?)),
+            None => Ok(Cow::Borrowed(record)),
+        }
+    }
+
+    /// 需要替换值时调用：严格模式返回错误，宽松模式返回 `Ok` 由调用方写出替代值
+    pub(crate) fn substitute(&self, err: impl FnOnce() -> FormatError) -> Result<(), FormatError> {
+        if self.strict { Err(err()) } else { Ok(()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_depth() {
+        let opts = FmtOptions {
+            max_depth: 1,
+            ..Default::default()
+        };
+        assert!(opts.check_depth(1).is_ok());
+        assert!(matches!(
+            opts.check_depth(2),
+            Err(FormatError::DepthExceeded { limit: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_substitute() {
+        let lenient = FmtOptions::default();
+        assert!(
+            lenient
+                .substitute(|| FormatError::DepthExceeded { limit: 0 })
+                .is_ok()
+        );
+        let strict = FmtOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(
+            strict
+                .substitute(|| FormatError::DepthExceeded { limit: 0 })
+                .is_err()
+        );
+    }
+}
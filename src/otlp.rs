@@ -0,0 +1,510 @@
+use std::{fmt, io};
+
+use wp_model_core::model::{DataRecord, DataType, FieldStorage, Value};
+
+use crate::Json;
+use crate::error::FormatError;
+use crate::float::{non_finite_name, write_ecmascript};
+use crate::formatter::{
+    IoSink, RecordFormatter, RecordWriter, ValueFormatter, ValueWriter, render,
+};
+use crate::json::write_json_str;
+use crate::meta::{MetaValue, without_fields};
+use crate::projected::lookup;
+use crate::time::TimeFormat;
+
+/// OpenTelemetry OTLP/JSON 日志
+///
+/// `fmt_record` 输出单个 `LogRecord`，`format_batch` 输出可直接 POST 到
+/// `/v1/logs` 的 `{"resourceLogs":[{"resource":..,"scopeLogs":[{"scope":..,"logRecords":[..]}]}]}`。
+///
+/// - `body` 取自消息字段（默认 `message`），字段缺失时省略
+/// - `timeUnixNano` 取自 `with_time_field` 指定的 `Value::Time` 字段，
+///   时区取 `Json` 的 `TimeFormat::with_offset`；该字段为 `fixed64`，
+///   1970 年之前或超出 u64 纳秒范围（约 2554 年）的时间返回 `FormatError::Unrepresentable`
+/// - `severityText` 取自 `with_severity`
+/// - 其余字段输出为 `attributes`，值为带类型的 `AnyValue`：`stringValue`、
+///   `intValue`（字符串形式）、`doubleValue`、`boolValue`、`kvlistValue`、`arrayValue`；
+///   NaN/Infinity 按 proto3 JSON 规则输出为字符串，空值字段省略
+///
+/// 时间字段按 `Json` 的 `TimeFormat` 转为 `stringValue`（epoch 格式为 `intValue`），
+/// IP 等其他类型输出为 `stringValue`。嵌套层数和键名遵循 `Json` 的设置。
+///
+/// ```
+/// use wp_data_fmt::{MetaValue, OtlpLogs};
+///
+/// let otlp = OtlpLogs::new()
+///     .with_body_field("msg")
+///     .with_time_field("timestamp")
+///     .with_severity(MetaValue::field("level"))
+///     .with_resource_attribute("service.name", "nginx")
+///     .with_scope("wp-connector", "0.2.3");
+/// ```
+#[derive(Debug, Clone)]
+pub struct OtlpLogs {
+    json: Json,
+    body_field: String,
+    time_field: Option<String>,
+    severity: Option<MetaValue>,
+    resource: Vec<(String, String)>,
+    scope: Option<(String, String)>,
+}
+
+impl Default for OtlpLogs {
+    fn default() -> Self {
+        Self {
+            json: Json::default(),
+            body_field: "message".to_string(),
+            time_field: None,
+            severity: None,
+            resource: Vec::new(),
+            scope: None,
+        }
+    }
+}
+
+impl OtlpLogs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 时间、嵌套层数和键名的选项
+    pub fn with_json(mut self, json: Json) -> Self {
+        self.json = json;
+        self
+    }
+
+    pub fn with_body_field(mut self, field: impl Into<String>) -> Self {
+        self.body_field = field.into();
+        self
+    }
+
+    pub fn with_time_field(mut self, field: impl Into<String>) -> Self {
+        self.time_field = Some(field.into());
+        self
+    }
+
+    pub fn with_severity(mut self, severity: MetaValue) -> Self {
+        self.severity = Some(severity);
+        self
+    }
+
+    /// 批量输出的资源属性，如 `service.name`
+    pub fn with_resource_attribute(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.resource.push((key.into(), value.into()));
+        self
+    }
+
+    /// 批量输出的 instrumentation scope 名称和版本
+    pub fn with_scope(mut self, name: impl Into<String>, version: impl Into<String>) -> Self {
+        self.scope = Some((name.into(), version.into()));
+        self
+    }
+
+    pub fn format_batch<'a>(&self, records: impl IntoIterator<Item = &'a DataRecord>) -> String {
+        self.try_format_batch(records).unwrap_or_default()
+    }
+
+    pub fn try_format_batch<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a DataRecord>,
+    ) -> Result<String, FormatError> {
+        render(|out| self.write_batch_fmt(records, out))
+    }
+
+    /// 写出 `ExportLogsServiceRequest`，所有记录位于同一个 resource 和 scope 下
+    pub fn write_batch_fmt<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a DataRecord>,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        out.write_str("{\"resourceLogs\":[{\"resource\":{\"attributes\":[")?;
+        for (i, (key, value)) in self.resource.iter().enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            write_string_attribute(key, value, out)?;
+        }
+        out.write_str("]},\"scopeLogs\":[{\"scope\":{")?;
+        if let Some((name, version)) = &self.scope {
+            out.write_str("\"name\":")?;
+            write_json_str(name, out)?;
+            out.write_str(",\"version\":")?;
+            write_json_str(version, out)?;
+        }
+        out.write_str("},\"logRecords\":[")?;
+        for (i, record) in records.into_iter().enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            self.write_record_fmt(record, out)?;
+        }
+        out.write_str("]}]}]}")?;
+        Ok(())
+    }
+
+    /// 流式写入 `io::Write`
+    pub fn write_batch<'a>(
+        &self,
+        records: impl IntoIterator<Item = &'a DataRecord>,
+        out: &mut dyn io::Write,
+    ) -> Result<(), FormatError> {
+        let mut sink = IoSink::new(out);
+        self.write_batch_fmt(records, &mut sink)
+            .map_err(|e| sink.map_error(e))
+    }
+
+    fn write_time(&self, record: &DataRecord, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        let Some(name) = &self.time_field else {
+            return Ok(());
+        };
+        match lookup(record, name).map(|f| f.get_value()) {
+            None | Some(Value::Null | Value::Ignore(_)) => {}
+            Some(Value::Time(t)) => {
+                let nanos = match self.json.opts().time.offset() {
+                    Some(offset) => TimeFormat::epoch_nanos().with_offset(offset),
+                    None => TimeFormat::epoch_nanos(),
+                };
+                let nanos = nanos.try_format(t)?;
+                if nanos.parse::<u64>().is_err() {
+                    return Err(FormatError::Unrepresentable {
+                        format: "otlp",
+                        detail: format!("time {} is outside the timeUnixNano range", t),
+                    });
+                }
+                write!(out, "\"timeUnixNano\":\"{}\",", nanos)?;
+            }
+            Some(value) => return Err(FormatError::unsupported("otlp", value)),
+        }
+        Ok(())
+    }
+
+    /// 写出 `{"key":..,"value":{AnyValue}}`
+    fn write_attribute(
+        &self,
+        key: &str,
+        value: &Value,
+        out: &mut dyn fmt::Write,
+        depth: usize,
+    ) -> Result<(), FormatError> {
+        out.write_str("{\"key\":")?;
+        write_json_str(&self.json.checked_key(key)?, out)?;
+        out.write_str(",\"value\":")?;
+        self.write_any_value(value, out, depth)?;
+        out.write_char('}')?;
+        Ok(())
+    }
+
+    /// 写出 `AnyValue`，`depth` 为值所在的嵌套层数
+    fn write_any_value(
+        &self,
+        value: &Value,
+        out: &mut dyn fmt::Write,
+        depth: usize,
+    ) -> Result<(), FormatError> {
+        match value {
+            Value::Null | Value::Ignore(_) => out.write_str("{}")?,
+            Value::Bool(v) => write!(out, "{{\"boolValue\":{}}}", v)?,
+            Value::Digit(v) => write!(out, "{{\"intValue\":\"{}\"}}", v)?,
+            Value::Float(v) if v.is_finite() => {
+                out.write_str("{\"doubleValue\":")?;
+                write_ecmascript(*v, out)?;
+                out.write_char('}')?;
+            }
+            Value::Float(v) => {
+                self.json
+                    .opts()
+                    .substitute(|| FormatError::non_finite("otlp", *v))?;
+                write!(out, "{{\"doubleValue\":\"{}\"}}", non_finite_name(*v))?
+            }
+            Value::Chars(v) => {
+                out.write_str("{\"stringValue\":")?;
+                write_json_str(v, out)?;
+                out.write_char('}')?;
+            }
+            Value::Time(t) => {
+                let time = &self.json.opts().time;
+                if time.is_numeric() {
+                    out.write_str("{\"intValue\":\"")?;
+                    time.write(t, out)?;
+                    out.write_str("\"}")?;
+                } else {
+                    out.write_str("{\"stringValue\":")?;
+                    write_json_str(&time.try_format(t)?, out)?;
+                    out.write_char('}')?;
+                }
+            }
+            Value::Obj(obj) => {
+                self.json.opts().check_depth(depth + 1)?;
+                out.write_str("{\"kvlistValue\":{\"values\":[")?;
+                let items = obj.iter().filter(|(_, f)| !is_null(f));
+                for (i, (k, field)) in items.enumerate() {
+                    if i > 0 {
+                        out.write_char(',')?;
+                    }
+                    self.write_attribute(k, field.get_value(), out, depth + 1)?;
+                }
+                out.write_str("]}}")?;
+            }
+            Value::Array(arr) => {
+                self.json.opts().check_depth(depth + 1)?;
+                out.write_str("{\"arrayValue\":{\"values\":[")?;
+                for (i, field) in arr.iter().enumerate() {
+                    if i > 0 {
+                        out.write_char(',')?;
+                    }
+                    self.write_any_value(field.get_value(), out, depth + 1)?;
+                }
+                out.write_str("]}}")?;
+            }
+            Value::IpAddr(_)
+            | Value::IpNet(_)
+            | Value::Domain(_)
+            | Value::Url(_)
+            | Value::Email(_)
+            | Value::IdCard(_)
+            | Value::MobilePhone(_)
+            | Value::Hex(_)
+            | Value::Symbol(_) => {
+                out.write_str("{\"stringValue\":")?;
+                write_json_str(&value.to_string(), out)?;
+                out.write_char('}')?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_null(field: &FieldStorage) -> bool {
+    matches!(field.get_value(), Value::Null | Value::Ignore(_))
+}
+
+fn write_string_attribute(key: &str, value: &str, out: &mut dyn fmt::Write) -> fmt::Result {
+    out.write_str("{\"key\":")?;
+    write_json_str(key, out)?;
+    out.write_str(",\"value\":{\"stringValue\":")?;
+    write_json_str(value, out)?;
+    out.write_str("}}")
+}
+
+impl ValueWriter for OtlpLogs {
+    /// 写出值对应的 `AnyValue`
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        self.write_any_value(value, out, 0)
+    }
+}
+
+impl RecordWriter for OtlpLogs {
+    /// 写出字段对应的 attribute
+    fn write_field(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        self.write_attribute(field.get_name(), field.get_value(), out, 0)
+    }
+
+    /// 写出单个 `LogRecord`
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        out.write_char('{')?;
+        self.write_time(record, out)?;
+        if let Some(text) = self.severity.as_ref().and_then(|s| s.resolve(record)) {
+            out.write_str("\"severityText\":")?;
+            write_json_str(&text, out)?;
+            out.write_char(',')?;
+        }
+        if let Some(body) = lookup(record, &self.body_field).filter(|f| !is_null(f)) {
+            out.write_str("\"body\":")?;
+            self.write_any_value(body.get_value(), out, 0)?;
+            out.write_char(',')?;
+        }
+
+        let promoted: Vec<&str> = [Some(self.body_field.as_str())]
+            .into_iter()
+            .chain([self.time_field.as_deref()])
+            .chain([self.severity.as_ref().and_then(|s| s.field_name())])
+            .flatten()
+            .collect();
+        let rest = without_fields(record, &promoted);
+        out.write_str("\"attributes\":[")?;
+        let items = rest
+            .items
+            .iter()
+            .filter(|f| *f.get_meta() != DataType::Ignore && !is_null(f));
+        for (i, field) in items.enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            self.write_field(field, out)?;
+        }
+        out.write_str("]}")?;
+        Ok(())
+    }
+}
+
+impl ValueFormatter for OtlpLogs {
+    type Output = String;
+
+    fn format_value(&self, value: &Value) -> String {
+        self.try_format_value(value).unwrap_or_default()
+    }
+
+    fn try_format_value(&self, value: &Value) -> Result<String, FormatError> {
+        render(|out| self.write_value(value, out))
+    }
+}
+
+impl RecordFormatter for OtlpLogs {
+    fn fmt_field(&self, field: &FieldStorage) -> String {
+        self.try_fmt_field(field).unwrap_or_default()
+    }
+
+    fn fmt_record(&self, record: &DataRecord) -> String {
+        self.try_fmt_record(record).unwrap_or_default()
+    }
+
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<String, FormatError> {
+        render(|out| self.write_field(field, out))
+    }
+
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<String, FormatError> {
+        render(|out| self.write_record_fmt(record, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::IpAddr;
+    use std::str::FromStr;
+    use wp_model_core::model::DataField;
+    use wp_model_core::model::types::value::ObjectValue;
+
+    fn make_record() -> DataRecord {
+        let ts = chrono::NaiveDateTime::parse_from_str(
+            "2019-08-06 12:12:19.250",
+            "%Y-%m-%d %H:%M:%S%.f",
+        )
+        .unwrap();
+        let mut http = ObjectValue::new();
+        http.insert(
+            "status".to_string(),
+            FieldStorage::from_owned(DataField::from_digit("status", 200)),
+        );
+        DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_time("ts", ts)),
+                FieldStorage::from_owned(DataField::from_chars("message", "GET /index")),
+                FieldStorage::from_owned(DataField::from_chars("level", "INFO")),
+                FieldStorage::from_owned(DataField::from_ip(
+                    "ip",
+                    IpAddr::from_str("10.0.0.1").unwrap(),
+                )),
+                FieldStorage::from_owned(DataField::from_obj("http", http)),
+                FieldStorage::from_owned(DataField::from_arr(
+                    "tags",
+                    vec![
+                        DataField::from_chars("", "a"),
+                        DataField::from_bool("", true),
+                    ],
+                )),
+                FieldStorage::from_owned(DataField::from_float("rt", 0.25)),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_log_record() {
+        let otlp = OtlpLogs::new()
+            .with_time_field("ts")
+            .with_severity(MetaValue::field("level"));
+        assert_eq!(
+            otlp.fmt_record(&make_record()),
+            concat!(
+                r#"{"timeUnixNano":"1565093539250000000","severityText":"INFO","#,
+                r#""body":{"stringValue":"GET /index"},"attributes":["#,
+                r#"{"key":"ip","value":{"stringValue":"10.0.0.1"}},"#,
+                r#"{"key":"http","value":{"kvlistValue":{"values":[{"key":"status","value":{"intValue":"200"}}]}}},"#,
+                r#"{"key":"tags","value":{"arrayValue":{"values":[{"stringValue":"a"},{"boolValue":true}]}}},"#,
+                r#"{"key":"rt","value":{"doubleValue":0.25}}]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_time_range() {
+        let otlp = OtlpLogs::new().with_time_field("ts");
+        let record = |ts: &str| DataRecord {
+            id: Default::default(),
+            items: vec![FieldStorage::from_owned(DataField::from_time(
+                "ts",
+                chrono::NaiveDateTime::parse_from_str(ts, "%Y-%m-%d %H:%M:%S").unwrap(),
+            ))],
+        };
+        for ts in ["1969-12-31 23:59:59", "2600-01-01 00:00:00"] {
+            assert!(matches!(
+                otlp.try_fmt_record(&record(ts)),
+                Err(FormatError::Unrepresentable { format: "otlp", .. })
+            ));
+        }
+        // 超出 i64 纳秒但仍在 fixed64 范围内
+        assert_eq!(
+            otlp.fmt_record(&record("2300-01-01 00:00:00")),
+            r#"{"timeUnixNano":"10413792000000000000","attributes":[]}"#
+        );
+        assert_eq!(
+            otlp.fmt_record(&record("1970-01-01 00:00:00")),
+            r#"{"timeUnixNano":"0","attributes":[]}"#
+        );
+    }
+
+    #[test]
+    fn test_defaults_and_values() {
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_float("nan", f64::NAN)),
+                FieldStorage::from_owned(DataField::new(DataType::default(), "empty", Value::Null)),
+            ],
+        };
+        assert_eq!(
+            OtlpLogs::new().fmt_record(&record),
+            r#"{"attributes":[{"key":"nan","value":{"doubleValue":"NaN"}}]}"#
+        );
+        let strict = OtlpLogs::new().with_json(Json::default().with_strict(true));
+        assert!(strict.try_fmt_record(&record).is_err());
+    }
+
+    #[test]
+    fn test_batch() {
+        let otlp = OtlpLogs::new()
+            .with_resource_attribute("service.name", "nginx")
+            .with_scope("wp", "1.0");
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![FieldStorage::from_owned(DataField::from_chars(
+                "message", "hi",
+            ))],
+        };
+        assert_eq!(
+            otlp.format_batch([&record, &record]),
+            concat!(
+                r#"{"resourceLogs":[{"resource":{"attributes":[{"key":"service.name","value":{"stringValue":"nginx"}}]},"#,
+                r#""scopeLogs":[{"scope":{"name":"wp","version":"1.0"},"logRecords":["#,
+                r#"{"body":{"stringValue":"hi"},"attributes":[]},{"body":{"stringValue":"hi"},"attributes":[]}]}]}]}"#
+            )
+        );
+        let parsed: serde_json::Value =
+            serde_json::from_str(&OtlpLogs::new().format_batch(&[])).unwrap();
+        assert!(parsed["resourceLogs"][0]["scopeLogs"][0]["logRecords"].is_array());
+    }
+}
@@ -0,0 +1,263 @@
+use std::fmt;
+
+use wp_model_core::model::{DataField, DataRecord, DataType, FieldStorage, Value};
+
+use crate::error::FormatError;
+use crate::formatter::{RecordFormatter, RecordWriter, ValueFormatter, ValueWriter};
+
+/// 投影列：源字段路径与输出名称
+#[derive(Debug, Clone)]
+struct Column {
+    source: String,
+    name: String,
+}
+
+/// 字段投影适配器：按列定义选取、重命名并重排字段，再交给内部格式化器
+///
+/// 源路径用 `/` 访问嵌套对象，数字段访问数组元素，如 `http/status`、`tags/0`；
+/// 与路径完全同名的顶层字段优先匹配。
+///
+/// ```
+/// use wp_data_fmt::{Json, Projected};
+///
+/// let fmt = Projected::new(Json::default(), [("ip", "client_ip"), ("http/status", "status")])
+///     .with_fill_missing(true);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Projected<F> {
+    inner: F,
+    columns: Vec<Column>,
+    fill_missing: bool,
+}
+
+impl<F> Projected<F> {
+    /// 以 `(源路径, 输出名称)` 列表创建投影
+    pub fn new<I, S, N>(inner: F, columns: I) -> Self
+    where
+        I: IntoIterator<Item = (S, N)>,
+        S: Into<String>,
+        N: Into<String>,
+    {
+        Self {
+            inner,
+            columns: columns
+                .into_iter()
+                .map(|(source, name)| Column {
+                    source: source.into(),
+                    name: name.into(),
+                })
+                .collect(),
+            fill_missing: false,
+        }
+    }
+
+    /// 只选取并重排字段，不重命名
+    pub fn select<I, S>(inner: F, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::new(
+            inner,
+            fields.into_iter().map(|f| {
+                let f = f.into();
+                (f.clone(), f)
+            }),
+        )
+    }
+
+    /// 缺失的字段输出为 null，而不是直接跳过
+    pub fn with_fill_missing(mut self, fill_missing: bool) -> Self {
+        self.fill_missing = fill_missing;
+        self
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    /// 按列定义生成新记录
+    pub fn project(&self, record: &DataRecord) -> DataRecord {
+        let mut items = Vec::with_capacity(self.columns.len());
+        for column in &self.columns {
+            match lookup(record, &column.source) {
+                Some(field) => {
+                    let mut field = field.clone();
+                    field.set_name(column.name.as_str());
+                    items.push(field);
+                }
+                None if self.fill_missing => items.push(FieldStorage::from_owned(DataField::new(
+                    DataType::Auto,
+                    column.name.as_str(),
+                    Value::Null,
+                ))),
+                None => {}
+            }
+        }
+        DataRecord {
+            id: record.id,
+            items,
+        }
+    }
+
+    /// 单个字段按列定义重命名；不在列定义中的字段保持原样
+    fn rename(&self, field: &FieldStorage) -> Option<FieldStorage> {
+        let column = self.columns.iter().find(|c| c.source == field.get_name())?;
+        let mut field = field.clone();
+        field.set_name(column.name.as_str());
+        Some(field)
+    }
+}
+
+/// 按路径查找字段
+pub(crate) fn lookup<'a>(record: &'a DataRecord, path: &str) -> Option<&'a FieldStorage> {
+    if let Some(field) = record.items.iter().find(|f| f.get_name() == path) {
+        return Some(field);
+    }
+    let mut segments = path.split('/');
+    let first = segments.next()?;
+    let mut field = record.items.iter().find(|f| f.get_name() == first)?;
+    for segment in segments {
+        field = match field.get_value() {
+            Value::Obj(obj) => obj.get(segment)?,
+            Value::Array(arr) => arr.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(field)
+}
+
+impl<F: ValueFormatter> ValueFormatter for Projected<F> {
+    type Output = F::Output;
+
+    fn format_value(&self, value: &Value) -> Self::Output {
+        self.inner.format_value(value)
+    }
+
+    fn try_format_value(&self, value: &Value) -> Result<Self::Output, FormatError> {
+        self.inner.try_format_value(value)
+    }
+}
+
+impl<F: RecordFormatter> RecordFormatter for Projected<F> {
+    fn fmt_field(&self, field: &FieldStorage) -> Self::Output {
+        match self.rename(field) {
+            Some(renamed) => self.inner.fmt_field(&renamed),
+            None => self.inner.fmt_field(field),
+        }
+    }
+
+    fn fmt_record(&self, record: &DataRecord) -> Self::Output {
+        self.inner.fmt_record(&self.project(record))
+    }
+
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<Self::Output, FormatError> {
+        match self.rename(field) {
+            Some(renamed) => self.inner.try_fmt_field(&renamed),
+            None => self.inner.try_fmt_field(field),
+        }
+    }
+
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<Self::Output, FormatError> {
+        self.inner.try_fmt_record(&self.project(record))
+    }
+}
+
+impl<F: ValueWriter> ValueWriter for Projected<F> {
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        self.inner.write_value(value, out)
+    }
+}
+
+impl<F: RecordWriter> RecordWriter for Projected<F> {
+    fn write_field(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        match self.rename(field) {
+            Some(renamed) => self.inner.write_field(&renamed, out),
+            None => self.inner.write_field(field, out),
+        }
+    }
+
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        self.inner.write_record_fmt(&self.project(record), out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Csv, Json, KeyValue};
+    use wp_model_core::model::types::value::ObjectValue;
+
+    fn make_record() -> DataRecord {
+        let mut http = ObjectValue::new();
+        http.insert(
+            "status".to_string(),
+            FieldStorage::from_owned(DataField::from_digit("status", 200)),
+        );
+        DataRecord {
+            id: 7,
+            items: vec![
+                FieldStorage::from_owned(DataField::from_chars("method", "GET")),
+                FieldStorage::from_owned(DataField::from_chars("ip", "10.0.0.1")),
+                FieldStorage::from_owned(DataField::from_obj("http", http)),
+                FieldStorage::from_owned(DataField::from_arr(
+                    "tags",
+                    vec![
+                        DataField::from_chars("", "a"),
+                        DataField::from_chars("", "b"),
+                    ],
+                )),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_project_rename_reorder() {
+        let fmt = Projected::new(
+            Json::default(),
+            [("http/status", "status"), ("ip", "client_ip")],
+        );
+        assert_eq!(
+            fmt.fmt_record(&make_record()),
+            r#"{"status":200,"client_ip":"10.0.0.1"}"#
+        );
+        assert_eq!(fmt.project(&make_record()).id, 7);
+    }
+
+    #[test]
+    fn test_project_array_index() {
+        let fmt = Projected::select(Csv::default(), ["tags/1", "method"]);
+        assert_eq!(fmt.fmt_record(&make_record()), "b,GET");
+    }
+
+    #[test]
+    fn test_missing_fields() {
+        let columns = [("ip", "ip"), ("user_agent", "ua"), ("http/missing", "x")];
+        let fmt = Projected::new(KeyValue::default(), columns);
+        assert_eq!(fmt.fmt_record(&make_record()), r#"ip: "10.0.0.1""#);
+
+        let fmt = Projected::new(Csv::default(), columns).with_fill_missing(true);
+        assert_eq!(fmt.fmt_record(&make_record()), "10.0.0.1,,");
+    }
+
+    #[test]
+    fn test_field_rename() {
+        let fmt = Projected::new(Json::default(), [("ip", "client_ip")]);
+        let ip = FieldStorage::from_owned(DataField::from_chars("ip", "10.0.0.1"));
+        assert_eq!(fmt.fmt_field(&ip), r#""client_ip":"10.0.0.1""#);
+        let other = FieldStorage::from_owned(DataField::from_digit("n", 1));
+        assert_eq!(fmt.fmt_field(&other), r#""n":1"#);
+    }
+}
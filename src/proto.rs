@@ -1,14 +1,162 @@
+use crate::error::FormatError;
+use crate::float::{FloatAction, FloatPolicy};
 #[allow(deprecated)]
 use crate::formatter::DataFormat;
-use crate::formatter::{RecordFormatter, ValueFormatter};
+use crate::formatter::{RecordFormatter, RecordWriter, ValueFormatter, ValueWriter, render};
+use crate::null::NullPolicy;
+use crate::options::FmtOptions;
+use crate::time::TimeFormat;
+use std::fmt;
 use wp_model_core::model::{DataRecord, DataType, FieldStorage, Value, types::value::ObjectValue};
 
-#[derive(Default)]
-pub struct ProtoTxt;
+#[derive(Debug, Clone)]
+pub struct ProtoTxt {
+    opts: FmtOptions,
+}
+
+/// 默认配置的 `ProtoTxt`，与 `ProtoTxt::default()` 相同；保留单元结构体时的写法，如 `FormatType::ProtoText(ProtoTxt)`
+#[allow(non_upper_case_globals)]
+pub const ProtoTxt: ProtoTxt = ProtoTxt {
+    opts: FmtOptions::DEFAULT,
+};
+
+impl Default for ProtoTxt {
+    fn default() -> Self {
+        ProtoTxt
+    }
+}
 
 impl ProtoTxt {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+    /// 严格模式：不支持的值类型返回错误，而不是输出空字符串
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.opts.strict = strict;
+        self
+    }
+    /// 嵌套消息/数组允许的最大层数
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.opts.max_depth = max_depth;
+        self
+    }
+    /// `Value::Time` 的输出方式，默认与 `NaiveDateTime` 的 `Display` 一致
+    pub fn with_time_format(mut self, time: TimeFormat) -> Self {
+        self.opts.time = time;
+        self
+    }
+    /// 浮点数的位数、科学计数法和 NaN/Infinity 处理方式
+    pub fn with_float_policy(mut self, float: FloatPolicy) -> Self {
+        self.opts.float = float;
+        self
+    }
+    /// 空值字段的处理方式：原生空值、省略或占位文本
+    pub fn with_null_policy(mut self, null: NullPolicy) -> Self {
+        self.opts.null = null;
+        self
+    }
+
+    fn write_string(&self, value: &str, out: &mut dyn fmt::Write) -> fmt::Result {
+        out.write_char('"')?;
+        for (i, part) in value.split('"').enumerate() {
+            if i > 0 {
+                out.write_str("\\\"")?;
+            }
+            out.write_str(part)?;
+        }
+        out.write_char('"')
+    }
+
+    fn write_null(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        match self.opts.null.text() {
+            Some(text) => self.write_string(text, out),
+            None => Ok(()),
+        }
+    }
+
+    fn write_value_at(
+        &self,
+        value: &Value,
+        out: &mut dyn fmt::Write,
+        depth: usize,
+    ) -> Result<(), FormatError> {
+        match value {
+            Value::Null => self.write_null(out)?,
+            Value::Bool(v) => write!(out, "{}", v)?,
+            Value::Chars(v) => self.write_string(v, out)?,
+            Value::Digit(v) => write!(out, "{}", v)?,
+            Value::Float(v) => match self.opts.float.action(*v) {
+                FloatAction::Native => write!(out, "{}", v)?,
+                FloatAction::Finite => self.opts.float.write_finite(*v, out)?,
+                FloatAction::Null => self.write_null(out)?,
+                FloatAction::Text(text) => self.write_string(text, out)?,
+                FloatAction::Error => return Err(FormatError::non_finite("proto-text", *v)),
+            },
+            Value::IpAddr(v) => write!(out, "\"{}\"", v)?,
+            Value::Time(v) => self
+                .opts
+                .time
+                .write_quoted(v, out, |s, o| self.write_string(s, o))?,
+            Value::Obj(obj) => self.write_object_at(obj, out, depth + 1)?,
+            Value::Array(arr) => self.write_array_at(arr, out, depth + 1)?,
+            Value::IpNet(_)
+            | Value::Domain(_)
+            | Value::Url(_)
+            | Value::Email(_)
+            | Value::IdCard(_)
+            | Value::MobilePhone(_)
+            | Value::Hex(_)
+            | Value::Symbol(_) => self.write_string(&value.to_string(), out)?,
+            Value::Ignore(_) => {
+                self.opts
+                    .substitute(|| FormatError::unsupported("proto-text", value))?;
+                out.write_str("\"\"")?
+            }
+        }
+        Ok(())
+    }
+
+    fn write_object_at(
+        &self,
+        value: &ObjectValue,
+        out: &mut dyn fmt::Write,
+        depth: usize,
+    ) -> Result<(), FormatError> {
+        self.opts.check_depth(depth)?;
+        for (i, (k, field)) in value
+            .iter()
+            .filter(|(_, f)| !self.opts.omits(f.get_value()))
+            .enumerate()
+        {
+            if i > 0 {
+                out.write_char(' ')?;
+            }
+            write!(out, "{}: ", k)?;
+            self.write_value_at(field.get_value(), out, depth)?;
+        }
+        Ok(())
+    }
+
+    fn write_array_at(
+        &self,
+        value: &[FieldStorage],
+        out: &mut dyn fmt::Write,
+        depth: usize,
+    ) -> Result<(), FormatError> {
+        self.opts.check_depth(depth)?;
+        out.write_char('[')?;
+        for (i, field) in value
+            .iter()
+            .filter(|f| !self.opts.omits(f.get_value()))
+            .enumerate()
+        {
+            if i > 0 {
+                out.write_str(", ")?;
+            }
+            self.write_value_at(field.get_value(), out, depth)?;
+        }
+        out.write_char(']')?;
+        Ok(())
     }
 }
 
@@ -16,67 +164,37 @@ impl ProtoTxt {
 impl DataFormat for ProtoTxt {
     type Output = String;
     fn format_null(&self) -> String {
-        String::new()
+        self.format_value(&Value::Null)
     }
     fn format_bool(&self, v: &bool) -> String {
         v.to_string()
     }
     fn format_string(&self, v: &str) -> String {
-        format!("\"{}\"", v.replace('"', "\\\""))
+        render(|out| Ok(self.write_string(v, out)?)).unwrap_or_default()
     }
     fn format_i64(&self, v: &i64) -> String {
         v.to_string()
     }
     fn format_f64(&self, v: &f64) -> String {
-        v.to_string()
+        self.format_value(&Value::Float(*v))
     }
     fn format_ip(&self, v: &std::net::IpAddr) -> String {
-        self.format_string(&v.to_string())
+        self.format_value(&Value::IpAddr(*v))
     }
     fn format_datetime(&self, v: &chrono::NaiveDateTime) -> String {
-        self.format_string(&v.to_string())
+        self.format_value(&Value::Time(*v))
     }
     fn format_object(&self, value: &ObjectValue) -> String {
-        let items: Vec<String> = value
-            .iter()
-            .map(|(k, v)| format!("{}: {}", k, self.fmt_value(v.get_value())))
-            .collect();
-        items.join(" ")
+        render(|out| self.write_object_at(value, out, 1)).unwrap_or_default()
     }
     fn format_array(&self, value: &[FieldStorage]) -> String {
-        let items: Vec<String> = value
-            .iter()
-            .map(|f| self.fmt_value(f.get_value()))
-            .collect();
-        format!("[{}]", items.join(", "))
+        render(|out| self.write_array_at(value, out, 1)).unwrap_or_default()
     }
     fn format_field(&self, field: &FieldStorage) -> String {
-        if *field.get_meta() == DataType::Ignore {
-            String::new()
-        } else {
-            match field.get_value() {
-                Value::Obj(_) | Value::Array(_) => format!(
-                    "{}: {}",
-                    field.get_name(),
-                    self.fmt_value(field.get_value())
-                ),
-                _ => format!(
-                    "{}: {}",
-                    field.get_name(),
-                    self.fmt_value(field.get_value())
-                ),
-            }
-        }
+        self.fmt_field(field)
     }
     fn format_record(&self, record: &DataRecord) -> String {
-        let items = record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|f| self.format_field(f))
-            .collect::<Vec<_>>();
-        // 生成标准的 proto-text 格式：消息用花括号包围
-        format!("{{ {} }}", items.join(" "))
+        self.fmt_record(record)
     }
 }
 
@@ -290,57 +408,74 @@ mod tests {
 // ============================================================================
 
 #[allow(clippy::items_after_test_module)]
+impl ValueWriter for ProtoTxt {
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        self.write_value_at(value, out, 0)
+    }
+}
+
+impl RecordWriter for ProtoTxt {
+    fn write_field(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        if *field.get_meta() == DataType::Ignore {
+            return Ok(());
+        }
+        write!(out, "{}: ", field.get_name())?;
+        self.write_value(field.get_value(), out)
+    }
+
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        // 生成标准的 proto-text 格式：消息用花括号包围
+        out.write_str("{ ")?;
+        for (i, field) in record
+            .items
+            .iter()
+            .filter(|f| *f.get_meta() != DataType::Ignore && !self.opts.omits(f.get_value()))
+            .enumerate()
+        {
+            if i > 0 {
+                out.write_char(' ')?;
+            }
+            self.write_field(field, out)?;
+        }
+        out.write_str(" }")?;
+        Ok(())
+    }
+}
+
 impl ValueFormatter for ProtoTxt {
     type Output = String;
 
     fn format_value(&self, value: &Value) -> String {
-        match value {
-            Value::Null => String::new(),
-            Value::Bool(v) => v.to_string(),
-            Value::Chars(v) => format!("\"{}\"", v.replace('"', "\\\"")),
-            Value::Digit(v) => v.to_string(),
-            Value::Float(v) => v.to_string(),
-            Value::IpAddr(v) => format!("\"{}\"", v),
-            Value::Time(v) => format!("\"{}\"", v),
-            Value::Obj(obj) => {
-                let items: Vec<String> = obj
-                    .iter()
-                    .map(|(k, field)| format!("{}: {}", k, self.format_value(field.get_value())))
-                    .collect();
-                items.join(" ")
-            }
-            Value::Array(arr) => {
-                let items: Vec<String> = arr
-                    .iter()
-                    .map(|field| self.format_value(field.get_value()))
-                    .collect();
-                format!("[{}]", items.join(", "))
-            }
-            _ => format!("\"{}\"", value.to_string().replace('"', "\\\"")),
-        }
+        self.try_format_value(value).unwrap_or_default()
+    }
+
+    fn try_format_value(&self, value: &Value) -> Result<String, FormatError> {
+        render(|out| self.write_value(value, out))
     }
 }
 
 impl RecordFormatter for ProtoTxt {
     fn fmt_field(&self, field: &FieldStorage) -> String {
-        if *field.get_meta() == DataType::Ignore {
-            String::new()
-        } else {
-            format!(
-                "{}: {}",
-                field.get_name(),
-                self.format_value(field.get_value())
-            )
-        }
+        self.try_fmt_field(field).unwrap_or_default()
     }
 
     fn fmt_record(&self, record: &DataRecord) -> String {
-        let items = record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|f| self.fmt_field(f))
-            .collect::<Vec<_>>();
-        format!("{{ {} }}", items.join(" "))
+        self.try_fmt_record(record).unwrap_or_default()
+    }
+
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<String, FormatError> {
+        render(|out| self.write_field(field, out))
+    }
+
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<String, FormatError> {
+        render(|out| self.write_record_fmt(record, out))
     }
 }
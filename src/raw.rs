@@ -1,15 +1,150 @@
+use crate::error::FormatError;
+use crate::float::{FloatAction, FloatPolicy};
 #[allow(deprecated)]
 use crate::formatter::DataFormat;
-use crate::formatter::{RecordFormatter, ValueFormatter};
+use crate::formatter::{RecordFormatter, RecordWriter, ValueFormatter, ValueWriter, render};
+use crate::null::NullPolicy;
+use crate::options::FmtOptions;
+use crate::time::TimeFormat;
+use std::fmt;
 use wp_model_core::model::types::value::ObjectValue;
 use wp_model_core::model::{DataRecord, DataType, FieldStorage, Value};
 
-#[derive(Debug, Default)]
-pub struct Raw;
+#[derive(Debug, Clone)]
+pub struct Raw {
+    opts: FmtOptions,
+}
+
+/// 默认配置的 `Raw`，与 `Raw::default()` 相同；保留单元结构体时的写法，如 `FormatType::Raw(Raw)`
+#[allow(non_upper_case_globals)]
+pub const Raw: Raw = Raw {
+    opts: FmtOptions::DEFAULT,
+};
+
+impl Default for Raw {
+    fn default() -> Self {
+        Raw
+    }
+}
 
 impl Raw {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+    /// 严格模式：不支持的值类型返回错误，而不是输出空文本
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.opts.strict = strict;
+        self
+    }
+    /// 嵌套对象/数组允许的最大层数
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.opts.max_depth = max_depth;
+        self
+    }
+    /// `Value::Time` 的输出方式，默认与 `NaiveDateTime` 的 `Display` 一致
+    pub fn with_time_format(mut self, time: TimeFormat) -> Self {
+        self.opts.time = time;
+        self
+    }
+    /// 浮点数的位数、科学计数法和 NaN/Infinity 处理方式
+    pub fn with_float_policy(mut self, float: FloatPolicy) -> Self {
+        self.opts.float = float;
+        self
+    }
+    /// 空值字段的处理方式：原生空值、省略或占位文本
+    pub fn with_null_policy(mut self, null: NullPolicy) -> Self {
+        self.opts.null = null;
+        self
+    }
+
+    fn write_null(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        match self.opts.null.text() {
+            Some(text) => out.write_str(text),
+            None => Ok(()),
+        }
+    }
+
+    fn write_value_at(
+        &self,
+        value: &Value,
+        out: &mut dyn fmt::Write,
+        depth: usize,
+    ) -> Result<(), FormatError> {
+        match value {
+            Value::Null => self.write_null(out)?,
+            Value::Chars(v) => out.write_str(v)?,
+            Value::Time(v) => self.opts.time.write(v, out)?,
+            Value::Float(v) => match self.opts.float.action(*v) {
+                FloatAction::Native => write!(out, "{}", v)?,
+                FloatAction::Finite => self.opts.float.write_finite(*v, out)?,
+                FloatAction::Null => self.write_null(out)?,
+                FloatAction::Text(text) => out.write_str(text)?,
+                FloatAction::Error => return Err(FormatError::non_finite("raw", *v)),
+            },
+            Value::Obj(obj) => self.write_object_at(obj, out, depth + 1)?,
+            Value::Array(arr) => self.write_array_at(arr, out, depth + 1)?,
+            Value::Bool(_)
+            | Value::Digit(_)
+            | Value::IpNet(_)
+            | Value::IpAddr(_)
+            | Value::Domain(_)
+            | Value::Url(_)
+            | Value::Email(_)
+            | Value::IdCard(_)
+            | Value::MobilePhone(_)
+            | Value::Hex(_)
+            | Value::Symbol(_) => write!(out, "{}", value)?,
+            Value::Ignore(_) => {
+                self.opts
+                    .substitute(|| FormatError::unsupported("raw", value))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_object_at(
+        &self,
+        value: &ObjectValue,
+        out: &mut dyn fmt::Write,
+        depth: usize,
+    ) -> Result<(), FormatError> {
+        self.opts.check_depth(depth)?;
+        out.write_char('{')?;
+        for (i, (k, field)) in value
+            .iter()
+            .filter(|(_, f)| !self.opts.omits(f.get_value()))
+            .enumerate()
+        {
+            if i > 0 {
+                out.write_str(", ")?;
+            }
+            write!(out, "{}=", k)?;
+            self.write_value_at(field.get_value(), out, depth)?;
+        }
+        out.write_char('}')?;
+        Ok(())
+    }
+
+    fn write_array_at(
+        &self,
+        value: &[FieldStorage],
+        out: &mut dyn fmt::Write,
+        depth: usize,
+    ) -> Result<(), FormatError> {
+        self.opts.check_depth(depth)?;
+        out.write_char('[')?;
+        for (i, field) in value
+            .iter()
+            .filter(|f| !self.opts.omits(f.get_value()))
+            .enumerate()
+        {
+            if i > 0 {
+                out.write_str(", ")?;
+            }
+            self.write_value_at(field.get_value(), out, depth)?;
+        }
+        out.write_char(']')?;
+        Ok(())
     }
 }
 
@@ -17,7 +152,7 @@ impl Raw {
 impl DataFormat for Raw {
     type Output = String;
     fn format_null(&self) -> String {
-        String::new()
+        self.format_value(&Value::Null)
     }
     fn format_bool(&self, v: &bool) -> String {
         v.to_string()
@@ -29,48 +164,25 @@ impl DataFormat for Raw {
         v.to_string()
     }
     fn format_f64(&self, v: &f64) -> String {
-        v.to_string()
+        self.format_value(&Value::Float(*v))
     }
     fn format_ip(&self, v: &std::net::IpAddr) -> String {
         v.to_string()
     }
     fn format_datetime(&self, v: &chrono::NaiveDateTime) -> String {
-        v.to_string()
+        self.format_value(&Value::Time(*v))
     }
     fn format_object(&self, value: &ObjectValue) -> String {
-        if value.is_empty() {
-            return "{}".to_string();
-        }
-        let segments: Vec<String> = value
-            .iter()
-            .map(|(k, v)| format!("{}={}", k, self.fmt_value(v.get_value())))
-            .collect();
-        format!("{{{}}}", segments.join(", "))
+        render(|out| self.write_object_at(value, out, 1)).unwrap_or_default()
     }
     fn format_array(&self, value: &[FieldStorage]) -> String {
-        if value.is_empty() {
-            return "[]".to_string();
-        }
-        let content: Vec<String> = value
-            .iter()
-            .map(|field| self.fmt_value(field.get_value()))
-            .collect();
-        format!("[{}]", content.join(", "))
+        render(|out| self.write_array_at(value, out, 1)).unwrap_or_default()
     }
     fn format_field(&self, field: &FieldStorage) -> String {
-        match field.get_value() {
-            Value::Chars(s) => s.to_string(),
-            _ => self.fmt_value(field.get_value()),
-        }
+        self.fmt_field(field)
     }
     fn format_record(&self, record: &DataRecord) -> String {
-        record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|f| self.format_field(f))
-            .collect::<Vec<_>>()
-            .join(" ")
+        self.fmt_record(record)
     }
 }
 
@@ -263,60 +375,59 @@ mod tests {
 // ============================================================================
 
 #[allow(clippy::items_after_test_module)]
+impl ValueWriter for Raw {
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        self.write_value_at(value, out, 0)
+    }
+}
+
+impl RecordWriter for Raw {
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        for (i, field) in record
+            .items
+            .iter()
+            .filter(|f| *f.get_meta() != DataType::Ignore && !self.opts.omits(f.get_value()))
+            .enumerate()
+        {
+            if i > 0 {
+                out.write_char(' ')?;
+            }
+            self.write_field(field, out)?;
+        }
+        Ok(())
+    }
+}
+
 impl ValueFormatter for Raw {
     type Output = String;
 
     fn format_value(&self, value: &Value) -> String {
-        match value {
-            Value::Null => String::new(),
-            Value::Bool(v) => v.to_string(),
-            Value::Chars(v) => v.to_string(),
-            Value::Digit(v) => v.to_string(),
-            Value::Float(v) => v.to_string(),
-            Value::IpAddr(v) => v.to_string(),
-            Value::Time(v) => v.to_string(),
-            Value::Obj(obj) => {
-                if obj.is_empty() {
-                    "{}".to_string()
-                } else {
-                    let segments: Vec<String> = obj
-                        .iter()
-                        .map(|(k, field)| format!("{}={}", k, self.format_value(field.get_value())))
-                        .collect();
-                    format!("{{{}}}", segments.join(", "))
-                }
-            }
-            Value::Array(arr) => {
-                if arr.is_empty() {
-                    "[]".to_string()
-                } else {
-                    let content: Vec<String> = arr
-                        .iter()
-                        .map(|field| self.format_value(field.get_value()))
-                        .collect();
-                    format!("[{}]", content.join(", "))
-                }
-            }
-            _ => value.to_string(),
-        }
+        self.try_format_value(value).unwrap_or_default()
+    }
+
+    fn try_format_value(&self, value: &Value) -> Result<String, FormatError> {
+        render(|out| self.write_value(value, out))
     }
 }
 
 impl RecordFormatter for Raw {
     fn fmt_field(&self, field: &FieldStorage) -> String {
-        match field.get_value() {
-            Value::Chars(s) => s.to_string(),
-            _ => self.format_value(field.get_value()),
-        }
+        self.try_fmt_field(field).unwrap_or_default()
     }
 
     fn fmt_record(&self, record: &DataRecord) -> String {
-        record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|f| self.fmt_field(f))
-            .collect::<Vec<_>>()
-            .join(" ")
+        self.try_fmt_record(record).unwrap_or_default()
+    }
+
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<String, FormatError> {
+        render(|out| self.write_field(field, out))
+    }
+
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<String, FormatError> {
+        render(|out| self.write_record_fmt(record, out))
     }
 }
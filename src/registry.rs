@@ -0,0 +1,297 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::error::ConfigError;
+use crate::{
+    Csv, FormatType, FormatterConfig, Json, KeyValue, ProtoTxt, Raw, RecordFormatter, SqlDialect,
+};
+
+/// 可在线程间共享的动态格式化器
+pub type BoxedFormatter = Box<dyn RecordFormatter<Output = String> + Send + Sync>;
+
+enum Factory {
+    /// 内置格式，`format_type` 返回对应的具体变体
+    Builtin(fn() -> FormatType),
+    /// 由配置构建的内置格式，每次创建时校验
+    Config(Box<FormatterConfig>),
+    Custom(Box<dyn Fn() -> BoxedFormatter + Send + Sync>),
+}
+
+/// 按名称注册格式化器工厂
+///
+/// `new()` 会预先注册内置格式（名称与 `TextFmt` 的文本形式一致）：
+/// `json`、`csv`、`kv`、`raw`、`show`、`proto`、`proto-text` 和 `sql`。
+/// `sql` 需要表名：未配置时 `try_format_type("sql")` 返回 `ConfigError`，
+/// 可用 `register_config` 以带表名的 `FormatterConfig::Sql` 覆盖。
+/// 使用同名注册即可覆盖内置实现。
+///
+/// ```
+/// use wp_data_fmt::{FormatType, FormatterConfig, FormatterRegistry, Json, RecordFormatter};
+///
+/// let mut registry = FormatterRegistry::new();
+/// registry.register("json-strict", || Box::new(Json::default().with_strict(true)));
+/// let fmt = registry.format_type("json-strict").unwrap();
+///
+/// assert!(registry.try_format_type("sql").is_err());
+/// let sql: FormatterConfig = serde_json::from_str(r#"{"kind":"sql","table":"nginx"}"#).unwrap();
+/// registry.register_config("sql", sql).unwrap();
+/// assert!(matches!(registry.format_type("sql"), Some(FormatType::Sql(_))));
+/// ```
+pub struct FormatterRegistry {
+    factories: BTreeMap<String, Factory>,
+}
+
+impl Default for FormatterRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for FormatterRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.factories.keys()).finish()
+    }
+}
+
+impl FormatterRegistry {
+    /// 创建注册表，并注册全部内置格式
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        registry
+            .builtin("json", || FormatType::Json(Json::default()))
+            .builtin("csv", || FormatType::Csv(Csv::default()))
+            .builtin("kv", || FormatType::Kv(KeyValue::default()))
+            .builtin("raw", || FormatType::Raw(Raw::default()))
+            .builtin("show", || FormatType::Raw(Raw::default()))
+            .builtin("proto", || FormatType::ProtoText(ProtoTxt::default()))
+            .builtin("proto-text", || FormatType::ProtoText(ProtoTxt::default()));
+        registry.factories.insert(
+            "sql".to_string(),
+            Factory::Config(Box::new(FormatterConfig::Sql {
+                table: String::new(),
+                dialect: SqlDialect::default(),
+                quote_identifiers: None,
+                object_format: None,
+                strict: false,
+                time: None,
+                float: None,
+                null: None,
+                flatten: None,
+            })),
+        );
+        registry
+    }
+
+    /// 创建不含任何格式的空注册表
+    pub fn empty() -> Self {
+        Self {
+            factories: BTreeMap::new(),
+        }
+    }
+
+    /// 注册格式化器工厂，同名的已有注册会被替换
+    pub fn register<F>(&mut self, name: impl Into<String>, factory: F) -> &mut Self
+    where
+        F: Fn() -> BoxedFormatter + Send + Sync + 'static,
+    {
+        self.factories
+            .insert(name.into(), Factory::Custom(Box::new(factory)));
+        self
+    }
+
+    /// 以配置注册内置格式，同名的已有注册会被替换；配置不合法时返回错误且不注册
+    pub fn register_config(
+        &mut self,
+        name: impl Into<String>,
+        config: FormatterConfig,
+    ) -> Result<&mut Self, ConfigError> {
+        config.build()?;
+        self.factories
+            .insert(name.into(), Factory::Config(Box::new(config)));
+        Ok(self)
+    }
+
+    fn builtin(&mut self, name: &str, factory: fn() -> FormatType) -> &mut Self {
+        self.factories
+            .insert(name.to_string(), Factory::Builtin(factory));
+        self
+    }
+
+    /// 移除注册，返回该名称之前是否存在
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.factories.remove(name).is_some()
+    }
+
+    /// 是否已注册该名称
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    /// 已注册的格式名称，按字典序排列
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(String::as_str)
+    }
+
+    /// 按名称创建格式化器，未注册或缺少必需配置（如 `sql` 的表名）时返回 `None`
+    pub fn create(&self, name: &str) -> Option<BoxedFormatter> {
+        self.try_create(name).ok().flatten()
+    }
+
+    /// 按名称创建格式化器，未注册时返回 `Ok(None)`，配置不完整时返回错误
+    pub fn try_create(&self, name: &str) -> Result<Option<BoxedFormatter>, ConfigError> {
+        Ok(self.try_format_type(name)?.map(|fmt| match fmt {
+            FormatType::Custom(formatter) => formatter,
+            builtin => Box::new(builtin),
+        }))
+    }
+
+    /// 按名称创建 `FormatType`，可用于任何接受 `FormatType` 的位置；
+    /// 内置格式返回对应的变体（如 `FormatType::Json`），其余返回 `FormatType::Custom`。
+    /// 未注册或缺少必需配置时返回 `None`
+    pub fn format_type(&self, name: &str) -> Option<FormatType> {
+        self.try_format_type(name).ok().flatten()
+    }
+
+    /// 同 `format_type`，配置不完整时返回错误，未注册时返回 `Ok(None)`
+    pub fn try_format_type(&self, name: &str) -> Result<Option<FormatType>, ConfigError> {
+        let Some(factory) = self.factories.get(name) else {
+            return Ok(None);
+        };
+        Ok(Some(match factory {
+            Factory::Builtin(factory) => factory(),
+            Factory::Config(config) => config.build()?,
+            Factory::Custom(factory) => FormatType::Custom(factory()),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ValueFormatter;
+    use wp_model_core::model::{DataField, DataRecord, FieldStorage, Value};
+
+    struct Upper;
+
+    impl ValueFormatter for Upper {
+        type Output = String;
+        fn format_value(&self, value: &Value) -> String {
+            value.to_string().to_uppercase()
+        }
+    }
+
+    impl RecordFormatter for Upper {
+        fn fmt_record(&self, record: &DataRecord) -> String {
+            record
+                .items
+                .iter()
+                .map(|f| self.fmt_field(f))
+                .collect::<Vec<_>>()
+                .join("|")
+        }
+    }
+
+    fn make_record() -> DataRecord {
+        DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_chars("name", "alice")),
+                FieldStorage::from_owned(DataField::from_digit("age", 30)),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_builtins_registered() {
+        let registry = FormatterRegistry::new();
+        let names: Vec<&str> = registry.names().collect();
+        assert_eq!(
+            names,
+            [
+                "csv",
+                "json",
+                "kv",
+                "proto",
+                "proto-text",
+                "raw",
+                "show",
+                "sql"
+            ]
+        );
+        let json = registry.create("json").unwrap();
+        assert_eq!(
+            json.fmt_record(&make_record()),
+            r#"{"name":"alice","age":30}"#
+        );
+        assert!(registry.create("xml").is_none());
+        // sql 已预注册，但需要配置表名
+        assert!(registry.contains("sql"));
+        assert!(registry.create("sql").is_none());
+        assert!(matches!(
+            registry.try_format_type("sql"),
+            Err(ConfigError::InvalidOption {
+                option: "table",
+                ..
+            })
+        ));
+        assert!(matches!(registry.try_format_type("xml"), Ok(None)));
+
+        assert!(matches!(
+            registry.format_type("json"),
+            Some(FormatType::Json(_))
+        ));
+        assert!(matches!(
+            registry.format_type("show"),
+            Some(FormatType::Raw(_))
+        ));
+    }
+
+    #[test]
+    fn test_register_sql_config() {
+        let mut registry = FormatterRegistry::new();
+        let config: FormatterConfig =
+            serde_json::from_str(r#"{"kind":"sql","table":"users","dialect":"mysql"}"#).unwrap();
+        registry.register_config("sql", config).unwrap();
+        let fmt = registry.format_type("sql").unwrap();
+        assert!(matches!(fmt, FormatType::Sql(_)));
+        assert_eq!(
+            fmt.fmt_record(&make_record()),
+            "INSERT INTO `users` (`name`, `age`) VALUES ('alice', 30);"
+        );
+        assert_eq!(
+            registry.create("sql").unwrap().fmt_record(&make_record()),
+            fmt.fmt_record(&make_record())
+        );
+
+        let bad: FormatterConfig = serde_json::from_str(r#"{"kind":"sql","table":" "}"#).unwrap();
+        assert!(registry.register_config("bad", bad).is_err());
+        assert!(!registry.contains("bad"));
+    }
+
+    #[test]
+    fn test_register_custom() {
+        let mut registry = FormatterRegistry::empty();
+        assert!(!registry.contains("upper"));
+        registry.register("upper", || Box::new(Upper));
+        assert!(registry.contains("upper"));
+
+        let fmt = registry.format_type("upper").unwrap();
+        assert!(matches!(fmt, FormatType::Custom(_)));
+        assert_eq!(fmt.fmt_record(&make_record()), "ALICE|30");
+
+        assert!(registry.unregister("upper"));
+        assert!(!registry.unregister("upper"));
+    }
+
+    #[test]
+    fn test_register_overrides_builtin() {
+        let mut registry = FormatterRegistry::new();
+        registry.register("json", || Box::new(Upper));
+        let fmt = registry.create("json").unwrap();
+        assert_eq!(fmt.format_value(&Value::Chars("x".into())), "X");
+        assert!(matches!(
+            registry.format_type("json"),
+            Some(FormatType::Custom(_))
+        ));
+    }
+}
@@ -0,0 +1,409 @@
+use std::borrow::Cow;
+
+use serde::Serialize;
+use serde::ser::{Error as _, SerializeMap, SerializeSeq, Serializer};
+use wp_model_core::model::{DataRecord, DataType, FieldStorage, Value, types::value::ObjectValue};
+
+use crate::Json;
+use crate::error::FormatError;
+use crate::float::{FloatAction, non_finite_name};
+use crate::unflatten::Node;
+
+/// 以 `Json` 的语义把 `DataRecord` 交给任意 serde 后端（YAML、CBOR、bincode 等）
+///
+/// Ignore 字段被过滤，严格模式、嵌套层数、时间、浮点数、空值、键名和字段名还原选项
+/// 取自 `with_format` 指定的 `Json`，默认与 `Json::default()` 一致。
+/// `Json::pretty`、`with_type_style` 和 `with_canonical` 只作用于文本输出，这里都不使用。
+///
+/// ```
+/// use wp_data_fmt::{Json, SerializeRecord};
+/// # let record = wp_model_core::model::DataRecord { id: Default::default(), items: vec![] };
+///
+/// let json = Json::default().with_strict(true);
+/// let value = serde_json::to_value(SerializeRecord::new(&record).with_format(&json)).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct SerializeRecord<'a> {
+    record: &'a DataRecord,
+    json: Cow<'a, Json>,
+}
+
+/// 以 `Json` 的语义序列化单个 `Value`，选项同 `SerializeRecord`
+#[derive(Debug, Clone)]
+pub struct SerializeValue<'a> {
+    value: &'a Value,
+    json: Cow<'a, Json>,
+}
+
+impl<'a> SerializeRecord<'a> {
+    pub fn new(record: &'a DataRecord) -> Self {
+        Self {
+            record,
+            json: Cow::Owned(Json::default()),
+        }
+    }
+
+    /// 使用指定 `Json` 的选项
+    pub fn with_format(mut self, json: &'a Json) -> Self {
+        self.json = Cow::Borrowed(json);
+        self
+    }
+}
+
+impl<'a> SerializeValue<'a> {
+    pub fn new(value: &'a Value) -> Self {
+        Self {
+            value,
+            json: Cow::Owned(Json::default()),
+        }
+    }
+
+    /// 使用指定 `Json` 的选项
+    pub fn with_format(mut self, json: &'a Json) -> Self {
+        self.json = Cow::Borrowed(json);
+        self
+    }
+}
+
+impl Serialize for SerializeRecord<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let json = self.json.as_ref();
+        if let Some(unflatten) = json.unflatten() {
+            let nodes = unflatten
+                .build(self.record, |v| json.opts().omits(v))
+                .map_err(S::Error::custom)?;
+            return Nodes {
+                nodes: &nodes,
+                json,
+                depth: 0,
+                scoped: json.integers().root_scope(),
+            }
+            .serialize(serializer);
+        }
+        let fields: Vec<&FieldStorage> = self
+            .record
+            .items
+            .iter()
+            .filter(|f| *f.get_meta() != DataType::Ignore && !json.opts().omits(f.get_value()))
+            .collect();
+        let mut map = serializer.serialize_map(Some(fields.len()))?;
+        for field in fields {
+            let key = json
+                .checked_key(field.get_name())
+                .map_err(S::Error::custom)?;
+            let scoped = json
+                .integers()
+                .enter(field.get_name(), json.integers().root_scope());
+            map.serialize_entry(key.as_ref(), &Ser::new(field.get_value(), json, 0, scoped))?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for SerializeValue<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let json = self.json.as_ref();
+        Ser::new(self.value, json, 0, json.integers().root_scope()).serialize(serializer)
+    }
+}
+
+/// 递归序列化的值，`depth` 与 `Json` 写出时的层数一致，
+/// `scoped` 表示是否处于 `IntegerPolicy` 的作用范围内
+struct Ser<'a> {
+    value: &'a Value,
+    json: &'a Json,
+    depth: usize,
+    scoped: bool,
+}
+
+impl<'a> Ser<'a> {
+    fn new(value: &'a Value, json: &'a Json, depth: usize, scoped: bool) -> Self {
+        Self {
+            value,
+            json,
+            depth,
+            scoped,
+        }
+    }
+
+    fn serialize_null<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.json.opts().null.text() {
+            Some(text) => serializer.serialize_str(text),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    fn serialize_f64<S: Serializer>(&self, value: f64, serializer: S) -> Result<S::Ok, S::Error> {
+        let opts = self.json.opts();
+        match opts.float.action(value) {
+            FloatAction::Native if value.is_finite() => serializer.serialize_f64(value),
+            FloatAction::Native => {
+                opts.substitute(|| FormatError::non_finite("json", value))
+                    .map_err(S::Error::custom)?;
+                if value.is_nan() {
+                    serializer.serialize_none()
+                } else {
+                    serializer.serialize_str(non_finite_name(value))
+                }
+            }
+            FloatAction::Finite => {
+                // 按策略舍入后的文本再转回数值
+                let mut text = String::new();
+                opts.float
+                    .write_finite(value, &mut text)
+                    .map_err(S::Error::custom)?;
+                serializer.serialize_f64(text.parse().unwrap_or(value))
+            }
+            FloatAction::Null => self.serialize_null(serializer),
+            FloatAction::Text(text) => serializer.serialize_str(text),
+            FloatAction::Error => Err(S::Error::custom(FormatError::non_finite("json", value))),
+        }
+    }
+
+    fn serialize_object<S: Serializer>(
+        &self,
+        value: &ObjectValue,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let opts = self.json.opts();
+        let depth = self.depth + 1;
+        opts.check_depth(depth).map_err(S::Error::custom)?;
+        let fields: Vec<_> = value
+            .iter()
+            .filter(|(_, f)| !opts.omits(f.get_value()))
+            .collect();
+        let mut map = serializer.serialize_map(Some(fields.len()))?;
+        for (k, field) in fields {
+            let key = self.json.checked_key(k).map_err(S::Error::custom)?;
+            let scoped = self.json.integers().enter(k, self.scoped);
+            map.serialize_entry(
+                key.as_ref(),
+                &Ser::new(field.get_value(), self.json, depth, scoped),
+            )?;
+        }
+        map.end()
+    }
+
+    fn serialize_array<S: Serializer>(
+        &self,
+        value: &[FieldStorage],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let opts = self.json.opts();
+        let depth = self.depth + 1;
+        opts.check_depth(depth).map_err(S::Error::custom)?;
+        let items: Vec<_> = value
+            .iter()
+            .filter(|f| !opts.omits(f.get_value()))
+            .collect();
+        let mut seq = serializer.serialize_seq(Some(items.len()))?;
+        for field in items {
+            seq.serialize_element(&Ser::new(field.get_value(), self.json, depth, self.scoped))?;
+        }
+        seq.end()
+    }
+}
+
+impl Serialize for Ser<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let opts = self.json.opts();
+        match self.value {
+            Value::Null => self.serialize_null(serializer),
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::Chars(v) => serializer.serialize_str(v),
+            Value::Digit(v) if self.json.integers().quotes(*v, self.scoped) => {
+                serializer.collect_str(v)
+            }
+            Value::Digit(v) => serializer.serialize_i64(*v),
+            Value::Float(v) => self.serialize_f64(*v, serializer),
+            Value::Time(v) => {
+                let text = opts.time.try_format(v).map_err(S::Error::custom)?;
+                if !opts.time.is_numeric() {
+                    serializer.serialize_str(&text)
+                } else if let Ok(n) = text.parse::<i64>() {
+                    serializer.serialize_i64(n)
+                } else {
+                    // epoch 纳秒可能超出 i64
+                    serializer.serialize_i128(text.parse().map_err(S::Error::custom)?)
+                }
+            }
+            Value::Obj(v) => self.serialize_object(v, serializer),
+            Value::Array(v) => self.serialize_array(v, serializer),
+            Value::IpAddr(_)
+            | Value::IpNet(_)
+            | Value::Domain(_)
+            | Value::Url(_)
+            | Value::Email(_)
+            | Value::IdCard(_)
+            | Value::MobilePhone(_)
+            | Value::Hex(_)
+            | Value::Symbol(_) => serializer.collect_str(self.value),
+            Value::Ignore(_) => {
+                opts.substitute(|| FormatError::unsupported("json", self.value))
+                    .map_err(S::Error::custom)?;
+                serializer.serialize_str("")
+            }
+        }
+    }
+}
+
+/// 字段名还原后的对象树
+struct Nodes<'n, 'a> {
+    nodes: &'n [(String, Node<'a>)],
+    json: &'a Json,
+    depth: usize,
+    scoped: bool,
+}
+
+impl Serialize for Nodes<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.nodes.len()))?;
+        for (k, node) in self.nodes {
+            let key = self.json.checked_key(k).map_err(S::Error::custom)?;
+            let scoped = self.json.integers().enter(k, self.scoped);
+            match node {
+                Node::Leaf(value) => map.serialize_entry(
+                    key.as_ref(),
+                    &Ser::new(value, self.json, self.depth, scoped),
+                )?,
+                Node::Branch(children) => {
+                    let depth = self.depth + 1;
+                    self.json
+                        .opts()
+                        .check_depth(depth)
+                        .map_err(S::Error::custom)?;
+                    let children = Nodes {
+                        nodes: children,
+                        json: self.json,
+                        depth,
+                        scoped,
+                    };
+                    map.serialize_entry(key.as_ref(), &children)?
+                }
+            }
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        FloatPolicy, IntegerPolicy, NonFinite, NullPolicy, RecordFormatter, TimeFormat, Unflatten,
+    };
+    use serde_json::Value as JsonValue;
+    use wp_model_core::model::DataField;
+
+    fn make_record() -> DataRecord {
+        let mut obj = ObjectValue::new();
+        obj.insert(
+            "inf".to_string(),
+            FieldStorage::from_owned(DataField::from_float("inf", f64::INFINITY)),
+        );
+        obj.insert(
+            "nan".to_string(),
+            FieldStorage::from_owned(DataField::from_float("nan", f64::NAN)),
+        );
+        DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_chars("name", "a\"b")),
+                FieldStorage::from_owned(DataField::from_digit("n", 7)),
+                FieldStorage::from_owned(DataField::from_float("f", 1.5)),
+                FieldStorage::from_owned(DataField::new(DataType::default(), "none", Value::Null)),
+                FieldStorage::from_owned(DataField::from_obj("obj", obj)),
+                FieldStorage::from_owned(DataField::from_arr(
+                    "arr",
+                    vec![DataField::from_digit("", 1), DataField::from_bool("", true)],
+                )),
+                FieldStorage::from_owned(DataField::new(DataType::Ignore, "skip", Value::Digit(0))),
+            ],
+        }
+    }
+
+    /// 序列化结果应与 `Json` 输出解析后的结构一致
+    fn assert_same(json: &Json, record: &DataRecord) {
+        let expected: JsonValue = serde_json::from_str(&json.fmt_record(record)).unwrap();
+        let actual = serde_json::to_value(SerializeRecord::new(record).with_format(json)).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_same_as_json() {
+        let record = make_record();
+        assert_same(&Json::default(), &record);
+        assert_same(
+            &Json::default()
+                .with_null_policy(NullPolicy::Omit)
+                .with_float_policy(FloatPolicy::new().with_non_finite(NonFinite::String)),
+            &record,
+        );
+        assert_same(
+            &Json::default().with_null_policy(NullPolicy::placeholder("-")),
+            &record,
+        );
+        assert_same(&Json::default().with_unflatten(Unflatten::new()), &record);
+    }
+
+    #[test]
+    fn test_ignore_filtered() {
+        let value = serde_json::to_value(SerializeRecord::new(&make_record())).unwrap();
+        assert!(value.get("skip").is_none());
+        assert_eq!(value["obj"]["inf"], "Infinity");
+        assert!(value["obj"]["nan"].is_null());
+    }
+
+    #[test]
+    fn test_strict_errors() {
+        let json = Json::default().with_strict(true);
+        let record = make_record();
+        let err =
+            serde_json::to_string(&SerializeRecord::new(&record).with_format(&json)).unwrap_err();
+        assert!(err.to_string().contains("non-finite"));
+
+        let deep = Json::default().with_max_depth(0);
+        let value = Value::Array(vec![]);
+        assert!(serde_json::to_string(&SerializeValue::new(&value).with_format(&deep)).is_err());
+    }
+
+    #[test]
+    fn test_value_and_time() {
+        let t = chrono::NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S")
+            .unwrap();
+        let value = Value::Time(t);
+        assert_eq!(
+            serde_json::to_string(&SerializeValue::new(&value)).unwrap(),
+            r#""2019-08-06 12:12:19""#
+        );
+        let json = Json::default().with_time_format(TimeFormat::epoch_seconds());
+        assert_eq!(
+            serde_json::to_string(&SerializeValue::new(&value).with_format(&json)).unwrap(),
+            "1565093539"
+        );
+        let json = Json::default().with_float_policy(FloatPolicy::new().with_precision(1));
+        assert_eq!(
+            serde_json::to_string(&SerializeValue::new(&Value::Float(2.25)).with_format(&json))
+                .unwrap(),
+            "2.2"
+        );
+    }
+
+    #[test]
+    fn test_integer_policy() {
+        let json = Json::default().with_integer_policy(IntegerPolicy::unsafe_as_string());
+        let value = Value::Array(vec![
+            FieldStorage::from_owned(DataField::from_digit("", 1)),
+            FieldStorage::from_owned(DataField::from_digit("", i64::MIN)),
+        ]);
+        assert_eq!(
+            serde_json::to_string(&SerializeValue::new(&value).with_format(&json)).unwrap(),
+            r#"[1,"-9223372036854775808"]"#
+        );
+        let record = make_record();
+        assert_same(
+            &Json::default().with_integer_policy(IntegerPolicy::all_as_string().with_fields(["n"])),
+            &record,
+        );
+    }
+}
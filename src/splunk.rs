@@ -0,0 +1,261 @@
+use std::fmt;
+
+use wp_model_core::model::{DataRecord, FieldStorage, Value};
+
+use crate::Json;
+use crate::error::FormatError;
+use crate::formatter::{RecordFormatter, RecordWriter, ValueFormatter, ValueWriter, render};
+use crate::json::write_json_str;
+use crate::meta::{MetaValue, without_fields};
+use crate::projected::lookup;
+
+/// Splunk HTTP Event Collector 事件信封
+///
+/// 每条记录输出为
+/// `{"time":..,"host":..,"source":..,"sourcetype":..,"index":..,"event":{..}}`，
+/// `event` 由 `Json` 的紧凑输出生成。未设置或取不到值的元数据不输出，由 HEC 使用默认值。
+///
+/// `time` 取自 `with_time_field` 指定的 `Value::Time` 字段，输出为带小数的 epoch 秒，
+/// 如 `1565093539.25`；时区取 `Json` 的 `TimeFormat::with_offset`，未指定时视为 UTC。
+/// `with_remove_fields(true)` 会从 `event` 中去掉提升为元数据的顶层字段。
+///
+/// ```
+/// use wp_data_fmt::{MetaValue, SplunkHec};
+///
+/// let hec = SplunkHec::new()
+///     .with_time_field("timestamp")
+///     .with_host(MetaValue::field("hostname"))
+///     .with_sourcetype(MetaValue::text("nginx:access"))
+///     .with_index(MetaValue::text("web"))
+///     .with_remove_fields(true);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SplunkHec {
+    json: Json,
+    time_field: Option<String>,
+    host: Option<MetaValue>,
+    source: Option<MetaValue>,
+    sourcetype: Option<MetaValue>,
+    index: Option<MetaValue>,
+    remove_fields: bool,
+}
+
+impl SplunkHec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `event` 的 JSON 选项
+    pub fn with_json(mut self, json: Json) -> Self {
+        self.json = json;
+        self
+    }
+
+    pub fn with_time_field(mut self, field: impl Into<String>) -> Self {
+        self.time_field = Some(field.into());
+        self
+    }
+
+    pub fn with_host(mut self, host: MetaValue) -> Self {
+        self.host = Some(host);
+        self
+    }
+
+    pub fn with_source(mut self, source: MetaValue) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn with_sourcetype(mut self, sourcetype: MetaValue) -> Self {
+        self.sourcetype = Some(sourcetype);
+        self
+    }
+
+    pub fn with_index(mut self, index: MetaValue) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// 从 `event` 中去掉时间字段和作为元数据来源的字段
+    pub fn with_remove_fields(mut self, remove_fields: bool) -> Self {
+        self.remove_fields = remove_fields;
+        self
+    }
+
+    fn metadata(&self) -> [(&'static str, Option<&MetaValue>); 4] {
+        [
+            ("host", self.host.as_ref()),
+            ("source", self.source.as_ref()),
+            ("sourcetype", self.sourcetype.as_ref()),
+            ("index", self.index.as_ref()),
+        ]
+    }
+
+    fn write_time(&self, record: &DataRecord, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        let Some(name) = &self.time_field else {
+            return Ok(());
+        };
+        match lookup(record, name).map(|f| f.get_value()) {
+            None | Some(Value::Null | Value::Ignore(_)) => {}
+            Some(Value::Time(t)) => {
+                out.write_str("\"time\":")?;
+                self.json.opts().time.write_epoch_fraction(t, out)?;
+                out.write_char(',')?;
+            }
+            Some(value) => return Err(FormatError::unsupported("splunk_hec", value)),
+        }
+        Ok(())
+    }
+}
+
+impl ValueWriter for SplunkHec {
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        self.json.write_value(value, out)
+    }
+}
+
+impl RecordWriter for SplunkHec {
+    fn write_field(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        self.json.write_field(field, out)
+    }
+
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        out.write_char('{')?;
+        self.write_time(record, out)?;
+        for (key, meta) in self.metadata() {
+            if let Some(text) = meta.and_then(|m| m.resolve(record)) {
+                write!(out, "\"{}\":", key)?;
+                write_json_str(&text, out)?;
+                out.write_char(',')?;
+            }
+        }
+        out.write_str("\"event\":")?;
+        if self.remove_fields {
+            let names: Vec<&str> = self
+                .metadata()
+                .into_iter()
+                .filter_map(|(_, meta)| meta?.field_name())
+                .chain(self.time_field.as_deref())
+                .collect();
+            let body = without_fields(record, &names);
+            self.json.write_record_compact(&body, out)?;
+        } else {
+            self.json.write_record_compact(record, out)?;
+        }
+        out.write_char('}')?;
+        Ok(())
+    }
+}
+
+impl ValueFormatter for SplunkHec {
+    type Output = String;
+
+    fn format_value(&self, value: &Value) -> String {
+        self.try_format_value(value).unwrap_or_default()
+    }
+
+    fn try_format_value(&self, value: &Value) -> Result<String, FormatError> {
+        render(|out| self.write_value(value, out))
+    }
+}
+
+impl RecordFormatter for SplunkHec {
+    fn fmt_field(&self, field: &FieldStorage) -> String {
+        self.try_fmt_field(field).unwrap_or_default()
+    }
+
+    fn fmt_record(&self, record: &DataRecord) -> String {
+        self.try_fmt_record(record).unwrap_or_default()
+    }
+
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<String, FormatError> {
+        render(|out| self.write_field(field, out))
+    }
+
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<String, FormatError> {
+        render(|out| self.write_record_fmt(record, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TimeFormat;
+    use chrono::FixedOffset;
+    use wp_model_core::model::DataField;
+
+    fn make_record() -> DataRecord {
+        let ts = chrono::NaiveDateTime::parse_from_str(
+            "2019-08-06 12:12:19.250",
+            "%Y-%m-%d %H:%M:%S%.f",
+        )
+        .unwrap();
+        DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_time("ts", ts)),
+                FieldStorage::from_owned(DataField::from_chars("hostname", "web01")),
+                FieldStorage::from_owned(DataField::from_digit("status", 200)),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_default_envelope() {
+        assert_eq!(
+            SplunkHec::new().fmt_record(&make_record()),
+            r#"{"event":{"ts":"2019-08-06 12:12:19.250","hostname":"web01","status":200}}"#
+        );
+    }
+
+    #[test]
+    fn test_metadata() {
+        let hec = SplunkHec::new()
+            .with_time_field("ts")
+            .with_host(MetaValue::field("hostname"))
+            .with_source(MetaValue::field("missing"))
+            .with_sourcetype(MetaValue::text("access"))
+            .with_index(MetaValue::text("web"));
+        assert_eq!(
+            hec.fmt_record(&make_record()),
+            concat!(
+                r#"{"time":1565093539.25,"host":"web01","sourcetype":"access","index":"web","#,
+                r#""event":{"ts":"2019-08-06 12:12:19.250","hostname":"web01","status":200}}"#
+            )
+        );
+
+        let hec = hec
+            .with_remove_fields(true)
+            .with_json(Json::default().with_time_format(
+                TimeFormat::native().with_offset(FixedOffset::east_opt(8 * 3600).unwrap()),
+            ));
+        assert_eq!(
+            hec.fmt_record(&make_record()),
+            concat!(
+                r#"{"time":1565064739.25,"host":"web01","sourcetype":"access","index":"web","#,
+                r#""event":{"status":200}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_time_field_type() {
+        let hec = SplunkHec::new().with_time_field("status");
+        let err = hec.try_fmt_record(&make_record()).unwrap_err();
+        assert!(matches!(
+            err,
+            FormatError::UnsupportedType {
+                format: "splunk_hec",
+                type_name: "digit"
+            }
+        ));
+    }
+}
@@ -1,13 +1,33 @@
+use crate::error::FormatError;
+use crate::flatten::Flatten;
+use crate::float::{FloatAction, FloatPolicy};
 #[allow(deprecated)]
 use crate::formatter::DataFormat;
-use crate::formatter::{RecordFormatter, ValueFormatter};
+use crate::formatter::{RecordFormatter, RecordWriter, ValueFormatter, ValueWriter, render};
+use crate::null::NullPolicy;
+use crate::options::FmtOptions;
+use crate::time::TimeFormat;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use wp_model_core::model::fmt_def::TextFmt;
 use wp_model_core::model::{DataRecord, DataType, FieldStorage, Value, types::value::ObjectValue};
 
+/// SQL 方言，影响标识符引号、字符串转义、建表类型和 upsert 语法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SqlDialect {
+    #[default]
+    #[serde(alias = "postgresql")]
+    Postgres,
+    MySql,
+}
+
 pub struct SqlInsert {
     pub table_name: String,
     pub quote_identifiers: bool,
     pub obj_formatter: crate::SqlFormat,
+    dialect: SqlDialect,
+    opts: FmtOptions,
 }
 
 impl Default for SqlInsert {
@@ -16,6 +36,8 @@ impl Default for SqlInsert {
             table_name: String::new(),
             quote_identifiers: true,
             obj_formatter: crate::SqlFormat::from(&TextFmt::Json),
+            dialect: SqlDialect::default(),
+            opts: FmtOptions::default(),
         }
     }
 }
@@ -26,21 +48,128 @@ impl SqlInsert {
             table_name: table.into(),
             quote_identifiers: true,
             obj_formatter: crate::SqlFormat::from(&TextFmt::Json),
+            dialect: SqlDialect::default(),
+            opts: FmtOptions::default(),
         }
     }
+    /// 严格模式：NaN/Infinity 返回错误，而不是输出 `NULL` 或 `'Infinity'`
+    ///
+    /// 对象/数组列按 `obj_formatter` 自身的选项渲染。
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.opts.strict = strict;
+        self
+    }
+    /// `Value::Time` 的输出方式，默认与 `NaiveDateTime` 的 `Display` 一致
+    pub fn with_time_format(mut self, time: TimeFormat) -> Self {
+        self.opts.time = time;
+        self
+    }
+    /// 浮点数的位数、科学计数法和 NaN/Infinity 处理方式
+    pub fn with_float_policy(mut self, float: FloatPolicy) -> Self {
+        self.opts.float = float;
+        self
+    }
+    /// 空值字段的处理方式：原生空值、省略或占位文本
+    pub fn with_null_policy(mut self, null: NullPolicy) -> Self {
+        self.opts.null = null;
+        self
+    }
+    /// 将嵌套对象和数组展开为多个顶层字段，如 `extends.ssl_cipher`、`tags[0]`
+    pub fn with_flatten(mut self, flatten: Flatten) -> Self {
+        self.opts.flatten = Some(flatten);
+        self
+    }
+    pub fn with_dialect(mut self, dialect: SqlDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+    pub fn dialect(&self) -> SqlDialect {
+        self.dialect
+    }
     fn quote_identifier(&self, name: &str) -> String {
+        render(|out| Ok(self.write_identifier(name, out)?)).unwrap_or_default()
+    }
+    fn write_identifier(&self, name: &str, out: &mut dyn fmt::Write) -> fmt::Result {
         if self.quote_identifiers {
-            let escaped = name.replace('"', "\"\"");
-            format!("\"{}\"", escaped)
+            let quote = match self.dialect {
+                SqlDialect::Postgres => '"',
+                SqlDialect::MySql => '`',
+            };
+            write_escaped(name, quote, out)
         } else {
-            name.to_string()
+            out.write_str(name)
+        }
+    }
+    fn write_string(&self, value: &str, out: &mut dyn fmt::Write) -> fmt::Result {
+        match self.dialect {
+            SqlDialect::Postgres => write_escaped(value, '\'', out),
+            // MySQL 默认把反斜杠当作转义符，需要先加倍
+            SqlDialect::MySql if value.contains('\\') => {
+                write_escaped(&value.replace('\\', "\\\\"), '\'', out)
+            }
+            SqlDialect::MySql => write_escaped(value, '\'', out),
         }
     }
-    fn escape_string(&self, value: &str) -> String {
-        value.replace('\'', "''")
+    fn write_null(&self, out: &mut dyn fmt::Write) -> fmt::Result {
+        match self.opts.null.text() {
+            Some(text) => self.write_string(text, out),
+            None => out.write_str("NULL"),
+        }
+    }
+
+    /// 写出非 Ignore 字段的列名列表，以 `, ` 分隔
+    fn write_columns(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        for (i, field) in record
+            .items
+            .iter()
+            .filter(|f| *f.get_meta() != DataType::Ignore)
+            .enumerate()
+        {
+            if i > 0 {
+                out.write_str(", ")?;
+            }
+            self.write_identifier(field.get_name(), out)?;
+        }
+        Ok(())
+    }
+    /// 写出非 Ignore 字段的值列表，以 `, ` 分隔
+    fn write_values(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        for (i, field) in record
+            .items
+            .iter()
+            .filter(|f| *f.get_meta() != DataType::Ignore)
+            .enumerate()
+        {
+            if i > 0 {
+                out.write_str(", ")?;
+            }
+            self.write_field(field, out)?;
+        }
+        Ok(())
     }
 }
 
+/// 用 `quote` 包围文本，内部出现的 `quote` 以重复一次的方式转义
+fn write_escaped(value: &str, quote: char, out: &mut dyn fmt::Write) -> fmt::Result {
+    out.write_char(quote)?;
+    for (i, part) in value.split(quote).enumerate() {
+        if i > 0 {
+            out.write_char(quote)?;
+            out.write_char(quote)?;
+        }
+        out.write_str(part)?;
+    }
+    out.write_char(quote)
+}
+
 #[allow(deprecated)]
 impl DataFormat for SqlInsert {
     type Output = String;
@@ -51,29 +180,19 @@ impl DataFormat for SqlInsert {
         if *value { "TRUE" } else { "FALSE" }.to_string()
     }
     fn format_string(&self, value: &str) -> String {
-        format!("'{}'", self.escape_string(value))
+        render(|out| Ok(self.write_string(value, out)?)).unwrap_or_default()
     }
     fn format_i64(&self, value: &i64) -> String {
         value.to_string()
     }
     fn format_f64(&self, value: &f64) -> String {
-        if value.is_nan() {
-            "NULL".into()
-        } else if value.is_infinite() {
-            if value.is_sign_positive() {
-                "'Infinity'".into()
-            } else {
-                "'-Infinity'".into()
-            }
-        } else {
-            value.to_string()
-        }
+        self.format_value(&Value::Float(*value))
     }
     fn format_ip(&self, value: &std::net::IpAddr) -> String {
-        self.format_string(&value.to_string())
+        self.format_value(&Value::IpAddr(*value))
     }
     fn format_datetime(&self, value: &chrono::NaiveDateTime) -> String {
-        self.format_string(&value.to_string())
+        self.format_value(&Value::Time(*value))
     }
     fn format_object(&self, value: &ObjectValue) -> String {
         let inner = match &self.obj_formatter {
@@ -82,7 +201,7 @@ impl DataFormat for SqlInsert {
             crate::SqlFormat::Raw(f) => f.format_object(value),
             crate::SqlFormat::ProtoText(f) => f.format_object(value),
         };
-        format!("'{}'", self.escape_string(&inner))
+        render(|out| Ok(self.write_string(&inner, out)?)).unwrap_or_default()
     }
     fn format_array(&self, value: &[FieldStorage]) -> String {
         let inner = match &self.obj_formatter {
@@ -91,90 +210,72 @@ impl DataFormat for SqlInsert {
             crate::SqlFormat::Raw(f) => f.format_array(value),
             crate::SqlFormat::ProtoText(f) => f.format_array(value),
         };
-        format!("'{}'", self.escape_string(&inner))
+        render(|out| Ok(self.write_string(&inner, out)?)).unwrap_or_default()
     }
     fn format_record(&self, record: &DataRecord) -> String {
-        let columns: Vec<String> = record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|f| self.quote_identifier(f.get_name()))
-            .collect();
-        let values: Vec<String> = record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|f| self.format_field(f))
-            .collect();
-        format!(
-            "INSERT INTO {} ({}) VALUES ({});",
-            self.quote_identifier(&self.table_name),
-            columns.join(", "),
-            values.join(", ")
-        )
+        self.fmt_record(record)
     }
     fn format_field(&self, field: &FieldStorage) -> String {
-        if *field.get_meta() == DataType::Ignore {
-            String::new()
-        } else {
-            self.fmt_value(field.get_value())
-        }
+        self.fmt_field(field)
     }
 }
 
 impl SqlInsert {
-    #[allow(deprecated)]
     pub fn format_batch(&self, records: &[DataRecord]) -> String {
-        if records.is_empty() {
-            return String::new();
-        }
-        let mut output = String::new();
-        let columns: Vec<String> = records[0]
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|f| self.quote_identifier(f.get_name()))
-            .collect();
-        use std::fmt::Write;
-        writeln!(
-            output,
-            "INSERT INTO {} ({}) VALUES",
-            self.quote_identifier(&self.table_name),
-            columns.join(", ")
-        )
-        .unwrap();
+        self.try_format_batch(records).unwrap_or_default()
+    }
+    pub fn try_format_batch(&self, records: &[DataRecord]) -> Result<String, FormatError> {
+        render(|out| self.write_batch_fmt(records, out))
+    }
+    /// 将多条记录写为一条批量 INSERT 语句，列名取自第一条记录
+    pub fn write_batch_fmt(
+        &self,
+        records: &[DataRecord],
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        let Some(first) = records.first() else {
+            return Ok(());
+        };
+        out.write_str("INSERT INTO ")?;
+        self.write_identifier(&self.table_name, out)?;
+        out.write_str(" (")?;
+        self.write_columns(&*self.opts.flattened(first)?, out)?;
+        out.write_str(") VALUES\n")?;
         for (i, record) in records.iter().enumerate() {
             if i > 0 {
-                output.push_str(",\n");
+                out.write_str(",\n")?;
             }
-            let values: Vec<String> = record
-                .items
-                .iter()
-                .filter(|f| *f.get_meta() != DataType::Ignore)
-                .map(|f| self.format_field(f))
-                .collect();
-            write!(output, "  ({})", values.join(", ")).unwrap();
+            out.write_str("  (")?;
+            self.write_values(&*self.opts.flattened(record)?, out)?;
+            out.write_char(')')?;
         }
-        output.push(';');
-        output
+        out.write_char(';')?;
+        Ok(())
     }
     pub fn generate_create_table(&self, records: &[DataRecord]) -> String {
         if records.is_empty() {
             return String::new();
         }
+        let Ok(first) = self.opts.flattened(&records[0]) else {
+            return String::new();
+        };
         let mut columns = Vec::new();
-        for field in &records[0].items {
+        for field in &first.items {
             if *field.get_meta() == DataType::Ignore {
                 continue;
             }
-            let sql_type = &match field.get_value() {
-                Value::Bool(_) => "BOOLEAN",
-                Value::Chars(_) => "TEXT",
-                Value::Digit(_) => "BIGINT",
-                Value::Float(_) => "DOUBLE PRECISION",
-                Value::Time(_) => "TIMESTAMP",
-                Value::IpAddr(_) => "INET",
-                Value::Obj(_) | Value::Array(_) => "JSONB",
+            let sql_type = &match (field.get_value(), self.dialect) {
+                (Value::Bool(_), _) => "BOOLEAN",
+                (Value::Chars(_), _) => "TEXT",
+                (Value::Digit(_), _) => "BIGINT",
+                (Value::Float(_), SqlDialect::Postgres) => "DOUBLE PRECISION",
+                (Value::Float(_), SqlDialect::MySql) => "DOUBLE",
+                (Value::Time(_), SqlDialect::Postgres) => "TIMESTAMP",
+                (Value::Time(_), SqlDialect::MySql) => "DATETIME",
+                (Value::IpAddr(_), SqlDialect::Postgres) => "INET",
+                (Value::IpAddr(_), SqlDialect::MySql) => "VARCHAR(45)",
+                (Value::Obj(_) | Value::Array(_), SqlDialect::Postgres) => "JSONB",
+                (Value::Obj(_) | Value::Array(_), SqlDialect::MySql) => "JSON",
                 _ => "TEXT",
             };
             columns.push(format!(
@@ -192,6 +293,9 @@ impl SqlInsert {
     #[allow(deprecated)]
     pub fn format_upsert(&self, record: &DataRecord, conflict_columns: &[&str]) -> String {
         let insert = self.format_record(record);
+        let Ok(record) = self.opts.flattened(record) else {
+            return insert;
+        };
         let mut update_parts = Vec::new();
         for field in record
             .items
@@ -201,11 +305,21 @@ impl SqlInsert {
             let name = field.get_name();
             if !conflict_columns.contains(&name) {
                 let col = self.quote_identifier(name);
-                update_parts.push(format!("{} = EXCLUDED.{}", &col, &col));
+                update_parts.push(match self.dialect {
+                    SqlDialect::Postgres => format!("{} = EXCLUDED.{}", &col, &col),
+                    SqlDialect::MySql => format!("{} = VALUES({})", &col, &col),
+                });
             }
         }
         if update_parts.is_empty() {
             insert
+        } else if self.dialect == SqlDialect::MySql {
+            // MySQL 依据表上的唯一键判断冲突，不需要列出冲突列
+            format!(
+                "{} ON DUPLICATE KEY UPDATE {};",
+                insert.trim_end_matches(';'),
+                update_parts.join(", ")
+            )
         } else {
             let quoted_conflicts: Vec<String> = conflict_columns
                 .iter()
@@ -233,6 +347,7 @@ mod tests {
             table_name: "t".into(),
             quote_identifiers: true,
             obj_formatter: crate::SqlFormat::from(&TextFmt::Json),
+            ..Default::default()
         };
         let r = DataRecord {
             id: Default::default(),
@@ -356,6 +471,7 @@ mod tests {
             table_name: "t".into(),
             quote_identifiers: false,
             obj_formatter: crate::SqlFormat::from(&TextFmt::Json),
+            ..Default::default()
         };
         assert_eq!(sql.quote_identifier("name"), "name");
     }
@@ -518,6 +634,53 @@ mod tests {
         let record = make_record_with_obj();
         assert_eq!(sql.format_record(&record), sql.fmt_record(&record));
     }
+
+    #[test]
+    fn test_strict_non_finite() {
+        let sql = SqlInsert::new_with_json("t").with_strict(true);
+        let err = sql
+            .try_format_value(&Value::Float(f64::INFINITY))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            FormatError::Unrepresentable { format: "sql", .. }
+        ));
+
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![FieldStorage::from_owned(DataField::from_float(
+                "v",
+                f64::NAN,
+            ))],
+        };
+        assert!(sql.try_fmt_record(&record).is_err());
+        assert!(sql.try_format_batch(&[record]).is_err());
+    }
+
+    #[test]
+    fn test_mysql_dialect() {
+        let sql = SqlInsert::new_with_json("t").with_dialect(SqlDialect::MySql);
+        assert_eq!(sql.dialect(), SqlDialect::MySql);
+        assert_eq!(sql.quote_identifier("na`me"), "`na``me`");
+        assert_eq!(
+            sql.format_value(&Value::Chars("it's C:\\tmp".into())),
+            "'it''s C:\\\\tmp'"
+        );
+
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_digit("id", 1)),
+                FieldStorage::from_owned(DataField::from_float("score", 1.5)),
+            ],
+        };
+        assert_eq!(
+            sql.format_upsert(&record, &["id"]),
+            "INSERT INTO `t` (`id`, `score`) VALUES (1, 1.5) ON DUPLICATE KEY UPDATE `score` = VALUES(`score`);"
+        );
+        let ddl = sql.generate_create_table(&[record]);
+        assert!(ddl.contains("`score` DOUBLE\n"));
+    }
 }
 
 // ============================================================================
@@ -525,113 +688,114 @@ mod tests {
 // ============================================================================
 
 #[allow(clippy::items_after_test_module)]
-impl ValueFormatter for SqlInsert {
-    type Output = String;
-
-    fn format_value(&self, value: &Value) -> String {
+impl ValueWriter for SqlInsert {
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
         match value {
-            Value::Null => "NULL".to_string(),
-            Value::Bool(v) => if *v { "TRUE" } else { "FALSE" }.to_string(),
-            Value::Chars(v) => format!("'{}'", self.escape_string(v)),
-            Value::Digit(v) => v.to_string(),
-            Value::Float(v) => {
-                if v.is_nan() {
-                    "NULL".into()
-                } else if v.is_infinite() {
-                    if v.is_sign_positive() {
-                        "'Infinity'".into()
+            Value::Null => self.write_null(out)?,
+            Value::Bool(v) => out.write_str(if *v { "TRUE" } else { "FALSE" })?,
+            Value::Chars(v) => self.write_string(v, out)?,
+            Value::Digit(v) => write!(out, "{}", v)?,
+            Value::Float(v) => match self.opts.float.action(*v) {
+                FloatAction::Native if v.is_finite() => write!(out, "{}", v)?,
+                FloatAction::Native => {
+                    self.opts
+                        .substitute(|| FormatError::non_finite("sql", *v))?;
+                    if v.is_nan() {
+                        out.write_str("NULL")?
+                    } else if v.is_sign_positive() {
+                        out.write_str("'Infinity'")?
                     } else {
-                        "'-Infinity'".into()
+                        out.write_str("'-Infinity'")?
                     }
-                } else {
-                    v.to_string()
                 }
+                FloatAction::Finite => self.opts.float.write_finite(*v, out)?,
+                FloatAction::Null => self.write_null(out)?,
+                FloatAction::Text(text) => self.write_string(text, out)?,
+                FloatAction::Error => return Err(FormatError::non_finite("sql", *v)),
+            },
+            Value::IpAddr(v) => write!(out, "'{}'", v)?,
+            Value::Time(v) => self
+                .opts
+                .time
+                .write_quoted(v, out, |s, o| self.write_string(s, o))?,
+            Value::Obj(_) | Value::Array(_) => {
+                let inner = render(|o| self.obj_formatter.write_value(value, o))?;
+                self.write_string(&inner, out)?
             }
-            Value::IpAddr(v) => format!("'{}'", self.escape_string(&v.to_string())),
-            Value::Time(v) => format!("'{}'", self.escape_string(&v.to_string())),
-            Value::Obj(_obj) => {
-                let inner = match &self.obj_formatter {
-                    crate::SqlFormat::Json(f) => f.format_value(value),
-                    crate::SqlFormat::Kv(f) => f.format_value(value),
-                    crate::SqlFormat::Raw(f) => f.format_value(value),
-                    crate::SqlFormat::ProtoText(f) => f.format_value(value),
-                };
-                format!("'{}'", self.escape_string(&inner))
+            Value::IpNet(_)
+            | Value::Domain(_)
+            | Value::Url(_)
+            | Value::Email(_)
+            | Value::IdCard(_)
+            | Value::MobilePhone(_)
+            | Value::Hex(_)
+            | Value::Symbol(_) => self.write_string(&value.to_string(), out)?,
+            Value::Ignore(_) => {
+                self.opts
+                    .substitute(|| FormatError::unsupported("sql", value))?;
+                out.write_str("''")?
             }
-            Value::Array(arr) => {
-                let inner = match &self.obj_formatter {
-                    crate::SqlFormat::Json(f) => {
-                        let items: Vec<String> = arr
-                            .iter()
-                            .map(|field| f.format_value(field.get_value()))
-                            .collect();
-                        format!("[{}]", items.join(","))
-                    }
-                    crate::SqlFormat::Kv(f) => {
-                        let mut output = String::new();
-                        output.push('[');
-                        for (i, field) in arr.iter().enumerate() {
-                            if i > 0 {
-                                output.push_str(", ");
-                            }
-                            output.push_str(&f.format_value(field.get_value()));
-                        }
-                        output.push(']');
-                        output
-                    }
-                    crate::SqlFormat::Raw(f) => {
-                        if arr.is_empty() {
-                            "[]".to_string()
-                        } else {
-                            let content: Vec<String> = arr
-                                .iter()
-                                .map(|field| f.format_value(field.get_value()))
-                                .collect();
-                            format!("[{}]", content.join(", "))
-                        }
-                    }
-                    crate::SqlFormat::ProtoText(f) => {
-                        let items: Vec<String> = arr
-                            .iter()
-                            .map(|field| f.format_value(field.get_value()))
-                            .collect();
-                        format!("[{}]", items.join(", "))
-                    }
-                };
-                format!("'{}'", self.escape_string(&inner))
-            }
-            _ => format!("'{}'", self.escape_string(&value.to_string())),
         }
+        Ok(())
     }
 }
 
-impl RecordFormatter for SqlInsert {
-    fn fmt_field(&self, field: &FieldStorage) -> String {
+impl RecordWriter for SqlInsert {
+    fn write_field(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
         if *field.get_meta() == DataType::Ignore {
-            String::new()
+            Ok(())
         } else {
-            self.format_value(field.get_value())
+            self.write_value(field.get_value(), out)
         }
     }
 
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        let record = self.opts.flattened(record)?;
+        out.write_str("INSERT INTO ")?;
+        self.write_identifier(&self.table_name, out)?;
+        out.write_str(" (")?;
+        self.write_columns(&record, out)?;
+        out.write_str(") VALUES (")?;
+        self.write_values(&record, out)?;
+        out.write_str(");")?;
+        Ok(())
+    }
+}
+
+impl ValueFormatter for SqlInsert {
+    type Output = String;
+
+    fn format_value(&self, value: &Value) -> String {
+        self.try_format_value(value).unwrap_or_default()
+    }
+
+    fn try_format_value(&self, value: &Value) -> Result<String, FormatError> {
+        render(|out| self.write_value(value, out))
+    }
+}
+
+impl RecordFormatter for SqlInsert {
+    fn fmt_field(&self, field: &FieldStorage) -> String {
+        self.try_fmt_field(field).unwrap_or_default()
+    }
+
     fn fmt_record(&self, record: &DataRecord) -> String {
-        let columns: Vec<String> = record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|f| self.quote_identifier(f.get_name()))
-            .collect();
-        let values: Vec<String> = record
-            .items
-            .iter()
-            .filter(|f| *f.get_meta() != DataType::Ignore)
-            .map(|f| self.fmt_field(f))
-            .collect();
-        format!(
-            "INSERT INTO {} ({}) VALUES ({});",
-            self.quote_identifier(&self.table_name),
-            columns.join(", "),
-            values.join(", ")
-        )
+        self.try_fmt_record(record).unwrap_or_default()
+    }
+
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<String, FormatError> {
+        render(|out| self.write_field(field, out))
+    }
+
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<String, FormatError> {
+        render(|out| self.write_record_fmt(record, out))
     }
 }
@@ -0,0 +1,803 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use chrono::{FixedOffset, NaiveDateTime, Timelike};
+use wp_model_core::model::{DataRecord, FieldStorage, Value};
+
+use crate::Raw;
+use crate::error::{ConfigError, FormatError};
+use crate::flatten::{Collision, Flatten};
+use crate::formatter::{RecordFormatter, RecordWriter, ValueFormatter, ValueWriter, render};
+use crate::meta::{MetaValue, without_fields};
+use crate::projected::lookup;
+use crate::time::TimeFormat;
+
+/// RFC 5424 的空值
+const NILVALUE: &str = "-";
+
+/// 设施名称，下标即设施代码
+const FACILITIES: [&str; 24] = [
+    "kern",
+    "user",
+    "mail",
+    "daemon",
+    "auth",
+    "syslog",
+    "lpr",
+    "news",
+    "uucp",
+    "cron",
+    "authpriv",
+    "ftp",
+    "ntp",
+    "audit",
+    "console",
+    "solaris-cron",
+    "local0",
+    "local1",
+    "local2",
+    "local3",
+    "local4",
+    "local5",
+    "local6",
+    "local7",
+];
+
+/// 级别名称，下标即级别代码
+const SEVERITIES: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+
+/// syslog 协议版本
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyslogProtocol {
+    /// `<PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA MSG`
+    #[default]
+    Rfc5424,
+    /// BSD 格式 `<PRI>Mmm dd hh:mm:ss HOSTNAME TAG[PROCID]: MSG`，不含结构化数据
+    Rfc3164,
+}
+
+/// 消息的分帧方式（RFC 6587）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// 不分帧，用于 UDP 等按报文传输的通道
+    #[default]
+    Bare,
+    /// 以 `\n` 结尾；消息内（`MSG` 和参数值）的换行写为 `#012`（同 rsyslog），
+    /// 避免接收端把一条消息拆成多条
+    NonTransparent,
+    /// 前缀字节长度和空格，如 `87 <13>1 ...`，消息中可以包含换行
+    OctetCounting,
+}
+
+/// syslog 消息
+///
+/// `PRI` 由设施和级别组成，默认 `user.notice`（13），可从字段映射；
+/// `TIMESTAMP` 取自 `with_time_field` 指定的 `Value::Time` 字段，时区由 `with_time_offset`
+/// 指定（默认 UTC）；`HOSTNAME`、`APP-NAME`、`PROCID`、`MSGID` 取固定文本或字段。
+/// 其余字段按 `Flatten` 展开后作为 RFC 5424 的 `STRUCTURED-DATA`，展开后重名的参数
+/// 依次追加 `_2`、`_3` 等后缀；参数值按 `Raw` 输出，并转义 `"`、`\` 和 `]`。
+///
+/// `MSG` 默认由内部的 `RecordFormatter` 对其余字段生成；`with_message_field` 改为取某个
+/// 字段的文本。两者同时输出结构化数据会重复，此时可用 `with_structured_data(false)` 关闭。
+///
+/// - 头部字段按 RFC 5424 的长度截断，非可打印 ASCII 字符替换为 `_`；取不到值时输出 `-`
+/// - 级别和设施字段可以是代码（`3`）或名称（`err`、`error`、`local0`），不区分大小写；
+///   无法识别时使用固定值
+/// - RFC 3164 模式没有结构化数据，取不到 `HOSTNAME` 时省略该字段（3164 没有 `-` 空值）；
+///   未设置或缺少时间字段时使用当前时间
+///
+/// ```
+/// use wp_data_fmt::{Framing, Json, MetaValue, Syslog};
+///
+/// let syslog = Syslog::new(Json::default())
+///     .with_facility(16)
+///     .unwrap()
+///     .with_severity_field("level")
+///     .with_time_field("timestamp")
+///     .with_hostname(MetaValue::field("host"))
+///     .with_app_name(MetaValue::text("nginx"))
+///     .with_message_field("message")
+///     .with_framing(Framing::OctetCounting);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Syslog<F> {
+    inner: F,
+    protocol: SyslogProtocol,
+    framing: Framing,
+    facility: u8,
+    severity: u8,
+    facility_field: Option<String>,
+    severity_field: Option<String>,
+    time_field: Option<String>,
+    offset: Option<FixedOffset>,
+    hostname: Option<MetaValue>,
+    app_name: Option<MetaValue>,
+    procid: Option<MetaValue>,
+    msgid: Option<MetaValue>,
+    message_field: Option<String>,
+    structured_data: bool,
+    sd_id: String,
+    flatten: Flatten,
+    raw: Raw,
+}
+
+impl<F> Syslog<F> {
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            protocol: SyslogProtocol::default(),
+            framing: Framing::default(),
+            facility: 1,
+            severity: 5,
+            facility_field: None,
+            severity_field: None,
+            time_field: None,
+            offset: None,
+            hostname: None,
+            app_name: None,
+            procid: None,
+            msgid: None,
+            message_field: None,
+            structured_data: true,
+            sd_id: "fields@32473".to_string(),
+            flatten: Flatten::new().with_collision(Collision::Suffix),
+            raw: Raw::default(),
+        }
+    }
+
+    pub fn with_protocol(mut self, protocol: SyslogProtocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// 固定的设施代码，0~23
+    pub fn with_facility(mut self, facility: u8) -> Result<Self, ConfigError> {
+        if usize::from(facility) >= FACILITIES.len() {
+            return Err(invalid(
+                "facility",
+                format!("facility {} out of 0..=23", facility),
+            ));
+        }
+        self.facility = facility;
+        Ok(self)
+    }
+
+    /// 固定的级别代码，0~7
+    pub fn with_severity(mut self, severity: u8) -> Result<Self, ConfigError> {
+        if usize::from(severity) >= SEVERITIES.len() {
+            return Err(invalid(
+                "severity",
+                format!("severity {} out of 0..=7", severity),
+            ));
+        }
+        self.severity = severity;
+        Ok(self)
+    }
+
+    /// 从字段映射设施，无法识别时使用 `with_facility` 的值
+    pub fn with_facility_field(mut self, field: impl Into<String>) -> Self {
+        self.facility_field = Some(field.into());
+        self
+    }
+
+    /// 从字段映射级别，无法识别时使用 `with_severity` 的值
+    pub fn with_severity_field(mut self, field: impl Into<String>) -> Self {
+        self.severity_field = Some(field.into());
+        self
+    }
+
+    pub fn with_time_field(mut self, field: impl Into<String>) -> Self {
+        self.time_field = Some(field.into());
+        self
+    }
+
+    /// `Value::Time` 所在的时区，同时也是 `TIMESTAMP` 的输出时区
+    pub fn with_time_offset(mut self, offset: FixedOffset) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub fn with_hostname(mut self, hostname: MetaValue) -> Self {
+        self.hostname = Some(hostname);
+        self
+    }
+
+    pub fn with_app_name(mut self, app_name: MetaValue) -> Self {
+        self.app_name = Some(app_name);
+        self
+    }
+
+    pub fn with_procid(mut self, procid: MetaValue) -> Self {
+        self.procid = Some(procid);
+        self
+    }
+
+    pub fn with_msgid(mut self, msgid: MetaValue) -> Self {
+        self.msgid = Some(msgid);
+        self
+    }
+
+    /// `MSG` 取该字段的文本，而不是内部格式化器的输出
+    pub fn with_message_field(mut self, field: impl Into<String>) -> Self {
+        self.message_field = Some(field.into());
+        self
+    }
+
+    /// 是否输出 `STRUCTURED-DATA`，默认输出
+    pub fn with_structured_data(mut self, structured_data: bool) -> Self {
+        self.structured_data = structured_data;
+        self
+    }
+
+    /// `SD-ID`，默认 `fields@32473`；须为 1~32 个可打印 ASCII 字符，不含 `=`、`]`、`"`
+    pub fn with_sd_id(mut self, sd_id: impl Into<String>) -> Result<Self, ConfigError> {
+        let sd_id = sd_id.into();
+        if sd_id.is_empty() || sd_id.len() > 32 || !sd_id.bytes().all(is_sd_name_byte) {
+            return Err(invalid("sd_id", format!("invalid SD-ID {:?}", sd_id)));
+        }
+        self.sd_id = sd_id;
+        Ok(self)
+    }
+
+    /// 嵌套字段的展开方式，默认以 `.` 连接，展开后重名时追加后缀
+    pub fn with_flatten(mut self, flatten: Flatten) -> Self {
+        self.flatten = flatten;
+        self
+    }
+
+    /// `SD-PARAM` 值的文本选项
+    pub fn with_raw(mut self, raw: Raw) -> Self {
+        self.raw = raw;
+        self
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    fn pri(&self, record: &DataRecord) -> u8 {
+        let facility = pri_part(
+            record,
+            self.facility_field.as_deref(),
+            &FACILITIES,
+            |name| match name {
+                "security" => Some(4),
+                _ => None,
+            },
+        );
+        let severity = pri_part(
+            record,
+            self.severity_field.as_deref(),
+            &SEVERITIES,
+            |name| match name {
+                "emergency" | "panic" => Some(0),
+                "critical" | "fatal" => Some(2),
+                "error" => Some(3),
+                "warn" => Some(4),
+                "information" => Some(6),
+                "trace" => Some(7),
+                _ => None,
+            },
+        );
+        facility.unwrap_or(self.facility) * 8 + severity.unwrap_or(self.severity)
+    }
+
+    fn timestamp(&self, record: &DataRecord) -> Result<Option<NaiveDateTime>, FormatError> {
+        let value = self
+            .time_field
+            .as_deref()
+            .and_then(|name| lookup(record, name))
+            .map(|f| f.get_value());
+        match value {
+            None | Some(Value::Null | Value::Ignore(_)) => Ok(None),
+            Some(Value::Time(t)) => Ok(Some(*t)),
+            Some(value) => Err(FormatError::unsupported("syslog", value)),
+        }
+    }
+
+    fn write_timestamp(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        let offset = self.offset.unwrap_or(FixedOffset::east_opt(0).unwrap());
+        let time = self.timestamp(record)?;
+        match self.protocol {
+            SyslogProtocol::Rfc5424 => match time {
+                // TIME-SECFRAC 最多 6 位
+                Some(t) => {
+                    let t = t.with_nanosecond(t.nanosecond() / 1000 * 1000).unwrap_or(t);
+                    TimeFormat::rfc3339().with_offset(offset).write(&t, out)?
+                }
+                None => out.write_str(NILVALUE)?,
+            },
+            SyslogProtocol::Rfc3164 => {
+                let t = time.unwrap_or_else(|| {
+                    (chrono::Utc::now().naive_utc() + offset)
+                        .with_nanosecond(0)
+                        .unwrap()
+                });
+                write!(out, "{}", t.format("%b %e %H:%M:%S"))?
+            }
+        }
+        Ok(())
+    }
+
+    /// 头部字段，取不到值时为 `-`
+    fn header<'a>(
+        &self,
+        meta: Option<&'a MetaValue>,
+        record: &'a DataRecord,
+        max_len: usize,
+    ) -> Cow<'a, str> {
+        match meta.and_then(|m| m.resolve(record)) {
+            Some(text) if !text.is_empty() => header_value(text, max_len),
+            _ => Cow::Borrowed(NILVALUE),
+        }
+    }
+
+    /// 不输出到结构化数据和内部格式化器的字段
+    fn promoted(&self) -> Vec<&str> {
+        [&self.hostname, &self.app_name, &self.procid, &self.msgid]
+            .into_iter()
+            .filter_map(|meta| meta.as_ref()?.field_name())
+            .chain(self.facility_field.as_deref())
+            .chain(self.severity_field.as_deref())
+            .chain(self.time_field.as_deref())
+            .chain(self.message_field.as_deref())
+            .collect()
+    }
+
+    fn write_structured_data(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        let flat = self.flatten.flatten(record)?;
+        let mut params = flat
+            .items
+            .iter()
+            .filter(|f| !matches!(f.get_value(), Value::Null | Value::Ignore(_)))
+            .peekable();
+        if !self.structured_data || params.peek().is_none() {
+            out.write_str(NILVALUE)?;
+            return Ok(());
+        }
+        write!(out, "[{}", self.sd_id)?;
+        for field in params {
+            write!(out, " {}=\"", param_name(field.get_name()))?;
+            let value = self.raw.try_format_value(field.get_value())?;
+            write_param_value(&value, out)?;
+            out.write_char('"')?;
+        }
+        out.write_char(']')?;
+        Ok(())
+    }
+}
+
+impl<F: RecordFormatter<Output = String>> Syslog<F> {
+    /// 写出不含分帧的消息
+    fn write_message(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        write!(out, "<{}>", self.pri(record))?;
+        let rest = without_fields(record, &self.promoted());
+        match self.protocol {
+            SyslogProtocol::Rfc5424 => {
+                out.write_str("1 ")?;
+                self.write_timestamp(record, out)?;
+                for (meta, max_len) in [
+                    (&self.hostname, 255),
+                    (&self.app_name, 48),
+                    (&self.procid, 128),
+                    (&self.msgid, 32),
+                ] {
+                    write!(out, " {}", self.header(meta.as_ref(), record, max_len))?;
+                }
+                out.write_char(' ')?;
+                self.write_structured_data(&rest, out)?;
+            }
+            SyslogProtocol::Rfc3164 => {
+                self.write_timestamp(record, out)?;
+                let hostname = self.hostname.as_ref().and_then(|m| m.resolve(record));
+                if let Some(hostname) = hostname.filter(|h| !h.is_empty()) {
+                    write!(out, " {}", header_value(hostname, 255))?;
+                }
+                let tag = self.app_name.as_ref().and_then(|m| m.resolve(record));
+                if let Some(tag) = tag.filter(|t| !t.is_empty()) {
+                    write!(out, " {}", header_value(tag, 32))?;
+                    let procid = self.procid.as_ref().and_then(|m| m.resolve(record));
+                    if let Some(procid) = procid.filter(|p| !p.is_empty()) {
+                        write!(out, "[{}]", header_value(procid, 128))?;
+                    }
+                    out.write_char(':')?;
+                }
+            }
+        }
+        let message = match &self.message_field {
+            Some(name) => MetaValue::field(name.as_str())
+                .resolve(record)
+                .map(Cow::into_owned),
+            None => Some(self.inner.try_fmt_record(&rest)?),
+        };
+        if let Some(message) = message.filter(|m| !m.is_empty()) {
+            write!(out, " {}", message)?;
+        }
+        Ok(())
+    }
+}
+
+/// 从字段取设施或级别：代码或名称（不区分大小写），`alias` 处理别名
+fn pri_part(
+    record: &DataRecord,
+    field: Option<&str>,
+    names: &[&str],
+    alias: impl Fn(&str) -> Option<u8>,
+) -> Option<u8> {
+    let value = lookup(record, field?)?.get_value();
+    let code = match value {
+        Value::Digit(n) => u8::try_from(*n).ok()?,
+        Value::Chars(s) => {
+            let s = s.trim().to_ascii_lowercase();
+            match s.parse::<u8>() {
+                Ok(n) => n,
+                Err(_) => names
+                    .iter()
+                    .position(|name| *name == s)
+                    .and_then(|i| u8::try_from(i).ok())
+                    .or_else(|| alias(&s))?,
+            }
+        }
+        _ => return None,
+    };
+    (usize::from(code) < names.len()).then_some(code)
+}
+
+/// 头部字段只允许可打印 ASCII（33~126），其余字符替换为 `_`，再截断到 `max_len`
+fn header_value(text: Cow<'_, str>, max_len: usize) -> Cow<'_, str> {
+    if text.len() <= max_len && text.bytes().all(|b| b.is_ascii_graphic()) {
+        return text;
+    }
+    Cow::Owned(
+        text.chars()
+            .map(|c| if c.is_ascii_graphic() { c } else { '_' })
+            .take(max_len)
+            .collect(),
+    )
+}
+
+fn is_sd_name_byte(b: u8) -> bool {
+    b.is_ascii_graphic() && !matches!(b, b'=' | b']' | b'"')
+}
+
+/// `PARAM-NAME`：非法字符（含非 ASCII 字符）逐字符替换为 `_`，最多 32 个字符
+fn param_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && is_sd_name_byte(c as u8) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(32)
+        .collect();
+    if name.is_empty() {
+        "_".to_string()
+    } else {
+        name
+    }
+}
+
+/// `PARAM-VALUE` 中的 `"`、`\` 和 `]` 须加反斜杠
+fn write_param_value(value: &str, out: &mut dyn fmt::Write) -> fmt::Result {
+    let mut rest = value;
+    while let Some(i) = rest.find(['"', '\\', ']']) {
+        out.write_str(&rest[..i])?;
+        out.write_char('\\')?;
+        out.write_str(&rest[i..i + 1])?;
+        rest = &rest[i + 1..];
+    }
+    out.write_str(rest)
+}
+
+fn invalid(option: &'static str, reason: String) -> ConfigError {
+    ConfigError::InvalidOption {
+        kind: "syslog",
+        option,
+        reason,
+    }
+}
+
+impl<F: RecordFormatter<Output = String>> ValueWriter for Syslog<F> {
+    fn write_value(&self, value: &Value, out: &mut dyn fmt::Write) -> Result<(), FormatError> {
+        self.raw.write_value(value, out)
+    }
+}
+
+impl<F: RecordFormatter<Output = String>> RecordWriter for Syslog<F> {
+    fn write_field(
+        &self,
+        field: &FieldStorage,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        self.raw.write_field(field, out)
+    }
+
+    /// 按 `Framing` 分帧后的一条消息
+    fn write_record_fmt(
+        &self,
+        record: &DataRecord,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        match self.framing {
+            Framing::Bare => self.write_message(record, out),
+            Framing::NonTransparent => {
+                let message = render(|buf| self.write_message(record, buf))?;
+                out.write_str(&message.replace('\n', "#012"))?;
+                out.write_char('\n')?;
+                Ok(())
+            }
+            Framing::OctetCounting => {
+                let message = render(|buf| self.write_message(record, buf))?;
+                write!(out, "{} {}", message.len(), message)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<F: RecordFormatter<Output = String>> ValueFormatter for Syslog<F> {
+    type Output = String;
+
+    fn format_value(&self, value: &Value) -> String {
+        self.try_format_value(value).unwrap_or_default()
+    }
+
+    fn try_format_value(&self, value: &Value) -> Result<String, FormatError> {
+        render(|out| self.write_value(value, out))
+    }
+}
+
+impl<F: RecordFormatter<Output = String>> RecordFormatter for Syslog<F> {
+    fn fmt_field(&self, field: &FieldStorage) -> String {
+        self.try_fmt_field(field).unwrap_or_default()
+    }
+
+    fn fmt_record(&self, record: &DataRecord) -> String {
+        self.try_fmt_record(record).unwrap_or_default()
+    }
+
+    fn try_fmt_field(&self, field: &FieldStorage) -> Result<String, FormatError> {
+        render(|out| self.write_field(field, out))
+    }
+
+    fn try_fmt_record(&self, record: &DataRecord) -> Result<String, FormatError> {
+        render(|out| self.write_record_fmt(record, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Json, KeyValue};
+    use wp_model_core::model::DataField;
+    use wp_model_core::model::types::value::ObjectValue;
+
+    fn make_record() -> DataRecord {
+        let ts = chrono::NaiveDateTime::parse_from_str(
+            "2019-08-06 12:12:19.250",
+            "%Y-%m-%d %H:%M:%S%.f",
+        )
+        .unwrap();
+        let mut http = ObjectValue::new();
+        http.insert(
+            "status".to_string(),
+            FieldStorage::from_owned(DataField::from_digit("status", 200)),
+        );
+        DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_time("ts", ts)),
+                FieldStorage::from_owned(DataField::from_chars("host", "web01")),
+                FieldStorage::from_owned(DataField::from_chars("level", "ERROR")),
+                FieldStorage::from_owned(DataField::from_digit("pid", 4242)),
+                FieldStorage::from_owned(DataField::from_chars("message", "GET /index")),
+                FieldStorage::from_owned(DataField::from_obj("http", http)),
+                FieldStorage::from_owned(DataField::from_chars("path", r#"/a"b\c]"#)),
+            ],
+        }
+    }
+
+    fn make_syslog() -> Syslog<Json> {
+        Syslog::new(Json::default())
+            .with_facility(16)
+            .unwrap()
+            .with_severity_field("level")
+            .with_time_field("ts")
+            .with_hostname(MetaValue::field("host"))
+            .with_app_name(MetaValue::text("nginx"))
+            .with_procid(MetaValue::field("pid"))
+            .with_message_field("message")
+    }
+
+    #[test]
+    fn test_rfc5424() {
+        assert_eq!(
+            make_syslog().fmt_record(&make_record()),
+            concat!(
+                r#"<131>1 2019-08-06T12:12:19.250Z web01 nginx 4242 - "#,
+                r#"[fields@32473 http.status="200" path="/a\"b\\c\]"] GET /index"#
+            )
+        );
+
+        let syslog = Syslog::new(KeyValue::default())
+            .with_structured_data(false)
+            .with_time_offset(FixedOffset::east_opt(8 * 3600).unwrap());
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![FieldStorage::from_owned(DataField::from_digit("n", 1))],
+        };
+        assert_eq!(syslog.fmt_record(&record), "<13>1 - - - - - - n: 1");
+        assert_eq!(
+            syslog
+                .with_time_field("missing")
+                .with_msgid(MetaValue::text("access log"))
+                .fmt_record(&record),
+            "<13>1 - - - - access_log - n: 1"
+        );
+    }
+
+    #[test]
+    fn test_rfc3164_and_framing() {
+        let syslog = make_syslog().with_protocol(SyslogProtocol::Rfc3164);
+        assert_eq!(
+            syslog.fmt_record(&make_record()),
+            "<131>Aug  6 12:12:19 web01 nginx[4242]: GET /index"
+        );
+
+        // 3164 没有空值，缺少 HOSTNAME 时省略
+        let syslog = make_syslog()
+            .with_protocol(SyslogProtocol::Rfc3164)
+            .with_hostname(MetaValue::field("missing"));
+        assert_eq!(
+            syslog.fmt_record(&make_record()),
+            "<131>Aug  6 12:12:19 nginx[4242]: GET /index"
+        );
+
+        let syslog = make_syslog().with_framing(Framing::OctetCounting);
+        let line = syslog.fmt_record(&make_record());
+        let (len, message) = line.split_once(' ').unwrap();
+        assert_eq!(len.parse::<usize>().unwrap(), message.len());
+        assert!(message.starts_with("<131>1 "));
+
+        let syslog = make_syslog().with_framing(Framing::NonTransparent);
+        assert!(syslog.fmt_record(&make_record()).ends_with("GET /index\n"));
+
+        // 消息内的换行不能充当分帧的结尾
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![FieldStorage::from_owned(DataField::from_chars(
+                "msg", "a\nb",
+            ))],
+        };
+        let syslog = Syslog::new(Json::default()).with_message_field("msg");
+        assert_eq!(
+            syslog
+                .with_framing(Framing::NonTransparent)
+                .fmt_record(&record),
+            "<13>1 - - - - - - a#012b\n"
+        );
+        let syslog = Syslog::new(Json::default())
+            .with_message_field("none")
+            .with_framing(Framing::NonTransparent);
+        assert_eq!(
+            syslog.fmt_record(&record),
+            "<13>1 - - - - - [fields@32473 msg=\"a#012b\"]\n"
+        );
+    }
+
+    #[test]
+    fn test_flatten_collision() {
+        let mut http = ObjectValue::new();
+        http.insert(
+            "status".to_string(),
+            FieldStorage::from_owned(DataField::from_digit("status", 200)),
+        );
+        let record = DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_obj("http", http)),
+                FieldStorage::from_owned(DataField::from_digit("http.status", 404)),
+            ],
+        };
+        let syslog = Syslog::new(Json::default()).with_message_field("none");
+        assert_eq!(
+            syslog.try_fmt_record(&record).unwrap(),
+            r#"<13>1 - - - - - [fields@32473 http.status="200" http.status_2="404"]"#
+        );
+    }
+
+    #[test]
+    fn test_pri_mapping() {
+        let record = |level: DataField| DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(level),
+                FieldStorage::from_owned(DataField::from_chars("facility", "LOCAL7")),
+            ],
+        };
+        let syslog = Syslog::new(Json::default())
+            .with_severity_field("level")
+            .with_facility_field("facility")
+            .with_message_field("none");
+        assert!(
+            syslog
+                .fmt_record(&record(DataField::from_chars("level", "warn")))
+                .starts_with("<188>1 ")
+        );
+        assert!(
+            syslog
+                .fmt_record(&record(DataField::from_digit("level", 7)))
+                .starts_with("<191>1 ")
+        );
+        // 无法识别时使用固定级别
+        assert!(
+            syslog
+                .fmt_record(&record(DataField::from_digit("level", 9)))
+                .starts_with("<189>1 ")
+        );
+
+        let facility = |name: &str| {
+            let record = DataRecord {
+                id: Default::default(),
+                items: vec![FieldStorage::from_owned(DataField::from_chars(
+                    "facility", name,
+                ))],
+            };
+            syslog.fmt_record(&record)
+        };
+        // 代码 13 是 log audit，`security` 是 auth（4）的别名
+        assert!(facility("audit").starts_with("<109>1 "));
+        assert!(facility("security").starts_with("<37>1 "));
+
+        assert!(Syslog::new(Json::default()).with_facility(24).is_err());
+        assert!(Syslog::new(Json::default()).with_severity(8).is_err());
+        assert!(Syslog::new(Json::default()).with_sd_id("a b").is_err());
+        assert!(Syslog::new(Json::default()).with_sd_id("meta@1").is_ok());
+    }
+
+    #[test]
+    fn test_param_name() {
+        assert_eq!(param_name("http.status"), "http.status");
+        assert_eq!(param_name("héllo wörld"), "h_llo_w_rld");
+        assert_eq!(param_name("a=b]\"c"), "a_b__c");
+        assert_eq!(param_name(&"日".repeat(40)), "_".repeat(32));
+        assert_eq!(param_name(""), "_");
+    }
+
+    #[test]
+    fn test_time_field_type() {
+        let err = make_syslog()
+            .with_time_field("pid")
+            .try_fmt_record(&make_record())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            FormatError::UnsupportedType {
+                format: "syslog",
+                type_name: "digit"
+            }
+        ));
+    }
+}
@@ -0,0 +1,180 @@
+use std::fmt;
+
+use chrono::format::{Item, StrftimeItems};
+use wp_model_core::model::{DataRecord, Value};
+
+use crate::error::{ConfigError, FormatError};
+use crate::projected::lookup;
+
+/// 引用字段值的文本模板，如 `logs-{service}-{ts:%Y.%m.%d}`
+///
+/// `{path}` 按 `Projected` 的路径规则查找字段，`{path:pattern}` 对时间字段使用
+/// strftime 模式；`{{` 和 `}}` 输出字面括号。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Field {
+        path: String,
+        pattern: Option<String>,
+    },
+}
+
+impl Template {
+    /// 解析模板，`kind` 和 `option` 用于错误信息
+    pub(crate) fn parse(
+        source: &str,
+        kind: &'static str,
+        option: &'static str,
+    ) -> Result<Self, ConfigError> {
+        let invalid = |reason: String| ConfigError::InvalidOption {
+            kind,
+            option,
+            reason,
+        };
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = source.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut spec = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => spec.push(c),
+                            None => return Err(invalid(format!("unclosed `{{` in {:?}", source))),
+                        }
+                    }
+                    let (path, pattern) = match spec.split_once(':') {
+                        Some((path, pattern)) => (path, Some(pattern.to_string())),
+                        None => (spec.as_str(), None),
+                    };
+                    if path.is_empty() {
+                        return Err(invalid(format!("empty field name in {:?}", source)));
+                    }
+                    if let Some(pattern) = &pattern
+                        && StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error))
+                    {
+                        return Err(invalid(format!("invalid strftime pattern {:?}", pattern)));
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field {
+                        path: path.to_string(),
+                        pattern,
+                    });
+                }
+                '}' => return Err(invalid(format!("unmatched `}}` in {:?}", source))),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Self { parts })
+    }
+
+    /// 模板中的字面文本片段，不含字段引用
+    pub(crate) fn literals(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|part| match part {
+            Part::Text(text) => Some(text.as_str()),
+            Part::Field { .. } => None,
+        })
+    }
+
+    /// 写出模板文本；字段缺失或为空值时返回 `FormatError::MissingField`
+    pub(crate) fn write(
+        &self,
+        record: &DataRecord,
+        format: &'static str,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => out.write_str(text)?,
+                Part::Field { path, pattern } => {
+                    let value = lookup(record, path)
+                        .map(|f| f.get_value())
+                        .filter(|v| !matches!(v, Value::Null | Value::Ignore(_)))
+                        .ok_or_else(|| FormatError::MissingField {
+                            format,
+                            name: path.clone(),
+                        })?;
+                    match (value, pattern) {
+                        (Value::Time(t), Some(pattern)) => write!(out, "{}", t.format(pattern))?,
+                        (Value::Chars(s), _) => out.write_str(s)?,
+                        (value, _) => write!(out, "{}", value)?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn render(
+        &self,
+        record: &DataRecord,
+        format: &'static str,
+    ) -> Result<String, FormatError> {
+        let mut out = String::new();
+        self.write(record, format, &mut out)?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wp_model_core::model::{DataField, FieldStorage};
+
+    fn make_record() -> DataRecord {
+        let ts = chrono::NaiveDateTime::parse_from_str("2024-01-15 10:30:45", "%Y-%m-%d %H:%M:%S")
+            .unwrap();
+        DataRecord {
+            id: Default::default(),
+            items: vec![
+                FieldStorage::from_owned(DataField::from_chars("service", "web")),
+                FieldStorage::from_owned(DataField::from_time("ts", ts)),
+                FieldStorage::from_owned(DataField::from_digit("shard", 3)),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_template_render() {
+        let template =
+            Template::parse("logs-{service}-{ts:%Y.%m.%d}-{shard}{{x}}", "t", "o").unwrap();
+        assert_eq!(
+            template.render(&make_record(), "t").unwrap(),
+            "logs-web-2024.01.15-3{x}"
+        );
+        let plain = Template::parse("logs", "t", "o").unwrap();
+        assert_eq!(plain.render(&make_record(), "t").unwrap(), "logs");
+    }
+
+    #[test]
+    fn test_template_errors() {
+        assert!(Template::parse("logs-{service", "t", "o").is_err());
+        assert!(Template::parse("logs-}", "t", "o").is_err());
+        assert!(Template::parse("logs-{}", "t", "o").is_err());
+        assert!(Template::parse("{ts:%Q}", "t", "o").is_err());
+
+        let template = Template::parse("logs-{missing}", "t", "o").unwrap();
+        let err = template.render(&make_record(), "t").unwrap_err();
+        assert!(matches!(err, FormatError::MissingField { ref name, .. } if name == "missing"));
+    }
+}
@@ -0,0 +1,312 @@
+use std::fmt;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, SecondsFormat};
+
+use crate::error::{ConfigError, FormatError};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+enum Style {
+    #[default]
+    Native,
+    Rfc3339,
+    EpochSeconds,
+    EpochMillis,
+    EpochNanos,
+    Custom(String),
+}
+
+/// `Value::Time` 的输出方式，由所有格式化器共享
+///
+/// `Value::Time` 是不带时区的 `NaiveDateTime`。`with_offset` 指定它所在的时区，
+/// RFC3339、epoch 和自定义格式都会按该时区换算为确定的时刻；未指定时视为 UTC。
+///
+/// - `native()`：默认值，与 `NaiveDateTime` 的 `Display` 一致，如 `2019-08-06 12:12:19`，忽略时区
+/// - `rfc3339()`：如 `2019-08-06T12:12:19+08:00`，未指定时区时以 `Z` 结尾
+/// - `epoch_seconds()` / `epoch_millis()` / `epoch_nanos()`：Unix 时间戳，作为数字输出
+/// - `custom(pattern)`：chrono strftime 模式，`%z` 等时区占位符使用上述时区
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeFormat {
+    style: Style,
+    offset: Option<FixedOffset>,
+}
+
+impl Default for TimeFormat {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl TimeFormat {
+    pub(crate) const DEFAULT: Self = Self {
+        style: Style::Native,
+        offset: None,
+    };
+
+    pub fn native() -> Self {
+        Self::default()
+    }
+
+    pub fn rfc3339() -> Self {
+        Self::with_style(Style::Rfc3339)
+    }
+
+    pub fn epoch_seconds() -> Self {
+        Self::with_style(Style::EpochSeconds)
+    }
+
+    pub fn epoch_millis() -> Self {
+        Self::with_style(Style::EpochMillis)
+    }
+
+    pub fn epoch_nanos() -> Self {
+        Self::with_style(Style::EpochNanos)
+    }
+
+    /// 自定义 strftime 模式，模式不合法时返回错误
+    pub fn custom(pattern: impl Into<String>) -> Result<Self, ConfigError> {
+        let pattern = pattern.into();
+        if StrftimeItems::new(&pattern).any(|item| matches!(item, Item::Error)) {
+            return Err(ConfigError::InvalidOption {
+                kind: "time",
+                option: "pattern",
+                reason: format!("invalid strftime pattern {:?}", pattern),
+            });
+        }
+        Ok(Self::with_style(Style::Custom(pattern)))
+    }
+
+    /// 指定 `Value::Time` 所在的时区
+    pub fn with_offset(mut self, offset: FixedOffset) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub fn offset(&self) -> Option<FixedOffset> {
+        self.offset
+    }
+
+    /// 是否输出为数字（epoch 系列），数字不需要加引号
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self.style,
+            Style::EpochSeconds | Style::EpochMillis | Style::EpochNanos
+        )
+    }
+
+    /// 按当前策略渲染时间，无法表示时返回空字符串
+    pub fn format(&self, value: &NaiveDateTime) -> String {
+        self.try_format(value).unwrap_or_default()
+    }
+
+    /// 按当前策略渲染时间；本地时间加上时区偏移后超出 chrono 的表示范围时返回
+    /// `FormatError::Unrepresentable`
+    pub fn try_format(&self, value: &NaiveDateTime) -> Result<String, FormatError> {
+        let mut out = String::new();
+        self.write(value, &mut out)?;
+        Ok(out)
+    }
+
+    /// 写出时间的裸文本或数字
+    pub(crate) fn write(
+        &self,
+        value: &NaiveDateTime,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        match &self.style {
+            Style::Native => write!(out, "{}", value)?,
+            Style::Rfc3339 => out.write_str(
+                &self
+                    .zoned(value)?
+                    .to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            )?,
+            Style::EpochSeconds => write!(out, "{}", self.zoned(value)?.timestamp())?,
+            Style::EpochMillis => write!(out, "{}", self.zoned(value)?.timestamp_millis())?,
+            Style::EpochNanos => {
+                // 用 i128 计算，避免超出 i64 纳秒范围（约 1677~2262 年）
+                let zoned = self.zoned(value)?;
+                let nanos = i128::from(zoned.timestamp()) * 1_000_000_000
+                    + i128::from(zoned.timestamp_subsec_nanos());
+                write!(out, "{}", nanos)?
+            }
+            Style::Custom(pattern) => write!(out, "{}", self.zoned(value)?.format(pattern))?,
+        }
+        Ok(())
+    }
+
+    /// 带小数的 epoch 秒，最多保留微秒，如 `1565093539.25`；按 `with_offset` 的时区换算
+    pub(crate) fn write_epoch_fraction(
+        &self,
+        value: &NaiveDateTime,
+        out: &mut dyn fmt::Write,
+    ) -> Result<(), FormatError> {
+        let micros = self.zoned(value)?.timestamp_micros();
+        let sign = if micros < 0 { "-" } else { "" };
+        let (secs, frac) = (
+            micros.unsigned_abs() / 1_000_000,
+            micros.unsigned_abs() % 1_000_000,
+        );
+        if frac == 0 {
+            write!(out, "{}{}", sign, secs)?;
+        } else {
+            let frac = format!("{:06}", frac);
+            write!(out, "{}{}.{}", sign, secs, frac.trim_end_matches('0'))?;
+        }
+        Ok(())
+    }
+
+    /// 数字直接写出；文本交给 `quote` 按目标格式加引号并转义
+    pub(crate) fn write_quoted(
+        &self,
+        value: &NaiveDateTime,
+        out: &mut dyn fmt::Write,
+        quote: impl FnOnce(&str, &mut dyn fmt::Write) -> fmt::Result,
+    ) -> Result<(), FormatError> {
+        if self.is_numeric() {
+            self.write(value, out)
+        } else {
+            Ok(quote(&self.try_format(value)?, out)?)
+        }
+    }
+
+    fn with_style(style: Style) -> Self {
+        Self {
+            style,
+            offset: None,
+        }
+    }
+
+    fn zoned(&self, value: &NaiveDateTime) -> Result<DateTime<FixedOffset>, FormatError> {
+        let offset = self.offset.unwrap_or(FixedOffset::east_opt(0).unwrap());
+        // 固定偏移下本地时间与时刻一一对应，只有靠近 NaiveDateTime::MIN/MAX 时换算会越界
+        value
+            .and_local_timezone(offset)
+            .single()
+            .ok_or_else(|| FormatError::Unrepresentable {
+                format: "time",
+                detail: format!("{} at offset {} is out of range", value, offset),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dt(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f").unwrap()
+    }
+
+    #[test]
+    fn test_native() {
+        let t = dt("2019-08-06 12:12:19");
+        assert_eq!(TimeFormat::native().format(&t), "2019-08-06 12:12:19");
+        let east8 = FixedOffset::east_opt(8 * 3600).unwrap();
+        assert_eq!(
+            TimeFormat::native().with_offset(east8).format(&t),
+            "2019-08-06 12:12:19"
+        );
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        let t = dt("2019-08-06 12:12:19");
+        assert_eq!(TimeFormat::rfc3339().format(&t), "2019-08-06T12:12:19Z");
+        let east8 = FixedOffset::east_opt(8 * 3600).unwrap();
+        assert_eq!(
+            TimeFormat::rfc3339().with_offset(east8).format(&t),
+            "2019-08-06T12:12:19+08:00"
+        );
+        let t = dt("2019-08-06 12:12:19.250");
+        assert_eq!(TimeFormat::rfc3339().format(&t), "2019-08-06T12:12:19.250Z");
+    }
+
+    #[test]
+    fn test_epoch() {
+        let t = dt("2019-08-06 12:12:19.250");
+        assert_eq!(TimeFormat::epoch_seconds().format(&t), "1565093539");
+        assert_eq!(TimeFormat::epoch_millis().format(&t), "1565093539250");
+        assert_eq!(TimeFormat::epoch_nanos().format(&t), "1565093539250000000");
+        // 东八区的本地时间比 UTC 早 8 小时
+        let east8 = FixedOffset::east_opt(8 * 3600).unwrap();
+        assert_eq!(
+            TimeFormat::epoch_seconds().with_offset(east8).format(&t),
+            "1565064739"
+        );
+        assert!(TimeFormat::epoch_millis().is_numeric());
+        assert!(!TimeFormat::rfc3339().is_numeric());
+    }
+
+    #[test]
+    fn test_epoch_nanos_out_of_i64_range() {
+        let t = dt("2300-01-01 00:00:00");
+        assert_eq!(TimeFormat::epoch_nanos().format(&t), "10413792000000000000");
+    }
+
+    #[test]
+    fn test_offset_out_of_range() {
+        let east8 = FixedOffset::east_opt(8 * 3600).unwrap();
+        let west5 = FixedOffset::west_opt(5 * 3600).unwrap();
+        let tf = TimeFormat::rfc3339().with_offset(east8);
+        assert!(matches!(
+            tf.try_format(&NaiveDateTime::MIN),
+            Err(FormatError::Unrepresentable { format: "time", .. })
+        ));
+        assert_eq!(tf.format(&NaiveDateTime::MIN), "");
+        assert!(
+            TimeFormat::epoch_nanos()
+                .with_offset(west5)
+                .try_format(&NaiveDateTime::MAX)
+                .is_err()
+        );
+        // 不涉及时区换算的格式不受影响
+        assert!(
+            TimeFormat::native()
+                .with_offset(east8)
+                .try_format(&NaiveDateTime::MIN)
+                .is_ok()
+        );
+        assert!(tf.try_format(&NaiveDateTime::MAX).is_ok());
+    }
+
+    #[test]
+    fn test_custom() {
+        let t = dt("2019-08-06 12:12:19");
+        let west5 = FixedOffset::west_opt(5 * 3600).unwrap();
+        let tf = TimeFormat::custom("%d/%b/%Y:%H:%M:%S %z")
+            .unwrap()
+            .with_offset(west5);
+        assert_eq!(tf.format(&t), "06/Aug/2019:12:12:19 -0500");
+        assert!(matches!(
+            TimeFormat::custom("%Y-%Q"),
+            Err(ConfigError::InvalidOption {
+                option: "pattern",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_epoch_fraction() {
+        let render = |format: TimeFormat, t: &NaiveDateTime| {
+            let mut out = String::new();
+            format.write_epoch_fraction(t, &mut out).unwrap();
+            out
+        };
+        let t = dt("2019-08-06 12:12:19.250");
+        assert_eq!(render(TimeFormat::native(), &t), "1565093539.25");
+        assert_eq!(
+            render(TimeFormat::native(), &dt("2019-08-06 12:12:19")),
+            "1565093539"
+        );
+        let east8 = FixedOffset::east_opt(8 * 3600).unwrap();
+        assert_eq!(
+            render(TimeFormat::native().with_offset(east8), &t),
+            "1565064739.25"
+        );
+        assert_eq!(
+            render(TimeFormat::native(), &dt("1969-12-31 23:59:58.5")),
+            "-1.5"
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use wp_model_core::model::{DataRecord, DataType, Value};
+
+use crate::error::FormatError;
+use crate::flatten::Collision;
+
+/// 按分隔符把字段名还原为嵌套 JSON 对象的策略
+///
+/// `http/request` 和 `http/status` 会合并为 `{"http":{"request":..,"status":..}}`，
+/// 并与同名的 `Value::Obj` 字段合并。同一路径上两个值无法合并时（标量与对象、
+/// 或两个标量）按 `Collision` 处理：
+///
+/// - `Error`（默认）：返回 `FormatError::DuplicateField`
+/// - `KeepFirst` / `KeepLast`：保留先出现或后出现的值。冲突以整个节点为单位处理：
+///   `http/request`、`http`、`http/status` 依次出现时，`KeepLast` 下标量 `http`
+///   会替换已合并的整个 `http` 子树，随后 `http/status` 又替换该标量，结果只剩
+///   `{"http":{"status":..}}`
+/// - `Suffix`：后出现的值改用 `name_2` 等键名，与先出现的值并存
+///
+/// ```
+/// use wp_data_fmt::{Collision, Json, Unflatten};
+///
+/// let json = Json::default().with_unflatten(Unflatten::new().with_collision(Collision::KeepLast));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unflatten {
+    delimiter: String,
+    collision: Collision,
+}
+
+impl Default for Unflatten {
+    fn default() -> Self {
+        Self {
+            delimiter: "/".to_string(),
+            collision: Collision::default(),
+        }
+    }
+}
+
+/// 还原后的对象树，键按首次出现的顺序排列，并按键名索引
+#[derive(Default)]
+pub(crate) struct Nodes<'a> {
+    entries: Vec<(String, Node<'a>)>,
+    index: HashMap<String, usize>,
+}
+
+impl<'a> Nodes<'a> {
+    fn position(&self, key: &str) -> Option<usize> {
+        self.index.get(key).copied()
+    }
+
+    fn push(&mut self, key: String, node: Node<'a>) {
+        self.index.insert(key.clone(), self.entries.len());
+        self.entries.push((key, node));
+    }
+}
+
+impl<'a> Deref for Nodes<'a> {
+    type Target = [(String, Node<'a>)];
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl<'a> FromIterator<(String, Node<'a>)> for Nodes<'a> {
+    fn from_iter<I: IntoIterator<Item = (String, Node<'a>)>>(iter: I) -> Self {
+        let mut nodes = Nodes::default();
+        for (key, node) in iter {
+            nodes.push(key, node);
+        }
+        nodes
+    }
+}
+
+pub(crate) enum Node<'a> {
+    /// 原始值，可能是尚未展开的 `Value::Obj`
+    Leaf(&'a Value),
+    /// 由多个字段合并出的对象
+    Branch(Nodes<'a>),
+}
+
+impl Unflatten {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 字段名的路径分隔符，默认 `/`
+    pub fn with_delimiter(mut self, delimiter: impl Into<String>) -> Self {
+        self.delimiter = delimiter.into();
+        self
+    }
+
+    pub fn with_collision(mut self, collision: Collision) -> Self {
+        self.collision = collision;
+        self
+    }
+
+    /// 构建对象树；`skip` 为 true 的值（如按策略省略的空值）不参与合并
+    pub(crate) fn build<'a>(
+        &self,
+        record: &'a DataRecord,
+        skip: impl Fn(&Value) -> bool,
+    ) -> Result<Nodes<'a>, FormatError> {
+        let mut nodes = Nodes::default();
+        for field in record
+            .items
+            .iter()
+            .filter(|f| *f.get_meta() != DataType::Ignore && !skip(f.get_value()))
+        {
+            let name = field.get_name();
+            let path: Vec<&str> = if self.delimiter.is_empty() {
+                vec![name]
+            } else {
+                name.split(self.delimiter.as_str()).collect()
+            };
+            self.insert(&mut nodes, &path, "", field.get_value(), &skip)?;
+        }
+        Ok(nodes)
+    }
+
+    fn insert<'a>(
+        &self,
+        nodes: &mut Nodes<'a>,
+        path: &[&str],
+        prefix: &str,
+        value: &'a Value,
+        skip: &impl Fn(&Value) -> bool,
+    ) -> Result<(), FormatError> {
+        let Some((key, rest)) = path.split_first() else {
+            return Ok(());
+        };
+        let full = if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}{}{}", prefix, self.delimiter, key)
+        };
+        let Some(pos) = nodes.position(key) else {
+            let node = self.subtree(rest, &full, value, skip)?;
+            nodes.push(key.to_string(), node);
+            return Ok(());
+        };
+        let mergeable = if rest.is_empty() {
+            matches!(value, Value::Obj(_))
+        } else {
+            true
+        };
+        if mergeable && let Some(children) = expand(&mut nodes.entries[pos].1, skip) {
+            return match (rest.is_empty(), value) {
+                (true, Value::Obj(obj)) => {
+                    for (k, child) in obj.iter() {
+                        if *child.get_meta() != DataType::Ignore && !skip(child.get_value()) {
+                            self.insert(children, &[k.as_str()], &full, child.get_value(), skip)?;
+                        }
+                    }
+                    Ok(())
+                }
+                _ => self.insert(children, rest, &full, value, skip),
+            };
+        }
+        match self.collision {
+            Collision::Error => Err(FormatError::DuplicateField { name: full }),
+            Collision::KeepFirst => Ok(()),
+            Collision::KeepLast => {
+                nodes.entries[pos].1 = self.subtree(rest, &full, value, skip)?;
+                Ok(())
+            }
+            Collision::Suffix => {
+                let suffixed = (2..)
+                    .map(|n| format!("{}_{}", key, n))
+                    .find(|candidate| nodes.position(candidate).is_none())
+                    .unwrap_or_default();
+                let node = self.subtree(rest, &full, value, skip)?;
+                nodes.push(suffixed, node);
+                Ok(())
+            }
+        }
+    }
+
+    /// 为剩余路径构建新节点
+    fn subtree<'a>(
+        &self,
+        rest: &[&str],
+        prefix: &str,
+        value: &'a Value,
+        skip: &impl Fn(&Value) -> bool,
+    ) -> Result<Node<'a>, FormatError> {
+        if rest.is_empty() {
+            return Ok(Node::Leaf(value));
+        }
+        let mut children = Nodes::default();
+        self.insert(&mut children, rest, prefix, value, skip)?;
+        Ok(Node::Branch(children))
+    }
+}
+
+/// 节点可容纳子键时返回其子节点列表，未展开的 `Value::Obj` 会先展开
+fn expand<'n, 'a>(
+    node: &'n mut Node<'a>,
+    skip: &impl Fn(&Value) -> bool,
+) -> Option<&'n mut Nodes<'a>> {
+    if let Node::Leaf(Value::Obj(obj)) = node {
+        let children = obj
+            .iter()
+            .filter(|(_, f)| *f.get_meta() != DataType::Ignore && !skip(f.get_value()))
+            .map(|(k, f)| (k.to_string(), Node::Leaf(f.get_value())))
+            .collect();
+        *node = Node::Branch(children);
+    }
+    match node {
+        Node::Branch(children) => Some(children),
+        Node::Leaf(_) => None,
+    }
+}
@@ -1,5 +1,5 @@
 // 演示新 API 的使用示例
-use wp_data_fmt::{Csv, Json, KeyValue, RecordFormatter, ValueFormatter};
+use wp_data_fmt::{Csv, FormatError, Json, KeyValue, RecordFormatter, ValueFormatter};
 use wp_model_core::model::{DataField, DataRecord, FieldStorage, Value};
 
 #[test]
@@ -101,3 +101,486 @@ fn test_new_api_object_formatting() {
     assert!(result.contains("\"x\":10"));
     assert!(result.contains("\"y\":20"));
 }
+
+#[test]
+fn test_new_api_streaming_writer() {
+    use wp_data_fmt::{FormatType, RecordWriter, ValueWriter};
+    use wp_model_core::model::fmt_def::TextFmt;
+
+    let record = DataRecord {
+        id: Default::default(),
+        items: vec![
+            FieldStorage::from_owned(DataField::from_chars("name", "Alice")),
+            FieldStorage::from_owned(DataField::from_digit("age", 30)),
+        ],
+    };
+
+    // 写入 io::Write（如文件、socket），与 fmt_record 输出一致
+    let json = Json::default();
+    let mut buf: Vec<u8> = Vec::new();
+    json.write_record(&record, &mut buf).unwrap();
+    assert_eq!(String::from_utf8(buf).unwrap(), json.fmt_record(&record));
+
+    // 多条记录追加写入同一个 fmt::Write 缓冲区
+    let csv = FormatType::from(&TextFmt::Csv);
+    let mut out = String::new();
+    csv.write_record_fmt(&record, &mut out).unwrap();
+    out.push('\n');
+    csv.write_record_fmt(&record, &mut out).unwrap();
+    assert_eq!(out, "Alice,30\nAlice,30");
+
+    let mut out = String::new();
+    KeyValue::default()
+        .write_value(&Value::Chars("hi".into()), &mut out)
+        .unwrap();
+    assert_eq!(out, "\"hi\"");
+}
+
+#[test]
+fn test_new_api_streaming_writer_io_error() {
+    use std::io;
+    use wp_data_fmt::RecordWriter;
+
+    struct Broken;
+    impl io::Write for Broken {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let record = DataRecord {
+        id: Default::default(),
+        items: vec![FieldStorage::from_owned(DataField::from_digit("n", 1))],
+    };
+    let err = Csv::default()
+        .write_record(&record, &mut Broken)
+        .unwrap_err();
+    match err {
+        FormatError::Io(e) => assert_eq!(e.kind(), io::ErrorKind::BrokenPipe),
+        other => panic!("unexpected error: {other}"),
+    }
+}
+
+#[test]
+fn test_new_api_strict_mode() {
+    let record = DataRecord {
+        id: Default::default(),
+        items: vec![
+            FieldStorage::from_owned(DataField::from_chars("name", "Alice")),
+            FieldStorage::from_owned(DataField::from_float("score", f64::INFINITY)),
+        ],
+    };
+
+    // 默认宽松：无穷大被替换为字符串
+    let lenient = Json::default();
+    assert_eq!(
+        lenient.fmt_record(&record),
+        r#"{"name":"Alice","score":"Infinity"}"#
+    );
+    assert!(lenient.try_fmt_record(&record).is_ok());
+
+    // 严格模式：返回错误而不是替换
+    let strict = Json::default().with_strict(true);
+    let err = strict.try_fmt_record(&record).unwrap_err();
+    assert!(matches!(err, FormatError::Unrepresentable { .. }));
+    assert_eq!(
+        err.to_string(),
+        "json: cannot represent non-finite float inf"
+    );
+}
+
+#[test]
+fn test_new_api_formatter_registry() {
+    use wp_data_fmt::{FormatType, FormatterRegistry};
+
+    // 团队内部的管道分隔格式
+    struct Pipe;
+    impl ValueFormatter for Pipe {
+        type Output = String;
+        fn format_value(&self, value: &Value) -> String {
+            value.to_string()
+        }
+    }
+    impl RecordFormatter for Pipe {
+        fn fmt_record(&self, record: &DataRecord) -> String {
+            let parts: Vec<String> = record.items.iter().map(|f| self.fmt_field(f)).collect();
+            parts.join("|")
+        }
+    }
+
+    let mut registry = FormatterRegistry::new();
+    registry.register("pipe", || Box::new(Pipe));
+
+    let record = DataRecord {
+        id: Default::default(),
+        items: vec![
+            FieldStorage::from_owned(DataField::from_chars("name", "Alice")),
+            FieldStorage::from_owned(DataField::from_digit("age", 30)),
+        ],
+    };
+
+    let fmt: FormatType = registry.format_type("pipe").unwrap();
+    assert_eq!(fmt.fmt_record(&record), "Alice|30");
+
+    // 内置格式也可以按名称获取
+    let csv = registry.format_type("csv").unwrap();
+    assert_eq!(csv.fmt_record(&record), "Alice,30");
+}
+
+#[test]
+fn test_new_api_formatter_config_toml() {
+    use serde::Deserialize;
+    use wp_data_fmt::{FormatType, FormatterConfig};
+
+    #[derive(Deserialize)]
+    struct SinkConfig {
+        format: FormatterConfig,
+    }
+
+    let record = DataRecord {
+        id: Default::default(),
+        items: vec![
+            FieldStorage::from_owned(DataField::from_chars("host", "web;01")),
+            FieldStorage::from_owned(DataField::from_digit("status", 200)),
+        ],
+    };
+
+    let cfg: SinkConfig =
+        toml::from_str(r#"format = { kind = "csv", delimiter = ";", quote = "'" }"#).unwrap();
+    let fmt: FormatType = cfg.format.build().unwrap();
+    assert_eq!(fmt.fmt_record(&record), "'web;01';200");
+
+    let cfg: SinkConfig =
+        toml::from_str(r#"format = { kind = "sql", table = "nginx", dialect = "mysql" }"#).unwrap();
+    let fmt = FormatType::try_from(&cfg.format).unwrap();
+    assert_eq!(
+        fmt.fmt_record(&record),
+        "INSERT INTO `nginx` (`host`, `status`) VALUES ('web;01', 200);"
+    );
+
+    // 非法选项给出明确的错误信息
+    let cfg: SinkConfig = toml::from_str(r#"format = { kind = "csv", quote = "" }"#).unwrap();
+    match cfg.format.build() {
+        Err(e) => assert_eq!(
+            e.to_string(),
+            r#"csv: invalid `quote`: expected a single character, got """#
+        ),
+        Ok(_) => panic!("empty quote should be rejected"),
+    }
+}
+
+#[test]
+fn test_new_api_time_format() {
+    use chrono::{FixedOffset, NaiveDateTime};
+    use wp_data_fmt::{ProtoTxt, Raw, SqlInsert, TimeFormat};
+
+    let t = NaiveDateTime::parse_from_str("2019-08-06 12:12:19", "%Y-%m-%d %H:%M:%S").unwrap();
+    let record = DataRecord {
+        id: Default::default(),
+        items: vec![FieldStorage::from_owned(DataField::from_time("ts", t))],
+    };
+    let east8 = FixedOffset::east_opt(8 * 3600).unwrap();
+    let rfc = TimeFormat::rfc3339().with_offset(east8);
+    let millis = TimeFormat::epoch_millis().with_offset(east8);
+
+    // 默认保持原有输出
+    assert_eq!(
+        Json::default().fmt_record(&record),
+        r#"{"ts":"2019-08-06 12:12:19"}"#
+    );
+
+    let json = Json::default().with_time_format(rfc.clone());
+    assert_eq!(
+        json.fmt_record(&record),
+        r#"{"ts":"2019-08-06T12:12:19+08:00"}"#
+    );
+    // epoch 以数字输出，不加引号
+    let json = Json::default().with_time_format(millis.clone());
+    assert_eq!(json.fmt_record(&record), r#"{"ts":1565064739000}"#);
+
+    let csv = Csv::default().with_time_format(millis.clone());
+    assert_eq!(csv.fmt_record(&record), "1565064739000");
+    let kv = KeyValue::default().with_time_format(rfc.clone());
+    assert_eq!(kv.fmt_record(&record), "ts: 2019-08-06T12:12:19+08:00");
+    let raw = Raw::default().with_time_format(TimeFormat::epoch_seconds());
+    assert_eq!(raw.fmt_record(&record), "1565093539");
+    let proto = ProtoTxt::default().with_time_format(rfc.clone());
+    assert_eq!(
+        proto.fmt_record(&record),
+        r#"{ ts: "2019-08-06T12:12:19+08:00" }"#
+    );
+    let sql = SqlInsert::new_with_json("t").with_time_format(rfc);
+    assert_eq!(
+        sql.fmt_record(&record),
+        r#"INSERT INTO "t" ("ts") VALUES ('2019-08-06T12:12:19+08:00');"#
+    );
+
+    // 自定义模式中的分隔符会按 CSV 规则转义
+    let csv = Csv::default().with_time_format(TimeFormat::custom("%Y,%m").unwrap());
+    assert_eq!(csv.fmt_record(&record), "\"2019,08\"");
+}
+
+#[test]
+fn test_new_api_float_policy() {
+    use wp_data_fmt::{FloatPolicy, FormatError, NonFinite, Raw, SqlInsert};
+
+    let record = DataRecord {
+        id: Default::default(),
+        items: vec![
+            FieldStorage::from_owned(DataField::from_float("ratio", 0.123456)),
+            FieldStorage::from_owned(DataField::from_float("bad", f64::NAN)),
+            FieldStorage::from_owned(DataField::from_digit("n", 1)),
+        ],
+    };
+
+    let fixed = FloatPolicy::new().with_precision(2);
+    let json = Json::default().with_float_policy(fixed.with_non_finite(NonFinite::String));
+    assert_eq!(
+        json.fmt_record(&record),
+        r#"{"ratio":0.12,"bad":"NaN","n":1}"#
+    );
+
+    // Skip 省略字段；CSV 需要保持列对齐，输出空单元格
+    let skip = fixed.with_non_finite(NonFinite::Skip);
+    let json = Json::default().with_float_policy(skip);
+    assert_eq!(json.fmt_record(&record), r#"{"ratio":0.12,"n":1}"#);
+    let kv = KeyValue::default().with_float_policy(skip);
+    assert_eq!(kv.fmt_record(&record), "ratio: 0.12, n: 1");
+    let csv = Csv::default().with_float_policy(skip);
+    assert_eq!(csv.fmt_record(&record), "0.12,,1");
+
+    let null = FloatPolicy::new().with_non_finite(NonFinite::Null);
+    let sql = SqlInsert::new_with_json("t").with_float_policy(null);
+    assert_eq!(
+        sql.fmt_record(&record),
+        r#"INSERT INTO "t" ("ratio", "bad", "n") VALUES (0.123456, NULL, 1);"#
+    );
+    let raw = Raw::default().with_float_policy(null);
+    assert_eq!(raw.fmt_record(&record), "0.123456  1");
+
+    // Error 不依赖严格模式
+    let csv =
+        Csv::default().with_float_policy(FloatPolicy::new().with_non_finite(NonFinite::Error));
+    assert!(matches!(
+        csv.try_fmt_record(&record),
+        Err(FormatError::Unrepresentable { format: "csv", .. })
+    ));
+
+    // 默认策略保持各格式原有输出
+    assert_eq!(Csv::default().fmt_record(&record), "0.123456,NaN,1");
+}
+
+#[test]
+fn test_new_api_null_policy() {
+    use wp_data_fmt::{NullPolicy, ProtoTxt, SqlInsert};
+
+    let record = DataRecord {
+        id: Default::default(),
+        items: vec![
+            FieldStorage::from_owned(DataField::from_chars("host", "web01")),
+            FieldStorage::from_owned(DataField::new(
+                wp_model_core::model::DataType::default(),
+                "referer",
+                Value::Null,
+            )),
+            FieldStorage::from_owned(DataField::from_digit("status", 200)),
+        ],
+    };
+
+    // 默认输出原生空值
+    assert_eq!(
+        KeyValue::default().fmt_record(&record),
+        r#"host: "web01", referer: , status: 200"#
+    );
+
+    let omit = NullPolicy::Omit;
+    let kv = KeyValue::default().with_null_policy(omit.clone());
+    assert_eq!(kv.fmt_record(&record), r#"host: "web01", status: 200"#);
+    let json = Json::default().with_null_policy(omit.clone());
+    assert_eq!(json.fmt_record(&record), r#"{"host":"web01","status":200}"#);
+    let proto = ProtoTxt::default().with_null_policy(omit.clone());
+    assert_eq!(
+        proto.fmt_record(&record),
+        r#"{ host: "web01" status: 200 }"#
+    );
+    // CSV 无法省略列，保持对齐
+    let csv = Csv::default().with_null_policy(omit);
+    assert_eq!(csv.fmt_record(&record), "web01,,200");
+
+    let dash = NullPolicy::placeholder("-");
+    let csv = Csv::default().with_null_policy(dash.clone());
+    assert_eq!(csv.fmt_record(&record), "web01,-,200");
+    let kv = KeyValue::default()
+        .with_pair_separator(" ")
+        .with_key_value_separator("=")
+        .with_quote_strings(false)
+        .with_null_policy(dash.clone());
+    assert_eq!(kv.fmt_record(&record), "host=web01 referer=- status=200");
+    let json = Json::default().with_null_policy(NullPolicy::placeholder("\\N"));
+    assert_eq!(
+        json.fmt_record(&record),
+        r#"{"host":"web01","referer":"\\N","status":200}"#
+    );
+    let sql = SqlInsert::new_with_json("t").with_null_policy(dash);
+    assert!(
+        sql.fmt_record(&record)
+            .contains("VALUES ('web01', '-', 200)")
+    );
+}
+
+#[test]
+fn test_new_api_projected() {
+    use wp_data_fmt::{Projected, ProtoTxt, SqlInsert};
+    use wp_model_core::model::types::value::ObjectValue;
+
+    let mut http = ObjectValue::new();
+    http.insert(
+        "status".to_string(),
+        FieldStorage::from_owned(DataField::from_digit("status", 404)),
+    );
+    let record = DataRecord {
+        id: Default::default(),
+        items: vec![
+            FieldStorage::from_owned(DataField::from_chars("method", "GET")),
+            FieldStorage::from_owned(DataField::from_obj("http", http)),
+            FieldStorage::from_owned(DataField::from_chars("ip", "10.0.0.1")),
+        ],
+    };
+    let columns = [("ip", "client_ip"), ("http/status", "status")];
+
+    let json = Projected::new(Json::default(), columns);
+    assert_eq!(
+        json.fmt_record(&record),
+        r#"{"client_ip":"10.0.0.1","status":404}"#
+    );
+    let csv = Projected::new(Csv::default(), columns);
+    assert_eq!(csv.fmt_record(&record), "10.0.0.1,404");
+    let kv = Projected::new(KeyValue::default(), columns);
+    assert_eq!(
+        kv.fmt_record(&record),
+        r#"client_ip: "10.0.0.1", status: 404"#
+    );
+    let proto = Projected::new(ProtoTxt::default(), columns);
+    assert_eq!(
+        proto.fmt_record(&record),
+        r#"{ client_ip: "10.0.0.1" status: 404 }"#
+    );
+    let sql = Projected::new(SqlInsert::new_with_json("access"), columns);
+    assert_eq!(
+        sql.fmt_record(&record),
+        r#"INSERT INTO "access" ("client_ip", "status") VALUES ('10.0.0.1', 404);"#
+    );
+
+    // 缺失字段填充为 null，保持列对齐
+    let csv = Projected::new(Csv::default(), [("ip", "ip"), ("ua", "ua")]).with_fill_missing(true);
+    assert_eq!(csv.fmt_record(&record), "10.0.0.1,");
+}
+
+#[test]
+fn test_new_api_flatten() {
+    use wp_data_fmt::{Collision, Flatten, SqlInsert};
+    use wp_model_core::model::types::value::ObjectValue;
+
+    let mut extends = ObjectValue::new();
+    extends.insert(
+        "ssl_cipher".to_string(),
+        FieldStorage::from_owned(DataField::from_chars("ssl_cipher", "AES128")),
+    );
+    extends.insert(
+        "bytes".to_string(),
+        FieldStorage::from_owned(DataField::from_digit("bytes", 512)),
+    );
+    let record = DataRecord {
+        id: Default::default(),
+        items: vec![
+            FieldStorage::from_owned(DataField::from_chars("host", "web01")),
+            FieldStorage::from_owned(DataField::from_obj("extends", extends)),
+            FieldStorage::from_owned(DataField::from_arr(
+                "tags",
+                vec![DataField::from_chars("", "edge")],
+            )),
+        ],
+    };
+
+    // 默认仍输出一个 JSON 单元格
+    assert_eq!(
+        Csv::default().fmt_record(&record),
+        r#"web01,"{""bytes"":512,""ssl_cipher"":""AES128""}","[""edge""]""#
+    );
+    let csv = Csv::default().with_flatten(Flatten::new());
+    assert_eq!(csv.fmt_record(&record), "web01,512,AES128,edge");
+
+    let kv = KeyValue::default().with_flatten(Flatten::new().with_separator("/"));
+    assert_eq!(
+        kv.fmt_record(&record),
+        r#"host: "web01", extends/bytes: 512, extends/ssl_cipher: "AES128", tags[0]: "edge""#
+    );
+
+    let sql = SqlInsert::new_with_json("access").with_flatten(Flatten::new());
+    assert_eq!(
+        sql.fmt_record(&record),
+        r#"INSERT INTO "access" ("host", "extends.bytes", "extends.ssl_cipher", "tags[0]") VALUES ('web01', 512, 'AES128', 'edge');"#
+    );
+    assert!(
+        sql.generate_create_table(std::slice::from_ref(&record))
+            .contains(r#""extends.bytes" BIGINT"#)
+    );
+
+    // 展平后重名：默认报错，可改为追加后缀
+    let mut dup = record.clone();
+    dup.items
+        .push(FieldStorage::from_owned(DataField::from_digit(
+            "extends.bytes",
+            1,
+        )));
+    assert!(matches!(
+        csv.try_fmt_record(&dup),
+        Err(FormatError::DuplicateField { .. })
+    ));
+    let csv = Csv::default().with_flatten(Flatten::new().with_collision(Collision::Suffix));
+    assert_eq!(csv.fmt_record(&dup), "web01,512,AES128,edge,1");
+}
+
+#[test]
+fn test_new_api_unflatten() {
+    use wp_data_fmt::{Collision, Unflatten};
+
+    let record = DataRecord {
+        id: Default::default(),
+        items: vec![
+            FieldStorage::from_owned(DataField::from_chars("http/request", "GET /")),
+            FieldStorage::from_owned(DataField::from_digit("http/status", 200)),
+            FieldStorage::from_owned(DataField::from_chars("src/ip", "10.0.0.1")),
+        ],
+    };
+    assert_eq!(
+        Json::default().fmt_record(&record),
+        r#"{"http/request":"GET /","http/status":200,"src/ip":"10.0.0.1"}"#
+    );
+    let json = Json::default().with_unflatten(Unflatten::new());
+    assert_eq!(
+        json.fmt_record(&record),
+        r#"{"http":{"request":"GET /","status":200},"src":{"ip":"10.0.0.1"}}"#
+    );
+
+    // 标量与对象争用同一路径时按 Collision 处理
+    let mut conflict = record.clone();
+    conflict
+        .items
+        .push(FieldStorage::from_owned(DataField::from_chars(
+            "src", "edge",
+        )));
+    assert!(matches!(
+        json.try_fmt_record(&conflict),
+        Err(FormatError::DuplicateField { .. })
+    ));
+    let json =
+        Json::default().with_unflatten(Unflatten::new().with_collision(Collision::KeepFirst));
+    assert_eq!(
+        json.fmt_record(&conflict),
+        r#"{"http":{"request":"GET /","status":200},"src":{"ip":"10.0.0.1"}}"#
+    );
+}